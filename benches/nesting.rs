@@ -0,0 +1,234 @@
+#![feature(proc_macro, conservative_impl_trait, generators, test)]
+
+//! throughput of control impulses and probes through nested organelles
+//!
+//! run with `cargo bench --bench nesting` on the commit before and after a
+//! change to the runtime to compare them. the impulse benches only measure
+//! delivery down to the sink; impulses sent back up by a soma still pass
+//! through a forwarding task at each level.
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+#[macro_use]
+extern crate organelle_derive;
+extern crate test;
+extern crate tokio_core;
+extern crate uuid;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use futures::prelude::*;
+use futures::unsync::oneshot;
+use organelle::*;
+use test::Bencher;
+use tokio_core::reactor;
+use uuid::Uuid;
+
+/// number of synapses connected and disconnected per iteration
+const BATCH: usize = 100;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Synapse)]
+enum BenchSynapse {
+    #[synapse(channel = "mpsc", message = "u32")]
+    Data,
+}
+
+/// hands its context to the benchmark so it can drive the organelle
+struct Driver {
+    ctx: Rc<RefCell<Option<SomaContext<BenchSynapse>>>>,
+}
+
+impl Soma for Driver {
+    type Synapse = BenchSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(ctx) => {
+                *self.ctx.borrow_mut() = Some(ctx);
+
+                Ok(self)
+            },
+            Impulse::AddTerminal(_, BenchSynapse::Data, _)
+            | Impulse::RemoveTerminal(_, BenchSynapse::Data) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// counts the impulses that make it to the bottom of the organelle
+struct Sink {
+    impulses: Rc<Cell<usize>>,
+}
+
+impl Soma for Sink {
+    type Synapse = BenchSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_)
+            | Impulse::AddDendrite(_, BenchSynapse::Data, _)
+            | Impulse::RemoveDendrite(_, BenchSynapse::Data) => {
+                self.impulses.set(self.impulses.get() + 1);
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// how many organelles the sink is wrapped in
+#[derive(Debug, Copy, Clone)]
+enum Depth {
+    /// a single organelle
+    Shallow,
+    /// five nested organelles
+    Deep,
+}
+
+/// a running organelle with a sink at the bottom of its nuclei
+struct Bench {
+    core: reactor::Core,
+    ctx: SomaContext<BenchSynapse>,
+    driver: Uuid,
+    nucleus: Uuid,
+    impulses: Rc<Cell<usize>>,
+}
+
+impl Bench {
+    /// wrap the sink in organelles and start them
+    fn new(depth: Depth) -> Self {
+        let core = reactor::Core::new().unwrap();
+        let handle = core.handle();
+
+        let impulses = Rc::new(Cell::new(0));
+        let sink = Sink {
+            impulses: impulses.clone(),
+        };
+
+        match depth {
+            Depth::Shallow => {
+                Self::start(core, Organelle::new(sink, handle), impulses)
+            },
+            Depth::Deep => {
+                let nested = Organelle::new(
+                    Organelle::new(
+                        Organelle::new(
+                            Organelle::new(sink, handle.clone()),
+                            handle.clone(),
+                        ),
+                        handle.clone(),
+                    ),
+                    handle.clone(),
+                );
+
+                Self::start(core, Organelle::new(nested, handle), impulses)
+            },
+        }
+    }
+
+    fn start<T>(
+        mut core: reactor::Core,
+        mut organelle: Organelle<T>,
+        impulses: Rc<Cell<usize>>,
+    ) -> Self
+    where
+        T: Soma<Synapse = BenchSynapse> + 'static,
+    {
+        let handle = core.handle();
+        let ctx = Rc::new(RefCell::new(None));
+
+        let nucleus = organelle.nucleus();
+        let driver = organelle.add_soma(Driver { ctx: ctx.clone() });
+
+        handle.spawn(
+            organelle
+                .run(handle.clone())
+                .map_err(|e| panic!("organelle failed - {}", e)),
+        );
+
+        while ctx.borrow().is_none() || impulses.get() == 0 {
+            core.turn(None);
+        }
+
+        let ctx = ctx.borrow_mut().take().unwrap();
+
+        Self {
+            core: core,
+            ctx: ctx,
+            driver: driver,
+            nucleus: nucleus,
+            impulses: impulses,
+        }
+    }
+
+    /// send a batch of synapses down to the sink and wait for all of them
+    fn impulses(&mut self) {
+        let control = self.ctx.control::<BenchSynapse>().unwrap();
+        let expected = self.impulses.get() + 2 * BATCH;
+
+        for _ in 0..BATCH {
+            control
+                .connect(self.driver, self.nucleus, BenchSynapse::Data)
+                .unwrap();
+            control
+                .disconnect(self.driver, self.nucleus, BenchSynapse::Data)
+                .unwrap();
+        }
+
+        while self.impulses.get() < expected {
+            self.core.turn(None);
+        }
+    }
+
+    /// probe the whole organelle and wait for the response
+    fn probe(&mut self) -> SomaData {
+        let (tx, rx) = oneshot::channel();
+
+        let probe = self.ctx
+            .sender()
+            .send(Impulse::Probe(probe::Settings::new(), tx))
+            .map_err(|_| Error::from("unable to send probe"))
+            .and_then(|_| {
+                rx.map_err(|_| Error::from("unable to receive probe"))
+            });
+
+        self.core.run(probe).unwrap()
+    }
+}
+
+#[bench]
+fn bench_impulses_shallow(b: &mut Bencher) {
+    let mut bench = Bench::new(Depth::Shallow);
+
+    b.iter(|| bench.impulses());
+}
+
+#[bench]
+fn bench_impulses_deep(b: &mut Bencher) {
+    let mut bench = Bench::new(Depth::Deep);
+
+    b.iter(|| bench.impulses());
+}
+
+#[bench]
+fn bench_probe_shallow(b: &mut Bencher) {
+    let mut bench = Bench::new(Depth::Shallow);
+
+    b.iter(|| bench.probe());
+}
+
+#[bench]
+fn bench_probe_deep(b: &mut Bencher) {
+    let mut bench = Bench::new(Depth::Deep);
+
+    b.iter(|| bench.probe());
+}
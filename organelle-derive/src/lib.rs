@@ -0,0 +1,526 @@
+#![recursion_limit = "256"]
+
+//! custom derive for organelle synapses
+//!
+//! ```ignore
+//! #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Synapse)]
+//! #[synapse(parent = "OrganelleSynapse")]
+//! enum CounterSynapse {
+//!     #[synapse(channel = "mpsc", message = "()", buffer = 10)]
+//!     Increment,
+//!     #[synapse(channel = "oneshot", message = "u32")]
+//!     Total,
+//! }
+//! ```
+//!
+//! this generates `CounterTerminal` and `CounterDendrite` enums with one
+//! variant per synapse variant holding the sending and receiving halves of
+//! the channel, an implementation of `organelle::Synapse` that describes each
+//! variant to probes, conversions into `OrganelleSynapse`,
+//! `OrganelleTerminal`, and `OrganelleDendrite`, and a `Mirror` mapping from
+//! them. parent synapses that `CounterSynapse` does not have are rejected by
+//! the mapping rather than converted.
+//!
+//! the terminal and dendrite enums are named by replacing the `Synapse`
+//! suffix of the enum (or appending `Terminal` and `Dendrite` if there is no
+//! suffix). use `#[synapse(terminal = "...", dendrite = "...")]` to name them
+//! yourself. parents are expected to follow the default naming.
+//!
+//! organelle also derives `Describe` for its own probe data, generating the
+//! JSON schema of probe documents from the types and their `#[serde(...)]`
+//! attributes. the generated code refers to organelle's probe module by its
+//! path inside the crate, so it cannot be used elsewhere.
+
+extern crate proc_macro;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use quote::Tokens;
+use syn::{Data, DeriveInput, Fields, Ident, Lit, Meta, NestedMeta};
+
+/// derive `organelle::Synapse` along with its terminal and dendrite enums
+#[proc_macro_derive(Synapse, attributes(synapse))]
+pub fn derive_synapse(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = syn::parse(input).unwrap();
+
+    expand(&input).into()
+}
+
+/// derive the JSON schema of one of organelle's probe types
+#[proc_macro_derive(Describe, attributes(serde))]
+pub fn derive_describe(input: TokenStream) -> TokenStream {
+    let input: DeriveInput = syn::parse(input).unwrap();
+
+    expand_describe(&input).into()
+}
+
+/// the kind of channel formed by a synapse variant
+enum Channel {
+    Mpsc(usize),
+    Oneshot,
+}
+
+struct Variant {
+    ident: Ident,
+    message: syn::Type,
+    channel: Channel,
+}
+
+fn expand(input: &DeriveInput) -> Tokens {
+    let synapse = &input.ident;
+    let vis = &input.vis;
+
+    let mut terminal = Ident::from(sibling_name(synapse.as_ref(), "Terminal"));
+    let mut dendrite = Ident::from(sibling_name(synapse.as_ref(), "Dendrite"));
+    let mut parents = vec![];
+
+    for (name, value) in synapse_attrs(&input.attrs) {
+        match (name.as_ref(), value) {
+            ("terminal", Lit::Str(s)) => terminal = Ident::from(s.value()),
+            ("dendrite", Lit::Str(s)) => dendrite = Ident::from(s.value()),
+            ("parent", Lit::Str(s)) => parents.push(Ident::from(s.value())),
+            (name, _) => panic!("unknown synapse attribute `{}`", name),
+        }
+    }
+
+    let variants = match input.data {
+        Data::Enum(ref data) => data.variants
+            .iter()
+            .map(|v| parse_variant(synapse, v))
+            .collect::<Vec<_>>(),
+        _ => panic!("Synapse can only be derived for enums"),
+    };
+
+    let idents = variants.iter().map(|v| &v.ident).collect::<Vec<_>>();
+
+    let senders = variants.iter().map(|v| {
+        let message = &v.message;
+
+        match v.channel {
+            Channel::Mpsc(_) => {
+                quote! { ::organelle::export::mpsc::Sender<#message> }
+            },
+            Channel::Oneshot => {
+                quote! { ::organelle::export::oneshot::Sender<#message> }
+            },
+        }
+    });
+    let receivers = variants.iter().map(|v| {
+        let message = &v.message;
+
+        match v.channel {
+            Channel::Mpsc(_) => {
+                quote! { ::organelle::export::mpsc::Receiver<#message> }
+            },
+            Channel::Oneshot => {
+                quote! { ::organelle::export::oneshot::Receiver<#message> }
+            },
+        }
+    });
+    let channels = variants.iter().map(|v| {
+        let ident = &v.ident;
+
+        let channel = match v.channel {
+            Channel::Mpsc(buffer) => {
+                quote! { ::organelle::export::mpsc::channel(#buffer) }
+            },
+            Channel::Oneshot => {
+                quote! { ::organelle::export::oneshot::channel() }
+            },
+        };
+
+        quote! {
+            #synapse::#ident => {
+                let (tx, rx) = #channel;
+
+                (#terminal::#ident(tx), #dendrite::#ident(rx))
+            }
+        }
+    });
+
+    let data = variants.iter().map(|v| {
+        let variant = v.ident.as_ref();
+        let message = &v.message;
+        let message = quote!(#message).to_string();
+
+        let channel = match v.channel {
+            Channel::Mpsc(_) => "mpsc",
+            Channel::Oneshot => "oneshot",
+        };
+
+        quote! {
+            ::organelle::probe::VariantData {
+                variant: #variant.to_string(),
+                channel: #channel.to_string(),
+                message: #message.to_string(),
+            }
+        }
+    });
+
+    let mut impls = vec![];
+
+    for parent in &parents {
+        let parent_terminal =
+            Ident::from(sibling_name(parent.as_ref(), "Terminal"));
+        let parent_dendrite =
+            Ident::from(sibling_name(parent.as_ref(), "Dendrite"));
+
+        impls.push(into_parent(synapse, parent, &idents, false));
+        impls.push(into_parent(&terminal, &parent_terminal, &idents, true));
+        impls.push(into_parent(&dendrite, &parent_dendrite, &idents, true));
+
+        impls.push(mirror(
+            (synapse, &terminal, &dendrite),
+            (parent, &parent_terminal, &parent_dendrite),
+            &idents,
+        ));
+    }
+
+    let idents = &idents;
+
+    quote! {
+        #[derive(Debug)]
+        #vis enum #terminal {
+            #(#idents(#senders),)*
+        }
+
+        #[derive(Debug)]
+        #vis enum #dendrite {
+            #(#idents(#receivers),)*
+        }
+
+        impl ::organelle::Synapse for #synapse {
+            type Terminal = #terminal;
+            type Dendrite = #dendrite;
+
+            fn data() -> ::organelle::probe::SynapseData {
+                ::organelle::probe::SynapseData::new::<Self>(vec![
+                    #(#data,)*
+                ])
+            }
+
+            fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+                match self {
+                    #(#channels,)*
+                }
+            }
+        }
+
+        #(#impls)*
+    }
+}
+
+/// generate the conversion from a child enum into its parent
+fn into_parent(
+    child: &Ident,
+    parent: &Ident,
+    idents: &[&Ident],
+    channel: bool,
+) -> Tokens {
+    let arms = idents.iter().map(|ident| {
+        if channel {
+            quote! { #child::#ident(c) => #parent::#ident(c) }
+        } else {
+            quote! { #child::#ident => #parent::#ident }
+        }
+    });
+
+    quote! {
+        impl From<#child> for #parent {
+            fn from(value: #child) -> Self {
+                match value {
+                    #(#arms,)*
+                }
+            }
+        }
+    }
+}
+
+/// generate the `Mirror` mapping from a parent synapse onto its child
+///
+/// the parent can have variants that the child does not, so those are left
+/// unmapped instead of converted.
+fn mirror(
+    child: (&Ident, &Ident, &Ident),
+    parent: (&Ident, &Ident, &Ident),
+    idents: &[&Ident],
+) -> Tokens {
+    let (synapse, terminal, dendrite) = child;
+    let (parent, parent_terminal, parent_dendrite) = parent;
+
+    let synapses = idents.iter().map(|ident| {
+        quote! { #parent::#ident => Some(#synapse::#ident) }
+    });
+    let terminals = idents.iter().map(|ident| {
+        quote! { #parent_terminal::#ident(c) => Some(#terminal::#ident(c)) }
+    });
+    let dendrites = idents.iter().map(|ident| {
+        quote! { #parent_dendrite::#ident(c) => Some(#dendrite::#ident(c)) }
+    });
+
+    quote! {
+        impl ::organelle::Mapping<#parent, #synapse> for ::organelle::Mirror {
+            #[allow(unreachable_patterns)]
+            fn synapse(&self, synapse: #parent) -> Option<#synapse> {
+                match synapse {
+                    #(#synapses,)*
+                    _ => None,
+                }
+            }
+            #[allow(unreachable_patterns)]
+            fn terminal(
+                &self,
+                terminal: #parent_terminal,
+            ) -> Option<#terminal> {
+                match terminal {
+                    #(#terminals,)*
+                    _ => None,
+                }
+            }
+            #[allow(unreachable_patterns)]
+            fn dendrite(
+                &self,
+                dendrite: #parent_dendrite,
+            ) -> Option<#dendrite> {
+                match dendrite {
+                    #(#dendrites,)*
+                    _ => None,
+                }
+            }
+
+            fn parent_synapse(&self, synapse: #synapse) -> Option<#parent> {
+                Some(synapse.into())
+            }
+            fn parent_terminal(
+                &self,
+                terminal: #terminal,
+            ) -> Option<#parent_terminal> {
+                Some(terminal.into())
+            }
+            fn parent_dendrite(
+                &self,
+                dendrite: #dendrite,
+            ) -> Option<#parent_dendrite> {
+                Some(dendrite.into())
+            }
+        }
+    }
+}
+
+fn parse_variant(synapse: &Ident, variant: &syn::Variant) -> Variant {
+    match variant.fields {
+        Fields::Unit => (),
+        _ => panic!(
+            "{}::{} must be a unit variant to derive Synapse",
+            synapse,
+            variant.ident
+        ),
+    }
+
+    let mut message = None;
+    let mut channel = None;
+    let mut buffer = 10;
+
+    for (name, value) in synapse_attrs(&variant.attrs) {
+        match (name.as_ref(), value) {
+            ("message", Lit::Str(s)) => {
+                message = Some(
+                    syn::parse_str::<syn::Type>(&s.value())
+                        .expect("message must be a type"),
+                )
+            },
+            ("channel", Lit::Str(s)) => channel = Some(s.value()),
+            ("buffer", Lit::Int(i)) => buffer = i.value() as usize,
+            ("buffer", Lit::Str(s)) => {
+                buffer = s.value().parse().expect("buffer must be a number")
+            },
+            (name, _) => panic!("unknown synapse attribute `{}`", name),
+        }
+    }
+
+    let message = message.unwrap_or_else(|| {
+        panic!(
+            "{}::{} needs a #[synapse(message = \"...\")] attribute",
+            synapse,
+            variant.ident
+        )
+    });
+
+    let channel = match channel.as_ref().map(|c| c.as_str()) {
+        Some("mpsc") | None => Channel::Mpsc(buffer),
+        Some("oneshot") => Channel::Oneshot,
+        Some(other) => panic!("unknown channel kind `{}`", other),
+    };
+
+    Variant {
+        ident: variant.ident,
+        message: message,
+        channel: channel,
+    }
+}
+
+/// collect the name/value pairs of all #[synapse(...)] attributes
+fn synapse_attrs(attrs: &[syn::Attribute]) -> Vec<(Ident, Lit)> {
+    let mut pairs = vec![];
+
+    for attr in attrs {
+        match attr.interpret_meta() {
+            Some(Meta::List(ref list)) if list.ident == "synapse" => {
+                for nested in list.nested.iter() {
+                    match nested {
+                        &NestedMeta::Meta(Meta::NameValue(ref pair)) => {
+                            pairs.push((pair.ident, pair.lit.clone()))
+                        },
+                        _ => panic!("expected #[synapse(name = value)]"),
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+
+    pairs
+}
+
+/// name the terminal or dendrite enum that goes with a synapse enum
+fn sibling_name(synapse: &str, sibling: &str) -> String {
+    if synapse.ends_with("Synapse") && synapse != "Synapse" {
+        format!("{}{}", &synapse[..synapse.len() - "Synapse".len()], sibling)
+    } else {
+        format!("{}{}", synapse, sibling)
+    }
+}
+
+fn expand_describe(input: &DeriveInput) -> Tokens {
+    let ident = &input.ident;
+    let name = ident.as_ref();
+
+    let mut tag = None;
+    let mut closed = false;
+
+    for meta in serde_attrs(&input.attrs) {
+        match meta {
+            Meta::NameValue(ref pair) if pair.ident == "tag" => {
+                if let Lit::Str(ref s) = pair.lit {
+                    tag = Some(s.value());
+                }
+            },
+            Meta::Word(ref word) if word == "deny_unknown_fields" => {
+                closed = true
+            },
+            _ => (),
+        }
+    }
+
+    let body = match input.data {
+        Data::Struct(ref data) => describe_object(name, &data.fields, closed),
+        Data::Enum(ref data) => {
+            let unit = data.variants.iter().all(|v| match v.fields {
+                Fields::Unit => true,
+                _ => false,
+            });
+
+            if unit {
+                let names = data.variants
+                    .iter()
+                    .map(|v| serde_name(&v.attrs, v.ident.as_ref()));
+
+                quote! { ::probe::schema::names(&[#(#names),*]) }
+            } else {
+                let tag = tag.unwrap_or_else(|| {
+                    panic!("{} needs a tag to derive Describe", name)
+                });
+
+                let objects = data.variants.iter().map(|v| {
+                    let variant = serde_name(&v.attrs, v.ident.as_ref());
+                    let object = describe_object(name, &v.fields, closed);
+
+                    quote! { #object.tag(#tag, #variant) }
+                });
+
+                quote! {
+                    ::probe::schema::one_of(vec![#(#objects.build()),*])
+                }
+            }
+        },
+        _ => panic!("Describe can only be derived for structs and enums"),
+    };
+
+    let body = match input.data {
+        Data::Struct(_) => quote! { #body.build() },
+        _ => body,
+    };
+
+    quote! {
+        impl ::probe::schema::Describe for #ident {
+            fn describe(
+                definitions: &mut ::probe::schema::Definitions,
+            ) -> ::serde_json::Value {
+                definitions.define(#name, |definitions| #body)
+            }
+        }
+    }
+}
+
+/// generate an object schema with one property per named field
+fn describe_object(name: &str, fields: &Fields, closed: bool) -> Tokens {
+    let fields = match *fields {
+        Fields::Named(ref fields) => &fields.named,
+        _ => panic!("{} needs named fields to derive Describe", name),
+    };
+
+    let properties = fields.iter().map(|field| {
+        let ident = field.ident.as_ref().unwrap();
+        let key = serde_name(&field.attrs, ident.as_ref());
+        let ty = &field.ty;
+
+        let default = serde_attrs(&field.attrs).iter().any(|meta| match meta {
+            &Meta::Word(ref word) => word == "default",
+            _ => false,
+        });
+
+        quote! { .property::<#ty>(#key, #default, definitions) }
+    });
+
+    quote! {
+        ::probe::schema::Object::new(#closed)#(#properties)*
+    }
+}
+
+/// the name serde gives a field or variant
+fn serde_name(attrs: &[syn::Attribute], ident: &str) -> String {
+    for meta in serde_attrs(attrs) {
+        match meta {
+            Meta::NameValue(ref pair) if pair.ident == "rename" => {
+                if let Lit::Str(ref s) = pair.lit {
+                    return s.value();
+                }
+            },
+            _ => (),
+        }
+    }
+
+    ident.to_string()
+}
+
+/// collect the items of all #[serde(...)] attributes
+fn serde_attrs(attrs: &[syn::Attribute]) -> Vec<Meta> {
+    let mut metas = vec![];
+
+    for attr in attrs {
+        match attr.interpret_meta() {
+            Some(Meta::List(ref list)) if list.ident == "serde" => {
+                for nested in list.nested.iter() {
+                    if let &NestedMeta::Meta(ref meta) = nested {
+                        metas.push(meta.clone());
+                    }
+                }
+            },
+            _ => (),
+        }
+    }
+
+    metas
+}
@@ -0,0 +1,93 @@
+extern crate organelle;
+#[macro_use]
+extern crate organelle_derive;
+
+use organelle::{Mapping, Mirror, Synapse};
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Synapse)]
+enum HostSynapse {
+    #[synapse(channel = "mpsc", message = "u32", buffer = 4)]
+    Increment,
+    #[synapse(channel = "oneshot", message = "String")]
+    Report,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Synapse)]
+#[synapse(parent = "HostSynapse")]
+enum CounterSynapse {
+    #[synapse(channel = "mpsc", message = "u32", buffer = 4)]
+    Increment,
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Synapse)]
+#[synapse(terminal = "Output", dendrite = "Input")]
+enum Named {
+    #[synapse(message = "()")]
+    Tick,
+}
+
+#[test]
+fn test_derive_enums() {
+    match HostSynapse::Increment.synapse() {
+        (HostTerminal::Increment(_), HostDendrite::Increment(_)) => (),
+        pair => panic!("unexpected channel {:?}", pair),
+    }
+    match HostSynapse::Report.synapse() {
+        (HostTerminal::Report(_), HostDendrite::Report(_)) => (),
+        pair => panic!("unexpected channel {:?}", pair),
+    }
+    match Named::Tick.synapse() {
+        (Output::Tick(_), Input::Tick(_)) => (),
+    }
+}
+
+#[test]
+fn test_derive_data() {
+    let data = HostSynapse::data();
+
+    assert!(data.name.ends_with("HostSynapse"));
+    assert_eq!(data.variants.len(), 2);
+
+    assert_eq!(data.variants[0].variant, "Increment");
+    assert_eq!(data.variants[0].channel, "mpsc");
+    assert_eq!(data.variants[0].message, "u32");
+
+    assert_eq!(data.variants[1].variant, "Report");
+    assert_eq!(data.variants[1].channel, "oneshot");
+    assert_eq!(data.variants[1].message, "String");
+}
+
+#[test]
+fn test_derive_parent() {
+    assert_eq!(
+        HostSynapse::from(CounterSynapse::Increment),
+        HostSynapse::Increment
+    );
+
+    let mapping: &Mapping<HostSynapse, CounterSynapse> = &Mirror;
+
+    assert_eq!(
+        mapping.synapse(HostSynapse::Increment),
+        Some(CounterSynapse::Increment)
+    );
+    // variants missing from the child are rejected instead of panicking
+    assert_eq!(mapping.synapse(HostSynapse::Report), None);
+
+    let (terminal, dendrite) = HostSynapse::Report.synapse();
+    assert!(mapping.terminal(terminal).is_none());
+    assert!(mapping.dendrite(dendrite).is_none());
+
+    let (terminal, dendrite) = HostSynapse::Increment.synapse();
+    assert!(mapping.terminal(terminal).is_some());
+    assert!(mapping.dendrite(dendrite).is_some());
+
+    let (terminal, dendrite) = CounterSynapse::Increment.synapse();
+    match mapping.parent_terminal(terminal) {
+        Some(HostTerminal::Increment(_)) => (),
+        other => panic!("unexpected terminal {:?}", other),
+    }
+    match mapping.parent_dendrite(dendrite) {
+        Some(HostDendrite::Increment(_)) => (),
+        other => panic!("unexpected dendrite {:?}", other),
+    }
+}
@@ -1,200 +1,894 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::intrinsics;
+use std::rc::Rc;
 
 use futures::prelude::*;
-use futures::unsync::oneshot;
+use serde_json;
 use uuid::Uuid;
 
 use super::{Error, ErrorKind, Result};
 use probe::{self, ConstraintData, SomaData};
-use soma::{Impulse, Soma, Synapse};
+use directory::{Directory, Peer};
+use recorder::Recorder;
+use soma::{self, attribute_origin, Impulse, Soma, SomaOrigin, Synapse};
+use validation::{IssueKind, ValidationIssue};
 
 /// constraints that can be put on axons for validation purposes
+#[derive(Debug, Copy, Clone)]
 pub enum Constraint<S: Synapse> {
     /// only accept one synapse
     One(S),
     /// accept any number of synapses
     Variadic(S),
+    /// accept zero or one synapse
+    Optional(S),
+    /// accept at least the given number of synapses
+    AtLeast(S, usize),
+    /// accept at most the given number of synapses
+    AtMost(S, usize),
+    /// accept between a minimum and maximum number of synapses, inclusive
+    ///
+    /// adding a range whose minimum is above its maximum panics, since it
+    /// could never be satisfied.
+    Range(S, usize, usize),
 }
 
-#[derive(Debug)]
-enum Requirement {
-    Unmet,
-    MetOne(Uuid),
-    MetVariadic(Vec<Uuid>),
+impl<S: Synapse> Constraint<S> {
+    /// the synapse being constrained
+    pub fn synapse(&self) -> S {
+        match *self {
+            Constraint::One(synapse)
+            | Constraint::Variadic(synapse)
+            | Constraint::Optional(synapse)
+            | Constraint::AtLeast(synapse, _)
+            | Constraint::AtMost(synapse, _)
+            | Constraint::Range(synapse, _, _) => synapse,
+        }
+    }
+
+    /// the minimum and maximum number of synapses accepted
+    pub fn bounds(&self) -> (usize, Option<usize>) {
+        match *self {
+            Constraint::One(_) => (1, Some(1)),
+            Constraint::Variadic(_) => (0, None),
+            Constraint::Optional(_) => (0, Some(1)),
+            Constraint::AtLeast(_, min) => (min, None),
+            Constraint::AtMost(_, max) => (0, Some(max)),
+            Constraint::Range(_, min, max) => (min, Some(max)),
+        }
+    }
 }
 
-/// wrap a soma with a set of requirements that will be validated upon startup
-pub struct Axon<T: Soma + 'static> {
-    soma: T,
+/// requirements on the soma at the other end of a synapse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PeerConstraint {
+    /// the peer must be a soma with the given type name
+    ///
+    /// see `Peer::is_named` for how names are matched.
+    Soma(String),
+    /// the peer must carry the given tag
+    Tag(String),
+}
 
-    uuid: Option<Uuid>,
+impl PeerConstraint {
+    /// require the peer to be a soma of type `T`
+    pub fn soma<T: Soma>() -> Self {
+        PeerConstraint::Soma(unsafe { intrinsics::type_name::<T>() }.into())
+    }
+
+    /// require the peer to carry a tag
+    pub fn tag<T: Into<String>>(tag: T) -> Self {
+        PeerConstraint::Tag(tag.into())
+    }
+
+    /// check whether a peer satisfies the constraint
+    pub fn matches(&self, peer: &Peer) -> bool {
+        match self {
+            &PeerConstraint::Soma(ref name) => peer.is_named(name),
+            &PeerConstraint::Tag(ref tag) => peer.has_tag(tag),
+        }
+    }
+}
 
-    dendrites: HashMap<T::Synapse, (Constraint<T::Synapse>, Requirement)>,
-    terminals: HashMap<T::Synapse, (Constraint<T::Synapse>, Requirement)>,
+impl fmt::Display for PeerConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &PeerConstraint::Soma(ref name) => {
+                write!(f, "a {}", soma::short_name(name))
+            },
+            &PeerConstraint::Tag(ref tag) => write!(f, "tagged {:?}", tag),
+        }
+    }
 }
 
-impl<T: Soma + 'static> Axon<T> {
-    /// wrap a soma with constraints specified by dendrite and terminal
-    /// constraints
+type Constraints<S> = HashMap<S, (Constraint<S>, Vec<Uuid>)>;
+type Peers<S> = HashMap<S, Vec<PeerConstraint>>;
+
+/// check the wiring inside a soma given the dendrites and terminals
+/// connected to it from outside
+#[derive(Clone)]
+struct Inner<S: Synapse>(
+    Rc<Fn(&[(Uuid, S)], &[(Uuid, S)]) -> Vec<ValidationIssue>>,
+);
+
+impl<S: Synapse> fmt::Debug for Inner<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Inner")
+    }
+}
+
+/// tracks the synapses connected to a set of dendrite and terminal
+/// constraints
+///
+/// once started, the requirements are re-checked whenever a synapse is added
+/// or removed. violations are returned and kept for probes rather than
+/// treated as errors so that synapses can be swapped while the organelle is
+/// running.
+#[derive(Debug, Clone)]
+pub struct Requirements<S: Synapse> {
+    started: bool,
+
+    dendrites: Constraints<S>,
+    terminals: Constraints<S>,
+
+    dendrite_peers: Peers<S>,
+    terminal_peers: Peers<S>,
+
+    strays: Vec<(Uuid, S, &'static str)>,
+    wrong_peers: Vec<(Uuid, S, &'static str, String)>,
+    violations: Vec<ValidationIssue>,
+
+    inner: Option<Inner<S>>,
+}
+
+impl<S: Synapse> Requirements<S> {
+    /// create requirements from dendrite and terminal constraints
     pub fn new(
-        soma: T,
-        dendrites: Vec<Constraint<T::Synapse>>,
-        terminals: Vec<Constraint<T::Synapse>>,
+        dendrites: Vec<Constraint<S>>,
+        terminals: Vec<Constraint<S>>,
     ) -> Self {
         Self {
-            soma: soma,
+            started: false,
 
-            uuid: None,
+            dendrites: Self::constraints(dendrites),
+            terminals: Self::constraints(terminals),
 
-            dendrites: dendrites
-                .iter()
-                .map(|d| match d {
-                    &Constraint::One(r) => {
-                        (r, (Constraint::One(r), Requirement::Unmet))
-                    },
-                    &Constraint::Variadic(r) => (
-                        r,
-                        (
-                            Constraint::Variadic(r),
-                            Requirement::MetVariadic(vec![]),
-                        ),
-                    ),
-                })
-                .collect(),
-            terminals: terminals
-                .iter()
-                .map(|d| match d {
-                    &Constraint::One(r) => {
-                        (r, (Constraint::One(r), Requirement::Unmet))
-                    },
-                    &Constraint::Variadic(r) => (
-                        r,
-                        (
-                            Constraint::Variadic(r),
-                            Requirement::MetVariadic(vec![]),
-                        ),
-                    ),
-                })
-                .collect(),
+            dendrite_peers: HashMap::new(),
+            terminal_peers: HashMap::new(),
+
+            strays: vec![],
+            wrong_peers: vec![],
+            violations: vec![],
+
+            inner: None,
         }
     }
 
-    fn add_dendrite(&mut self, uuid: Uuid, synapse: T::Synapse) -> Result<()> {
-        if let Some(&mut (ref mut constraint, ref mut req)) =
-            self.dendrites.get_mut(&synapse)
-        {
-            match constraint {
-                &mut Constraint::One(_) => match req {
-                    &mut Requirement::Unmet => *req = Requirement::MetOne(uuid),
-                    &mut Requirement::MetOne(_) => {
-                        bail!(ErrorKind::InvalidSynapse(format!(
-                            "expected only one dendrite for {:?}",
-                            synapse
-                        )))
-                    },
-                    _ => unreachable!(),
-                },
-                &mut Constraint::Variadic(_) => match req {
-                    &mut Requirement::MetVariadic(ref mut dendrites) => {
-                        dendrites.push(uuid);
-                    },
-                    _ => unreachable!(),
-                },
-            }
-        } else {
-            bail!(ErrorKind::InvalidSynapse(format!(
-                "no constraints found for {:?}",
-                synapse
-            )))
+    /// check the wiring inside the soma as well when auditing
+    ///
+    /// synapses without a constraint are left to the inner wiring instead of
+    /// being reported as unknown, the way an organelle hands them to its
+    /// nucleus.
+    pub(crate) fn with_inner<F>(self, inner: F) -> Self
+    where
+        F: Fn(&[(Uuid, S)], &[(Uuid, S)]) -> Vec<ValidationIssue> + 'static,
+    {
+        Self {
+            inner: Some(Inner(Rc::new(inner))),
+            ..self
+        }
+    }
+
+    /// add a dendrite constraint
+    pub fn add_dendrite_constraint(&mut self, constraint: Constraint<S>) {
+        self.dendrites.extend(Self::constraints(vec![constraint]));
+    }
+
+    /// add a terminal constraint
+    pub fn add_terminal_constraint(&mut self, constraint: Constraint<S>) {
+        self.terminals.extend(Self::constraints(vec![constraint]));
+    }
+
+    /// require the somas connected to a dendrite to satisfy a peer
+    /// constraint
+    pub fn add_dendrite_peer(&mut self, synapse: S, peer: PeerConstraint) {
+        self.dendrite_peers
+            .entry(synapse)
+            .or_insert_with(Vec::new)
+            .push(peer);
+    }
+
+    /// require the somas connected to a terminal to satisfy a peer
+    /// constraint
+    pub fn add_terminal_peer(&mut self, synapse: S, peer: PeerConstraint) {
+        self.terminal_peers
+            .entry(synapse)
+            .or_insert_with(Vec::new)
+            .push(peer);
+    }
+
+    fn constraints(constraints: Vec<Constraint<S>>) -> Constraints<S> {
+        constraints
+            .into_iter()
+            .map(|c| {
+                if let Constraint::Range(synapse, min, max) = c {
+                    assert!(
+                        min <= max,
+                        "range for {:?} has a minimum above its maximum",
+                        synapse
+                    );
+                }
+
+                (c.synapse(), (c, vec![]))
+            })
+            .collect()
+    }
+
+    /// record a dendrite connected to the given soma
+    ///
+    /// once started, this returns the constraints newly violated by the
+    /// synapse.
+    pub fn add_dendrite(
+        &mut self,
+        origin: &SomaOrigin,
+        uuid: Uuid,
+        synapse: S,
+    ) -> Result<Vec<ValidationIssue>> {
+        let started = self.started;
+
+        let known = Self::connect(
+            &mut self.dendrites,
+            "dendrite",
+            origin,
+            started,
+            uuid,
+            synapse,
+        )?;
+
+        if !known {
+            self.strays.push((uuid, synapse, "dendrite"));
+        }
+
+        Ok(self.revalidate(origin))
+    }
+
+    /// record a terminal connected to the given soma
+    ///
+    /// once started, this returns the constraints newly violated by the
+    /// synapse.
+    pub fn add_terminal(
+        &mut self,
+        origin: &SomaOrigin,
+        uuid: Uuid,
+        synapse: S,
+    ) -> Result<Vec<ValidationIssue>> {
+        let started = self.started;
+
+        let known = Self::connect(
+            &mut self.terminals,
+            "terminal",
+            origin,
+            started,
+            uuid,
+            synapse,
+        )?;
+
+        if !known {
+            self.strays.push((uuid, synapse, "terminal"));
+        }
+
+        Ok(self.revalidate(origin))
+    }
+
+    /// forget a dendrite connected to the given soma
+    ///
+    /// once started, this returns the constraints newly violated by losing
+    /// the synapse.
+    pub fn remove_dendrite(
+        &mut self,
+        origin: &SomaOrigin,
+        uuid: Uuid,
+        synapse: S,
+    ) -> Vec<ValidationIssue> {
+        Self::disconnect(&mut self.dendrites, uuid, synapse);
+
+        self.strays.retain(|&(soma, stray, kind)| {
+            soma != uuid || stray != synapse || kind != "dendrite"
+        });
+        self.wrong_peers.retain(|&(soma, peer, kind, _)| {
+            soma != uuid || peer != synapse || kind != "dendrite"
+        });
+
+        self.revalidate(origin)
+    }
+
+    /// forget a terminal connected to the given soma
+    ///
+    /// once started, this returns the constraints newly violated by losing
+    /// the synapse.
+    pub fn remove_terminal(
+        &mut self,
+        origin: &SomaOrigin,
+        uuid: Uuid,
+        synapse: S,
+    ) -> Vec<ValidationIssue> {
+        Self::disconnect(&mut self.terminals, uuid, synapse);
+
+        self.strays.retain(|&(soma, stray, kind)| {
+            soma != uuid || stray != synapse || kind != "terminal"
+        });
+        self.wrong_peers.retain(|&(soma, peer, kind, _)| {
+            soma != uuid || peer != synapse || kind != "terminal"
+        });
+
+        self.revalidate(origin)
+    }
+
+    /// make sure every constraint is met before starting
+    pub fn start(&mut self, origin: &SomaOrigin) -> Result<()> {
+        self.started = true;
+
+        let missing = Self::check(&self.dendrites, "dendrite")
+            .into_iter()
+            .chain(Self::check(&self.terminals, "terminal"))
+            .next();
+
+        if let Some((_, msg)) = missing {
+            bail!(ErrorKind::MissingSynapse(origin.clone(), msg))
+        }
+
+        Ok(())
+    }
+
+    /// check a dendrite added since starting against the peer constraints
+    ///
+    /// a mismatch is kept as a violation until the synapse is removed, and
+    /// is returned if it was not already known.
+    pub fn check_dendrite_peer(
+        &mut self,
+        origin: &SomaOrigin,
+        directory: &Directory,
+        uuid: Uuid,
+        synapse: S,
+    ) -> Vec<ValidationIssue> {
+        self.check_peer(origin, directory, uuid, synapse, "dendrite")
+    }
+
+    /// check a terminal added since starting against the peer constraints
+    ///
+    /// see `check_dendrite_peer`
+    pub fn check_terminal_peer(
+        &mut self,
+        origin: &SomaOrigin,
+        directory: &Directory,
+        uuid: Uuid,
+        synapse: S,
+    ) -> Vec<ValidationIssue> {
+        self.check_peer(origin, directory, uuid, synapse, "terminal")
+    }
+
+    fn check_peer(
+        &mut self,
+        origin: &SomaOrigin,
+        directory: &Directory,
+        uuid: Uuid,
+        synapse: S,
+        kind: &'static str,
+    ) -> Vec<ValidationIssue> {
+        let mismatches = Self::peer_mismatch(
+            match kind {
+                "dendrite" => &self.dendrite_peers,
+                _ => &self.terminal_peers,
+            },
+            kind,
+            directory,
+            uuid,
+            synapse,
+        );
+
+        // a soma reconnecting after a restart replaces its old mismatches
+        self.wrong_peers.retain(|&(soma, peer, peer_kind, _)| {
+            soma != uuid || peer != synapse || peer_kind != kind
+        });
+        self.wrong_peers.extend(
+            mismatches
+                .into_iter()
+                .map(|msg| (uuid, synapse, kind, msg)),
+        );
+
+        self.revalidate(origin)
+    }
+
+    /// make sure every connected soma satisfies the peer constraints
+    pub fn check_peers(
+        &self,
+        origin: &SomaOrigin,
+        directory: &Directory,
+    ) -> Result<()> {
+        let mismatch = Self::peer_mismatches(
+            &self.dendrites,
+            &self.dendrite_peers,
+            "dendrite",
+            directory,
+        ).into_iter()
+            .chain(Self::peer_mismatches(
+                &self.terminals,
+                &self.terminal_peers,
+                "terminal",
+                directory,
+            ))
+            .next();
+
+        if let Some((_, msg)) = mismatch {
+            bail!(ErrorKind::InvalidSynapse(origin.clone(), msg))
         }
 
         Ok(())
     }
 
-    fn add_terminal(&mut self, uuid: Uuid, synapse: T::Synapse) -> Result<()> {
-        if let Some(&mut (ref mut constraint, ref mut req)) =
-            self.terminals.get_mut(&synapse)
+    /// check the given synapses against every constraint at once
+    ///
+    /// unlike `add_dendrite`, `add_terminal` and `start`, this does not record
+    /// anything and reports every problem instead of stopping at the first.
+    pub(crate) fn audit(
+        &self,
+        dendrites: &[(Uuid, S)],
+        terminals: &[(Uuid, S)],
+        directory: &Directory,
+    ) -> Vec<(IssueKind, S, String)> {
+        let mut issues = Self::audit_constraints(
+            &self.dendrites,
+            "dendrite",
+            dendrites,
+        );
+        issues.extend(Self::audit_constraints(
+            &self.terminals,
+            "terminal",
+            terminals,
+        ));
+
+        let peers = |synapses: &[(Uuid, S)]| {
+            let mut peers = Constraints::new();
+
+            for &(uuid, synapse) in synapses {
+                peers
+                    .entry(synapse)
+                    .or_insert_with(|| (Constraint::Variadic(synapse), vec![]))
+                    .1
+                    .push(uuid);
+            }
+
+            peers
+        };
+
+        for (synapse, msg) in Self::peer_mismatches(
+            &peers(dendrites),
+            &self.dendrite_peers,
+            "dendrite",
+            directory,
+        ).into_iter()
+            .chain(Self::peer_mismatches(
+                &peers(terminals),
+                &self.terminal_peers,
+                "terminal",
+                directory,
+            )) {
+            issues.push((IssueKind::WrongPeer, synapse, msg));
+        }
+
+        if self.inner.is_some() {
+            issues.retain(|&(kind, _, _)| kind != IssueKind::UnknownSynapse);
+        }
+
+        // the constraints are kept in a map, so sort them for a stable report
+        issues.sort_by(|a, b| a.2.cmp(&b.2));
+
+        issues
+    }
+
+    /// check the wiring inside the soma given the synapses connected to it
+    pub(crate) fn audit_inner(
+        &self,
+        dendrites: &[(Uuid, S)],
+        terminals: &[(Uuid, S)],
+    ) -> Vec<ValidationIssue> {
+        match self.inner {
+            Some(Inner(ref inner)) => inner(dendrites, terminals),
+            None => vec![],
+        }
+    }
+
+    /// find the connected somas that do not satisfy their peer constraints
+    fn peer_mismatches(
+        constraints: &Constraints<S>,
+        peers: &Peers<S>,
+        kind: &str,
+        directory: &Directory,
+    ) -> Vec<(S, String)> {
+        let mut mismatches = vec![];
+
+        for (synapse, &(_, ref somas)) in constraints {
+            for uuid in somas {
+                mismatches.extend(
+                    Self::peer_mismatch(peers, kind, directory, *uuid, *synapse)
+                        .into_iter()
+                        .map(|msg| (*synapse, msg)),
+                );
+            }
+        }
+
+        mismatches
+    }
+
+    /// check a single connected soma against its peer constraints
+    fn peer_mismatch(
+        peers: &Peers<S>,
+        kind: &str,
+        directory: &Directory,
+        uuid: Uuid,
+        synapse: S,
+    ) -> Vec<String> {
+        let required = match peers.get(&synapse) {
+            Some(required) => required,
+            None => return vec![],
+        };
+
+        let peer = directory.get(uuid);
+
+        required
+            .iter()
+            .filter(|constraint| {
+                !peer.as_ref()
+                    .map(|peer| constraint.matches(peer))
+                    .unwrap_or(false)
+            })
+            .map(|constraint| {
+                format!(
+                    "expected {} for {} {:?}, found {}",
+                    constraint,
+                    kind,
+                    synapse,
+                    peer.as_ref()
+                        .map(|peer| soma::short_name(&peer.name))
+                        .unwrap_or_else(|| uuid.to_string())
+                )
+            })
+            .collect()
+    }
+
+    /// the constraints violated since starting
+    pub fn violations(&self) -> &[ValidationIssue] {
+        &self.violations
+    }
+
+    /// data associated with the dendrite constraints
+    pub fn dendrite_data(&self) -> Vec<ConstraintData> {
+        Self::data(&self.dendrites)
+    }
+
+    /// data associated with the terminal constraints
+    pub fn terminal_data(&self) -> Vec<ConstraintData> {
+        Self::data(&self.terminals)
+    }
+
+    /// record a synapse against its constraint
+    ///
+    /// a synapse without a constraint is an error before startup. afterwards,
+    /// it is accepted and `false` is returned so it can be reported as a
+    /// violation instead.
+    fn connect(
+        constraints: &mut Constraints<S>,
+        kind: &str,
+        origin: &SomaOrigin,
+        started: bool,
+        uuid: Uuid,
+        synapse: S,
+    ) -> Result<bool> {
+        if let Some(&mut (ref constraint, ref mut somas)) =
+            constraints.get_mut(&synapse)
         {
-            match constraint {
-                &mut Constraint::One(_) => match req {
-                    &mut Requirement::Unmet => *req = Requirement::MetOne(uuid),
-                    &mut Requirement::MetOne(_) => {
-                        bail!(ErrorKind::InvalidSynapse(format!(
-                            "expected only one terminal for {:?}",
-                            synapse
-                        )))
-                    },
-                    _ => unreachable!(),
-                },
-                &mut Constraint::Variadic(_) => match req {
-                    &mut Requirement::MetVariadic(ref mut terminals) => {
-                        terminals.push(uuid);
-                    },
-                    _ => unreachable!(),
-                },
+            if somas.contains(&uuid) {
+                // the same soma is reconnecting after a restart
+                return Ok(true);
             }
+
+            // before startup, too many synapses is a configuration error.
+            // afterwards, it is reported as a violation instead.
+            if !started {
+                if let Some((IssueKind::DuplicateSynapse, msg)) =
+                    Self::verify(constraint, kind, somas.len() + 1)
+                {
+                    bail!(ErrorKind::InvalidSynapse(origin.clone(), msg))
+                }
+            }
+
+            somas.push(uuid);
+        } else if started {
+            return Ok(false);
         } else {
-            bail!(ErrorKind::InvalidSynapse(format!(
-                "no constraints found for {:?}",
-                synapse
-            )))
+            bail!(ErrorKind::InvalidSynapse(
+                origin.clone(),
+                format!("no constraints found for {:?}", synapse)
+            ))
         }
 
-        Ok(())
+        Ok(true)
     }
 
-    fn start(&mut self, uuid: Uuid) -> Result<()> {
-        self.uuid = Some(uuid);
+    fn audit_constraints(
+        constraints: &Constraints<S>,
+        kind: &str,
+        synapses: &[(Uuid, S)],
+    ) -> Vec<(IssueKind, S, String)> {
+        let mut issues = vec![];
+        let mut counts = HashMap::new();
+
+        for &(_, synapse) in synapses {
+            if constraints.contains_key(&synapse) {
+                *counts.entry(synapse).or_insert(0) += 1;
+            } else {
+                issues.push((
+                    IssueKind::UnknownSynapse,
+                    synapse,
+                    format!("no constraints found for {} {:?}", kind, synapse),
+                ));
+            }
+        }
 
-        for (synapse, &(ref constraint, ref req)) in &self.dendrites {
-            match constraint {
-                &Constraint::One(_) => match req {
-                    &Requirement::MetOne(_) => (),
-                    &Requirement::Unmet => bail!(ErrorKind::MissingSynapse(
-                        format!("expected dendrite synapse for {:?}", *synapse)
-                    )),
-                    _ => unreachable!(),
-                },
-                &Constraint::Variadic(_) => match req {
-                    &Requirement::MetVariadic(_) => (),
-                    _ => unreachable!(),
-                },
+        for (synapse, &(ref constraint, _)) in constraints {
+            let count = counts.get(synapse).cloned().unwrap_or(0);
+
+            match (Self::verify(constraint, kind, count), constraint.bounds()) {
+                (Some((issue, msg)), _) => issues.push((issue, *synapse, msg)),
+                // optional ports are allowed to stay empty, but likely should
+                // not be
+                (None, (0, _)) if count == 0 => issues.push((
+                    IssueKind::UnconnectedPort,
+                    *synapse,
+                    format!("no {} connected for {:?}", kind, synapse),
+                )),
+                _ => (),
             }
         }
 
-        for (synapse, &(ref constraint, ref req)) in &self.terminals {
+        issues
+    }
+
+    /// compare the number of synapses connected to a constraint with its
+    /// bounds
+    fn verify(
+        constraint: &Constraint<S>,
+        kind: &str,
+        count: usize,
+    ) -> Option<(IssueKind, String)> {
+        let synapse = constraint.synapse();
+
+        match constraint.bounds() {
+            (1, _) if count == 0 => Some((
+                IssueKind::MissingSynapse,
+                format!("expected {} synapse for {:?}", kind, synapse),
+            )),
+            (min, _) if count < min => Some((
+                IssueKind::MissingSynapse,
+                format!(
+                    "expected at least {} {}s for {:?}, found {}",
+                    min, kind, synapse, count
+                ),
+            )),
+            (_, Some(1)) if count > 1 => Some((
+                IssueKind::DuplicateSynapse,
+                format!("expected only one {} for {:?}", kind, synapse),
+            )),
+            (_, Some(max)) if count > max => Some((
+                IssueKind::DuplicateSynapse,
+                format!(
+                    "expected at most {} {}s for {:?}, found {}",
+                    max, kind, synapse, count
+                ),
+            )),
+            _ => None,
+        }
+    }
+
+    fn disconnect(constraints: &mut Constraints<S>, uuid: Uuid, synapse: S) {
+        if let Some(&mut (_, ref mut somas)) = constraints.get_mut(&synapse) {
+            somas.retain(|soma| *soma != uuid);
+        }
+    }
+
+    fn check(
+        constraints: &Constraints<S>,
+        kind: &str,
+    ) -> Vec<(IssueKind, String)> {
+        constraints
+            .values()
+            .filter_map(|&(ref constraint, ref somas)| {
+                Self::verify(constraint, kind, somas.len())
+            })
+            .collect()
+    }
+
+    /// re-check the constraints after a synapse was added or removed
+    ///
+    /// returns the violations that were not already known.
+    fn revalidate(&mut self, origin: &SomaOrigin) -> Vec<ValidationIssue> {
+        if !self.started {
+            return vec![];
+        }
+
+        let mut violations = Self::check(&self.dendrites, "dendrite");
+        violations.extend(Self::check(&self.terminals, "terminal"));
+
+        for &(_, synapse, kind) in &self.strays {
+            violations.push((
+                IssueKind::UnknownSynapse,
+                format!("no constraints found for {} {:?}", kind, synapse),
+            ));
+        }
+
+        for &(_, _, _, ref msg) in &self.wrong_peers {
+            violations.push((IssueKind::WrongPeer, msg.clone()));
+        }
+
+        let violations: Vec<_> = violations
+            .into_iter()
+            .map(|(kind, message)| ValidationIssue {
+                kind: kind,
+                origin: origin.clone(),
+                message: message,
+            })
+            .collect();
+
+        let raised = violations
+            .iter()
+            .filter(|violation| !self.violations.contains(violation))
+            .cloned()
+            .collect();
+
+        self.violations = violations;
+
+        raised
+    }
+
+    fn data(constraints: &Constraints<S>) -> Vec<ConstraintData> {
+        let mut data = vec![];
+
+        for (synapse, &(ref constraint, ref somas)) in constraints {
+            let variant = format!("{:?}", *synapse);
+
             match constraint {
-                &Constraint::One(_) => match req {
-                    &Requirement::MetOne(_) => (),
-                    &Requirement::Unmet => bail!(ErrorKind::MissingSynapse(
-                        format!("expected terminal synapse for {:?}", *synapse)
-                    )),
-                    _ => unreachable!(),
+                &Constraint::One(_) => for soma in somas {
+                    data.push(ConstraintData::One {
+                        variant: variant.clone(),
+                        soma: *soma,
+                    })
+                },
+                &Constraint::Variadic(_) => {
+                    data.push(ConstraintData::Variadic {
+                        variant: variant,
+                        somas: somas.clone(),
+                    })
                 },
-                &Constraint::Variadic(_) => match req {
-                    &Requirement::MetVariadic(_) => (),
-                    _ => unreachable!(),
+                &Constraint::Optional(_) if somas.is_empty() => {
+                    data.push(ConstraintData::Optional {
+                        variant: variant,
+                        soma: None,
+                    })
+                },
+                &Constraint::Optional(_) => for soma in somas {
+                    data.push(ConstraintData::Optional {
+                        variant: variant.clone(),
+                        soma: Some(*soma),
+                    })
+                },
+                &Constraint::AtLeast(_, min) => {
+                    data.push(ConstraintData::AtLeast {
+                        variant: variant,
+                        min: min,
+                        somas: somas.clone(),
+                    })
+                },
+                &Constraint::AtMost(_, max) => {
+                    data.push(ConstraintData::AtMost {
+                        variant: variant,
+                        max: max,
+                        somas: somas.clone(),
+                    })
+                },
+                &Constraint::Range(_, min, max) => {
+                    data.push(ConstraintData::Range {
+                        variant: variant,
+                        min: min,
+                        max: max,
+                        somas: somas.clone(),
+                    })
                 },
             }
         }
 
-        Ok(())
+        data
     }
+}
 
-    #[async]
-    fn perform_probe(
-        self,
-        settings: probe::Settings,
-        tx: oneshot::Sender<SomaData>,
-    ) -> Result<Self> {
-        let (axon, data) = await!(self.probe(settings))?;
+/// wrap a soma with a set of requirements that will be validated upon startup
+///
+/// see `Requirements` for how the constraints are checked once started. any
+/// violations found afterwards show up in the axon's probe data and are
+/// recorded by the organelle's `Recorder`, if it has one.
+pub struct Axon<T: Soma + 'static> {
+    soma: T,
+
+    uuid: Option<Uuid>,
+    directory: Option<Directory>,
+    recorder: Option<Recorder>,
+
+    requirements: Requirements<T::Synapse>,
+}
+
+impl<T: Soma + 'static> Axon<T> {
+    /// wrap a soma with constraints specified by dendrite and terminal
+    /// constraints
+    pub fn new(
+        soma: T,
+        dendrites: Vec<Constraint<T::Synapse>>,
+        terminals: Vec<Constraint<T::Synapse>>,
+    ) -> Self {
+        Self {
+            soma: soma,
 
-        if let Err(_) = tx.send(data) {
-            // rx does not care anymore
+            uuid: None,
+            directory: None,
+            recorder: None,
+
+            requirements: Requirements::new(dendrites, terminals),
         }
+    }
+
+    /// require the somas connected to a dendrite to satisfy a peer
+    /// constraint
+    ///
+    /// peers are checked against the organelle's `Directory` when the axon
+    /// starts. a mismatch then fails to start the axon, while synapses added
+    /// afterwards are checked on their own and reported as violations.
+    pub fn with_dendrite_peer(
+        mut self,
+        synapse: T::Synapse,
+        peer: PeerConstraint,
+    ) -> Self {
+        self.requirements.add_dendrite_peer(synapse, peer);
+        self
+    }
 
-        Ok(axon)
+    /// require the somas connected to a terminal to satisfy a peer
+    /// constraint
+    ///
+    /// see `with_dendrite_peer`
+    pub fn with_terminal_peer(
+        mut self,
+        synapse: T::Synapse,
+        peer: PeerConstraint,
+    ) -> Self {
+        self.requirements.add_terminal_peer(synapse, peer);
+        self
+    }
+
+    /// record the violations raised by a synapse of the running axon
+    fn raise(
+        &self,
+        peer: Uuid,
+        synapse: T::Synapse,
+        violations: Vec<ValidationIssue>,
+    ) {
+        if let Some(ref recorder) = self.recorder {
+            for violation in &violations {
+                recorder.violated(peer, synapse, violation);
+            }
+        }
+    }
+
+    fn origin(&self) -> SomaOrigin {
+        SomaOrigin::new(self.uuid, unsafe { intrinsics::type_name::<Self>() })
     }
 }
 
@@ -204,102 +898,126 @@ impl<T: Soma + 'static> Soma for Axon<T> {
 
     #[async(boxed)]
     fn probe(self, _settings: probe::Settings) -> Result<(Self, SomaData)> {
-        let terminals = self.terminals
+        let terminals = self.requirements.terminal_data();
+        let dendrites = self.requirements.dendrite_data();
+        let violations = self.requirements
+            .violations()
             .iter()
-            .map(|(synapse, &(ref constraint, ref requirement))| {
-                match constraint {
-                    &Constraint::One(_) => ConstraintData::One {
-                        variant: format!("{:?}", *synapse),
-                        soma: match requirement {
-                            &Requirement::MetOne(ref uuid) => *uuid,
-                            _ => panic!("axon failed to validate"),
-                        },
-                    },
-                    &Constraint::Variadic(_) => ConstraintData::Variadic {
-                        variant: format!("{:?}", *synapse),
-                        somas: match requirement {
-                            &Requirement::MetVariadic(ref somas) => {
-                                somas.clone()
-                            },
-                            _ => unreachable!(),
-                        },
-                    },
-                }
-            })
-            .collect();
-        let dendrites = self.dendrites
-            .iter()
-            .map(|(synapse, &(ref constraint, ref requirement))| {
-                match constraint {
-                    &Constraint::One(_) => ConstraintData::One {
-                        variant: format!("{:?}", *synapse),
-                        soma: match requirement {
-                            &Requirement::MetOne(ref uuid) => *uuid,
-                            _ => panic!("axon failed to validate"),
-                        },
-                    },
-                    &Constraint::Variadic(_) => ConstraintData::Variadic {
-                        variant: format!("{:?}", *synapse),
-                        somas: match requirement {
-                            &Requirement::MetVariadic(ref somas) => {
-                                somas.clone()
-                            },
-                            _ => unreachable!(),
-                        },
-                    },
-                }
-            })
+            .map(|violation| violation.message.clone())
             .collect();
 
-        let uuid = self.uuid.unwrap();
+        // the runtime fills in the uuid of an axon that has not started
+        let uuid = self.uuid.unwrap_or_else(Uuid::nil);
 
         Ok((
             self,
             SomaData::Axon {
                 terminals: terminals,
                 dendrites: dendrites,
+                violations: violations,
                 uuid: uuid,
                 name: unsafe { intrinsics::type_name::<Self>().to_string() },
+                state: None,
             },
         ))
     }
 
+    fn inspect(&self) -> Option<serde_json::Value> {
+        self.soma.inspect()
+    }
+
+    fn requirements(&self) -> Option<Requirements<Self::Synapse>> {
+        Some(self.requirements.clone())
+    }
+
+    #[async(boxed)]
+    fn on_stop(self) -> Result<()> {
+        let origin = self.origin();
+
+        await!(self.soma.on_stop()).map_err(|e| attribute_origin(e, origin))
+    }
+
     #[async(boxed)]
     fn update(mut self, imp: Impulse<T::Synapse>) -> Result<Self> {
         match imp {
             Impulse::AddDendrite(uuid, synapse, _) => {
-                self.add_dendrite(uuid, synapse)?;
+                let origin = self.origin();
+                let mut raised =
+                    self.requirements.add_dendrite(&origin, uuid, synapse)?;
+
+                // the directory is only known once the axon has started
+                if let Some(ref directory) = self.directory {
+                    raised.extend(self.requirements.check_dendrite_peer(
+                        &origin, directory, uuid, synapse,
+                    ));
+                }
+
+                self.raise(uuid, synapse, raised);
 
-                self.soma =
-                    await!(self.soma.update(imp)).map_err(|e| e.into())?;
+                self.soma = await!(self.soma.update(imp))
+                    .map_err(|e| attribute_origin(e, origin))?;
 
                 Ok(self)
             },
             Impulse::AddTerminal(uuid, synapse, _) => {
-                self.add_terminal(uuid, synapse)?;
+                let origin = self.origin();
+                let mut raised =
+                    self.requirements.add_terminal(&origin, uuid, synapse)?;
+
+                // the directory is only known once the axon has started
+                if let Some(ref directory) = self.directory {
+                    raised.extend(self.requirements.check_terminal_peer(
+                        &origin, directory, uuid, synapse,
+                    ));
+                }
+
+                self.raise(uuid, synapse, raised);
 
-                self.soma =
-                    await!(self.soma.update(imp)).map_err(|e| e.into())?;
+                self.soma = await!(self.soma.update(imp))
+                    .map_err(|e| attribute_origin(e, origin))?;
 
                 Ok(self)
             },
-            Impulse::Start(uuid, _, _) => {
-                self.start(uuid)?;
+            Impulse::RemoveDendrite(uuid, synapse) => {
+                let origin = self.origin();
+                let raised =
+                    self.requirements.remove_dendrite(&origin, uuid, synapse);
+                self.raise(uuid, synapse, raised);
 
-                self.soma =
-                    await!(self.soma.update(imp)).map_err(|e| e.into())?;
+                self.soma = await!(self.soma.update(imp))
+                    .map_err(|e| attribute_origin(e, origin))?;
 
                 Ok(self)
             },
+            Impulse::RemoveTerminal(uuid, synapse) => {
+                let origin = self.origin();
+                let raised =
+                    self.requirements.remove_terminal(&origin, uuid, synapse);
+                self.raise(uuid, synapse, raised);
+
+                self.soma = await!(self.soma.update(imp))
+                    .map_err(|e| attribute_origin(e, origin))?;
 
-            Impulse::Probe(settings, tx) => {
-                await!(self.perform_probe(settings, tx))
+                Ok(self)
+            },
+            Impulse::Start(ctx) => {
+                self.uuid = Some(ctx.uuid());
+                self.directory = Some(ctx.directory());
+                self.recorder = ctx.recorder();
+
+                let origin = self.origin();
+                self.requirements.start(&origin)?;
+                self.requirements.check_peers(&origin, &ctx.directory())?;
+
+                self.soma = await!(self.soma.update(Impulse::Start(ctx)))
+                    .map_err(|e| attribute_origin(e, origin))?;
+
+                Ok(self)
             },
 
-            Impulse::Stop | Impulse::Error(_) => {
+            Impulse::Probe(_, _) | Impulse::Stop | Impulse::Error(_) => {
                 bail!("unexpected impulse in axon")
             },
-            //_ => await!(self.soma.update(imp))?,
         }
     }
 }
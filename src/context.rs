@@ -0,0 +1,247 @@
+use std;
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+use futures::future::Either;
+use futures::prelude::*;
+use futures::unsync::{mpsc, oneshot};
+use tokio_core::reactor;
+use uuid::Uuid;
+
+use super::Error;
+use directory::Directory;
+use organelle::Control;
+use recorder::Recorder;
+use soma::{attribute_error, Impulse, Synapse};
+
+type Tasks = Rc<RefCell<Option<Vec<oneshot::Sender<()>>>>>;
+
+/// everything a soma needs to interact with its organelle once started
+///
+/// the context is handed to each soma with `Impulse::Start`. it can be cloned
+/// freely and moved into tasks spawned by the soma.
+pub struct SomaContext<R: Synapse> {
+    uuid: Uuid,
+    name: String,
+
+    tx: mpsc::Sender<Impulse<R>>,
+    handle: reactor::Handle,
+
+    tasks: Tasks,
+    control: Option<Rc<Any>>,
+    directory: Directory,
+    recorder: Option<Recorder>,
+}
+
+impl<R: Synapse + 'static> SomaContext<R> {
+    pub(crate) fn new(
+        uuid: Uuid,
+        name: String,
+        tx: mpsc::Sender<Impulse<R>>,
+        handle: reactor::Handle,
+    ) -> Self {
+        Self {
+            uuid: uuid,
+            name: name,
+
+            tx: tx,
+            handle: handle,
+
+            tasks: Rc::new(RefCell::new(Some(vec![]))),
+            control: None,
+            directory: Directory::default(),
+            recorder: None,
+        }
+    }
+
+    /// give the soma access to the identities of its peers
+    pub(crate) fn with_directory(self, directory: Directory) -> Self {
+        Self {
+            directory: directory,
+            ..self
+        }
+    }
+
+    /// hand the organelle's recorder down to nested organelles
+    pub(crate) fn with_recorder(self, recorder: Option<Recorder>) -> Self {
+        Self {
+            recorder: recorder,
+            ..self
+        }
+    }
+
+    /// give the soma access to the control plane of its organelle
+    pub(crate) fn with_control<S: Synapse + 'static>(
+        self,
+        control: Control<S>,
+    ) -> Self {
+        let control: Rc<Any> = Rc::new(control);
+
+        Self {
+            control: Some(control),
+            ..self
+        }
+    }
+
+    /// redirect the context through a different channel
+    ///
+    /// this is used when impulses sent by a soma need to be converted before
+    /// they reach its organelle.
+    pub(crate) fn redirect<T: Synapse + 'static>(
+        self,
+        tx: mpsc::Sender<Impulse<T>>,
+    ) -> SomaContext<T> {
+        SomaContext {
+            uuid: self.uuid,
+            name: self.name,
+
+            tx: tx,
+            handle: self.handle,
+
+            tasks: self.tasks,
+            control: self.control,
+            directory: self.directory,
+            recorder: self.recorder,
+        }
+    }
+
+    /// the recorder of the soma's organelle, if it has one
+    pub(crate) fn recorder(&self) -> Option<Recorder> {
+        self.recorder.clone()
+    }
+
+    /// cancel every task spawned with this context
+    pub(crate) fn cancel_tasks(&self) {
+        // dropping the senders cancels the tasks waiting on them
+        self.tasks.borrow_mut().take();
+    }
+
+    /// cancel every task spawned with this context once the guard is dropped
+    pub(crate) fn guard(&self) -> TaskGuard {
+        TaskGuard {
+            tasks: self.tasks.clone(),
+        }
+    }
+
+    /// unique id of the soma
+    pub fn uuid(&self) -> Uuid {
+        self.uuid
+    }
+
+    /// type name of the soma
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// handle to the event loop running the soma
+    pub fn handle(&self) -> reactor::Handle {
+        self.handle.clone()
+    }
+
+    /// sender for impulses to the soma's organelle
+    pub fn sender(&self) -> mpsc::Sender<Impulse<R>> {
+        self.tx.clone()
+    }
+
+    /// the control plane of the soma's organelle
+    ///
+    /// `S` must be the synapse type of the organelle, which may differ from
+    /// the soma's own synapse type. returns `None` if it does not match or if
+    /// the soma is not running inside an organelle.
+    pub fn control<S: Synapse + 'static>(&self) -> Option<Control<S>> {
+        self.control
+            .as_ref()
+            .and_then(|control| control.downcast_ref::<Control<S>>())
+            .cloned()
+    }
+
+    /// the identities of the somas in the soma's organelle
+    pub fn directory(&self) -> Directory {
+        self.directory.clone()
+    }
+
+    /// spawn a task that is cancelled when the soma stops
+    ///
+    /// errors returned by the task are attributed to the soma and reported to
+    /// the organelle.
+    pub fn spawn<F>(&self, task: F)
+    where
+        F: Future<Item = (), Error = Error> + 'static,
+    {
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+
+        if let Some(ref mut tasks) = *self.tasks.borrow_mut() {
+            tasks.push(cancel_tx);
+        } else {
+            // the soma has already stopped
+            return;
+        }
+
+        let ctx = self.clone();
+
+        self.handle.spawn(task.select2(cancel_rx).then(
+            move |result| -> std::result::Result<(), ()> {
+                if let Err(Either::A((e, _))) = result {
+                    ctx.report_error(attribute_error(e, ctx.uuid, &ctx.name));
+                }
+
+                Ok(())
+            },
+        ));
+    }
+
+    /// stop the organelle gracefully
+    pub fn stop(&self) {
+        self.send(Impulse::Stop);
+    }
+
+    /// terminate the organelle with an error
+    pub fn report_error<E: Into<Error>>(&self, e: E) {
+        self.send(Impulse::Error(e.into()));
+    }
+
+    fn send(&self, imp: Impulse<R>) {
+        // an organelle that is gone has nothing left to stop or report to
+        self.handle
+            .spawn(self.tx.clone().send(imp).map(|_| ()).map_err(|_| ()));
+    }
+}
+
+/// cancels the tasks spawned by a soma when its event loop exits
+pub(crate) struct TaskGuard {
+    tasks: Tasks,
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.tasks.borrow_mut().take();
+    }
+}
+
+impl<R: Synapse> Clone for SomaContext<R> {
+    fn clone(&self) -> Self {
+        Self {
+            uuid: self.uuid,
+            name: self.name.clone(),
+
+            tx: self.tx.clone(),
+            handle: self.handle.clone(),
+
+            tasks: self.tasks.clone(),
+            control: self.control.clone(),
+            directory: self.directory.clone(),
+            recorder: self.recorder.clone(),
+        }
+    }
+}
+
+impl<R: Synapse> fmt::Debug for SomaContext<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SomaContext")
+            .field("uuid", &self.uuid)
+            .field("name", &self.name)
+            .finish()
+    }
+}
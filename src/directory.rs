@@ -0,0 +1,104 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::intrinsics;
+use std::rc::Rc;
+
+use uuid::Uuid;
+
+use soma;
+
+/// identity of a soma as seen by the somas connected to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peer {
+    /// type name of the soma
+    pub name: String,
+    /// tags given to the soma by its organelle
+    pub tags: Vec<String>,
+}
+
+impl Peer {
+    /// check whether the soma has the given type name
+    ///
+    /// module paths are ignored, and axons also match the soma they wrap.
+    pub fn is_named(&self, name: &str) -> bool {
+        let name = soma::short_name(name);
+        let peer = soma::short_name(&self.name);
+
+        peer == name || peer == format!("Axon<{}>", name)
+    }
+
+    /// check whether the soma is of type `T`
+    pub fn is<T>(&self) -> bool {
+        self.is_named(unsafe { intrinsics::type_name::<T>() })
+    }
+
+    /// check whether the soma carries the given tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+}
+
+/// the identities of the somas in an organelle
+///
+/// each organelle keeps a directory of its somas. nested organelles fall back
+/// to the directory of their parent once started, so synapses that cross
+/// organelle boundaries can still be identified.
+#[derive(Debug, Clone, Default)]
+pub struct Directory {
+    peers: Rc<RefCell<HashMap<Uuid, Peer>>>,
+    parent: Rc<RefCell<Option<Directory>>>,
+}
+
+impl Directory {
+    /// look up a soma by uuid
+    pub fn get(&self, uuid: Uuid) -> Option<Peer> {
+        if let Some(peer) = self.peers.borrow().get(&uuid) {
+            return Some(peer.clone());
+        }
+
+        match *self.parent.borrow() {
+            Some(ref parent) => parent.get(uuid),
+            None => None,
+        }
+    }
+
+    /// record a soma, keeping its tags if it is being respawned
+    pub(crate) fn insert(&self, uuid: Uuid, name: String) {
+        let mut peers = self.peers.borrow_mut();
+
+        let tags = match peers.remove(&uuid) {
+            Some(peer) => peer.tags,
+            None => vec![],
+        };
+
+        peers.insert(
+            uuid,
+            Peer {
+                name: name,
+                tags: tags,
+            },
+        );
+    }
+
+    pub(crate) fn remove(&self, uuid: Uuid) {
+        self.peers.borrow_mut().remove(&uuid);
+    }
+
+    pub(crate) fn add_tag(&self, uuid: Uuid, tag: String) -> bool {
+        match self.peers.borrow_mut().get_mut(&uuid) {
+            Some(peer) => {
+                if !peer.tags.contains(&tag) {
+                    peer.tags.push(tag);
+                }
+
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// fall back to the directory of the enclosing organelle
+    pub(crate) fn set_parent(&self, parent: Directory) {
+        *self.parent.borrow_mut() = Some(parent);
+    }
+}
@@ -1,4 +1,5 @@
 #![warn(missing_docs)]
+#![recursion_limit = "256"]
 #![feature(core_intrinsics, proc_macro, conservative_impl_trait, generators)]
 
 //! Organelle - reactive architecture for emergent AI systems
@@ -6,11 +7,14 @@
 #[macro_use]
 extern crate error_chain;
 #[macro_use]
+extern crate organelle_derive;
+#[macro_use]
 extern crate serde_derive;
 
 extern crate bytes;
 extern crate futures_await as futures;
 extern crate serde;
+#[macro_use]
 extern crate serde_json;
 extern crate tokio;
 extern crate tokio_core;
@@ -22,8 +26,14 @@ extern crate hyper;
 extern crate open;
 
 mod axon;
+mod context;
+mod directory;
+mod mailbox;
+mod metrics;
 mod organelle;
+mod recorder;
 mod soma;
+mod validation;
 
 /// visualization soma used to visualize the results of a probe
 #[cfg(feature = "visualizer")]
@@ -32,10 +42,23 @@ pub mod visualizer;
 /// probe soma used to inspect the internal structure of an organelle
 pub mod probe;
 
-pub use axon::{Axon, Constraint};
-pub use organelle::Organelle;
-pub use probe::{ConstraintData, SomaData};
-pub use soma::{Impulse, Soma, Synapse};
+pub use axon::{Axon, Constraint, PeerConstraint, Requirements};
+pub use context::SomaContext;
+pub use directory::{Directory, Peer};
+pub use mailbox::{Mailbox, Overflow, DEFAULT_MAILBOX_CAPACITY};
+pub use organelle::{Control, Organelle, RestartStrategy, Supervisor};
+pub use probe::{
+    ConstraintData, MailboxData, MetricsData, PortData, SomaData,
+};
+pub use recorder::{ImpulseEvent, ImpulseKind, Recorder};
+pub use soma::{Impulse, Mapping, Mirror, Soma, SomaOrigin, Synapse};
+pub use validation::{IssueKind, ValidationIssue, ValidationReport};
+
+/// re-exports used by code generated with `organelle-derive`
+#[doc(hidden)]
+pub mod export {
+    pub use futures::unsync::{mpsc, oneshot};
+}
 
 /// organelle error
 error_chain! {
@@ -58,21 +81,89 @@ error_chain! {
     }
     errors {
         /// a soma returned an error when called into
-        SomaError {
+        SomaError(origin: SomaOrigin) {
             description("an error occurred while calling into a soma"),
-            display("an error occurred while calling into a soma")
+            display("an error occurred while calling into soma {}", origin)
+        }
+
+        /// a soma panicked while it was being called into
+        SomaPanicked(origin: SomaOrigin, message: String) {
+            description("a soma panicked"),
+            display("soma {} panicked - {}", origin, message)
         }
 
         /// axon failed to validate a synapse
-        InvalidSynapse(msg: String) {
+        InvalidSynapse(origin: SomaOrigin, msg: String) {
             description("invalid synapse given to somas"),
-            display("invalid synapse given to somas - {}", msg)
+            display("invalid synapse given to soma {} - {}", origin, msg)
         }
 
         /// axon is missing a synapse
-        MissingSynapse(msg: String) {
+        MissingSynapse(origin: SomaOrigin, msg: String) {
             description("missing synapse"),
-            display("invalid synapse - {}", msg)
+            display("soma {} is missing a synapse - {}", origin, msg)
+        }
+
+        /// a soma was given a synapse that has no mapping onto its own
+        UnmappedSynapse(origin: SomaOrigin, synapse: String) {
+            description("synapse has no mapping onto the soma"),
+            display("soma {} does not understand synapse {}", origin, synapse)
+        }
+
+        /// a supervised soma failed too many times within its restart window
+        RestartLimit(restarts: usize) {
+            description("supervised soma failed too many times"),
+            display(
+                "supervised soma failed after {} restarts within the restart \
+                 window",
+                restarts
+            )
+        }
+
+        /// a soma's mailbox overflowed with `Overflow::Error`
+        MailboxOverflow(origin: SomaOrigin, capacity: usize) {
+            description("soma mailbox overflowed"),
+            display(
+                "mailbox of soma {} overflowed its capacity of {}",
+                origin,
+                capacity
+            )
+        }
+
+        /// somas of an organelle did not stop within its stop timeout
+        StopTimeout(origin: SomaOrigin, timeout: std::time::Duration) {
+            description("somas did not stop in time"),
+            display(
+                "somas of {} did not stop within {:?} and were abandoned",
+                origin,
+                timeout
+            )
+        }
+
+        /// a probe document was written in an unsupported format version
+        ProbeVersion(version: u32) {
+            description("unsupported probe format version"),
+            display(
+                "probe format version {} is not supported (expected {})",
+                version,
+                probe::FORMAT_VERSION
+            )
+        }
+    }
+}
+
+impl Error {
+    /// get the error originally returned by the soma that failed
+    ///
+    /// errors returned by somas are chained to `ErrorKind::SomaError`, so this
+    /// can be used to recover the soma's own error type.
+    pub fn soma_error<E: std::error::Error + 'static>(&self) -> Option<&E> {
+        match self.0 {
+            ErrorKind::SomaError(_) => self.1
+                .next_error
+                .as_ref()
+                .and_then(|e| e.downcast_ref::<E>()),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,387 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use futures::prelude::*;
+use futures::task::{self, Task};
+
+use soma::{Impulse, Synapse};
+
+/// default number of impulses a soma's mailbox can hold
+pub const DEFAULT_MAILBOX_CAPACITY: usize = 10;
+
+/// what to do with an impulse that arrives while a soma's mailbox is full
+///
+/// wiring and lifecycle impulses are never dropped (see `essential`). unless
+/// the policy is `Block`, a full mailbox holds them past its capacity
+/// instead, so only probes and errors are subject to the policy.
+#[derive(
+    Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq, Describe,
+)]
+pub enum Overflow {
+    /// wait until the soma makes room, holding up the sender
+    #[serde(rename = "block")]
+    Block,
+    /// make room by dropping the oldest impulse in the mailbox
+    #[serde(rename = "drop_oldest")]
+    DropOldest,
+    /// drop the impulse that did not fit
+    #[serde(rename = "drop_newest")]
+    DropNewest,
+    /// drop the impulse that did not fit and fail with
+    /// `ErrorKind::MailboxOverflow`
+    #[serde(rename = "error")]
+    Error,
+}
+
+/// capacity and overflow policy of a soma's mailbox
+///
+/// the mailbox buffers the impulses that the organelle sends to a soma while
+/// the soma is busy handling an earlier one.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Mailbox {
+    capacity: usize,
+    overflow: Overflow,
+}
+
+impl Mailbox {
+    /// create a mailbox that blocks once it holds `capacity` impulses
+    ///
+    /// panics if `capacity` is zero, since the soma could never receive
+    /// anything.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "mailbox capacity must be at least 1");
+
+        Self {
+            capacity: capacity,
+            overflow: Overflow::Block,
+        }
+    }
+
+    /// set what happens once the mailbox is full
+    pub fn overflow(self, overflow: Overflow) -> Self {
+        Self {
+            overflow: overflow,
+            ..self
+        }
+    }
+
+    /// the number of impulses the mailbox can hold
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// what happens once the mailbox is full
+    pub fn overflow_policy(&self) -> Overflow {
+        self.overflow
+    }
+}
+
+/// whether an impulse must survive a full mailbox
+///
+/// losing a wiring or lifecycle impulse would leave the soma out of sync with
+/// its organelle. a dropped probe only makes the soma look unresponsive.
+pub(crate) fn essential<S: Synapse>(imp: &Impulse<S>) -> bool {
+    match *imp {
+        Impulse::AddDendrite(..)
+        | Impulse::AddTerminal(..)
+        | Impulse::RemoveDendrite(..)
+        | Impulse::RemoveTerminal(..) => true,
+
+        Impulse::Start(_) | Impulse::Stop => true,
+
+        Impulse::Probe(..) | Impulse::Error(_) => false,
+    }
+}
+
+impl Default for Mailbox {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAILBOX_CAPACITY)
+    }
+}
+
+/// the settings of a mailbox and how many impulses it has dropped
+#[derive(Debug, Clone)]
+pub(crate) struct Usage {
+    mailbox: Mailbox,
+    dropped: Rc<Cell<usize>>,
+    depth: Rc<Cell<usize>>,
+}
+
+impl Usage {
+    /// the mailbox the queue was created with
+    pub(crate) fn mailbox(&self) -> Mailbox {
+        self.mailbox
+    }
+
+    /// the number of impulses dropped because the mailbox was full
+    pub(crate) fn dropped(&self) -> usize {
+        self.dropped.get()
+    }
+
+    /// the number of impulses waiting in the mailbox
+    pub(crate) fn depth(&self) -> usize {
+        self.depth.get()
+    }
+}
+
+struct State<T> {
+    usage: Usage,
+
+    // essential items are never dropped
+    items: VecDeque<(T, bool)>,
+    closed: bool,
+    abandoned: bool,
+
+    reader: Option<Task>,
+    writers: Vec<Task>,
+}
+
+/// bounded queue that applies a mailbox's overflow policy
+///
+/// the queue is drained by a single reader, and closes once every writer is
+/// gone.
+pub(crate) struct Queue<T> {
+    state: Rc<RefCell<State<T>>>,
+}
+
+impl<T> Queue<T> {
+    pub(crate) fn new(mailbox: Mailbox) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State {
+                usage: Usage {
+                    mailbox: mailbox,
+                    dropped: Rc::new(Cell::new(0)),
+                    depth: Rc::new(Cell::new(0)),
+                },
+
+                items: VecDeque::new(),
+                closed: false,
+                abandoned: false,
+
+                reader: None,
+                writers: vec![],
+            })),
+        }
+    }
+
+    /// handle used to fill the queue
+    pub(crate) fn writer(&self) -> Writer<T> {
+        Writer {
+            closer: Rc::new(Closer {
+                state: self.state.clone(),
+            }),
+        }
+    }
+
+    /// stream of the queued items
+    pub(crate) fn receiver(&self) -> Receiver<T> {
+        Receiver {
+            state: self.state.clone(),
+        }
+    }
+
+    /// the settings and drop count of the queue
+    pub(crate) fn usage(&self) -> Usage {
+        self.state.borrow().usage.clone()
+    }
+}
+
+/// closes the queue once the last writer and pending push are gone
+struct Closer<T> {
+    state: Rc<RefCell<State<T>>>,
+}
+
+impl<T> Drop for Closer<T> {
+    fn drop(&mut self) {
+        let mut state = self.state.borrow_mut();
+
+        state.closed = true;
+
+        if let Some(reader) = state.reader.take() {
+            reader.notify();
+        }
+    }
+}
+
+/// fills a queue, closing it once dropped
+pub(crate) struct Writer<T> {
+    closer: Rc<Closer<T>>,
+}
+
+impl<T> Writer<T> {
+    /// queue an item, resolving to whether it was queued or dropped
+    ///
+    /// resolves to an error if the item was dropped by a mailbox with the
+    /// `Error` policy, or if the reader is gone.
+    pub(crate) fn push(&self, item: T, essential: bool) -> Push<T> {
+        Push {
+            closer: self.closer.clone(),
+            item: Some((item, essential)),
+        }
+    }
+}
+
+/// delivers impulses straight into the mailbox of a soma
+///
+/// impulses are converted to the soma's own synapse as they are delivered, so
+/// nothing sits between the organelle and the soma's mailbox.
+pub(crate) struct Inbox<S: Synapse> {
+    deliver: Rc<Fn(Impulse<S>) -> Box<Future<Item = (), Error = ()>>>,
+}
+
+impl<S: Synapse> Inbox<S> {
+    pub(crate) fn new<F>(deliver: F) -> Self
+    where
+        F: Fn(Impulse<S>) -> Box<Future<Item = (), Error = ()>> + 'static,
+    {
+        Self {
+            deliver: Rc::new(deliver),
+        }
+    }
+
+    /// deliver an impulse, resolving once it is in the mailbox
+    pub(crate) fn send(
+        &self,
+        imp: Impulse<S>,
+    ) -> Box<Future<Item = (), Error = ()>> {
+        (self.deliver)(imp)
+    }
+}
+
+impl<S: Synapse> Clone for Inbox<S> {
+    fn clone(&self) -> Self {
+        Self {
+            deliver: self.deliver.clone(),
+        }
+    }
+}
+
+/// why a push failed
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum PushError {
+    /// the mailbox was full and its policy is `Overflow::Error`
+    Overflow,
+    /// the reader is gone, so nothing will ever be taken out of the queue
+    Abandoned,
+}
+
+/// future that resolves once an item has been queued or dropped
+pub(crate) struct Push<T> {
+    // keeps the queue open until the item is in it
+    closer: Rc<Closer<T>>,
+    item: Option<(T, bool)>,
+}
+
+impl<T> Future for Push<T> {
+    type Item = bool;
+    type Error = PushError;
+
+    fn poll(&mut self) -> Poll<bool, PushError> {
+        let mut state = self.closer.state.borrow_mut();
+        let (item, essential) = self.item.take().expect("polled after ready");
+
+        if state.abandoned {
+            return Err(PushError::Abandoned);
+        }
+
+        let mailbox = state.usage.mailbox;
+        let dropped = state.usage.dropped.clone();
+
+        if state.items.len() < mailbox.capacity {
+            state.items.push_back((item, essential));
+        } else {
+            match mailbox.overflow {
+                Overflow::Block => {
+                    state.writers.push(task::current());
+                    self.item = Some((item, essential));
+
+                    return Ok(Async::NotReady);
+                },
+                Overflow::DropOldest => {
+                    let oldest = state.items.iter().position(|&(_, e)| !e);
+
+                    if let Some(i) = oldest {
+                        state.items.remove(i);
+                        dropped.set(dropped.get() + 1);
+                    }
+
+                    state.items.push_back((item, essential));
+                },
+                Overflow::DropNewest if !essential => {
+                    dropped.set(dropped.get() + 1);
+
+                    return Ok(Async::Ready(false));
+                },
+                Overflow::DropNewest => {
+                    state.items.push_back((item, essential))
+                },
+                Overflow::Error if !essential => {
+                    dropped.set(dropped.get() + 1);
+
+                    return Err(PushError::Overflow);
+                },
+                Overflow::Error => {
+                    state.items.push_back((item, essential))
+                },
+            }
+        }
+
+        state.usage.depth.set(state.items.len());
+
+        if let Some(reader) = state.reader.take() {
+            reader.notify();
+        }
+
+        Ok(Async::Ready(true))
+    }
+}
+
+/// stream of the items in a queue
+///
+/// the queue is abandoned once the receiver is dropped, failing every push
+/// from then on.
+pub(crate) struct Receiver<T> {
+    state: Rc<RefCell<State<T>>>,
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.state.borrow_mut();
+
+        state.abandoned = true;
+        state.items.clear();
+        state.usage.depth.set(0);
+
+        for writer in state.writers.drain(..) {
+            writer.notify();
+        }
+    }
+}
+
+impl<T> Stream for Receiver<T> {
+    type Item = T;
+    type Error = ();
+
+    fn poll(&mut self) -> Poll<Option<T>, ()> {
+        let mut state = self.state.borrow_mut();
+
+        match state.items.pop_front() {
+            Some((item, _)) => {
+                state.usage.depth.set(state.items.len());
+
+                for writer in state.writers.drain(..) {
+                    writer.notify();
+                }
+
+                Ok(Async::Ready(Some(item)))
+            },
+            None if state.closed => Ok(Async::Ready(None)),
+            None => {
+                state.reader = Some(task::current());
+
+                Ok(Async::NotReady)
+            },
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use futures::prelude::*;
+use uuid::Uuid;
+
+use mailbox::Usage;
+use probe::MetricsData;
+
+/// number of recent update latencies kept for the percentiles
+const LATENCY_SAMPLES: usize = 1024;
+
+#[derive(Debug, Default)]
+struct Counters {
+    impulses: u64,
+    updates: u64,
+    errors: u64,
+
+    update_time: Duration,
+    latencies: VecDeque<Duration>,
+}
+
+/// run-time metrics recorded for a soma by its organelle
+///
+/// the metrics are kept when a supervised soma is restarted, so they cover
+/// every instance of the soma.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Metrics {
+    counters: Rc<RefCell<Counters>>,
+}
+
+impl Metrics {
+    /// count an impulse taken from the soma's mailbox
+    pub(crate) fn received(&self) {
+        self.counters.borrow_mut().impulses += 1;
+    }
+
+    /// count an error reported by the soma
+    pub(crate) fn failed(&self) {
+        self.counters.borrow_mut().errors += 1;
+    }
+
+    /// time a call to the soma's `update`, counting it as an error if it fails
+    pub(crate) fn update<F: Future>(
+        &self,
+        update: F,
+    ) -> impl Future<Item = F::Item, Error = F::Error> {
+        let counters = self.counters.clone();
+        let started = Instant::now();
+
+        update.then(move |result| {
+            let elapsed = started.elapsed();
+            let mut counters = counters.borrow_mut();
+
+            counters.updates += 1;
+            counters.update_time += elapsed;
+
+            if counters.latencies.len() == LATENCY_SAMPLES {
+                counters.latencies.pop_front();
+            }
+            counters.latencies.push_back(elapsed);
+
+            if result.is_err() {
+                counters.errors += 1;
+            }
+
+            result
+        })
+    }
+
+    /// snapshot of the metrics for a probe
+    pub(crate) fn data(
+        &self,
+        soma: Uuid,
+        name: String,
+        mailbox: &Usage,
+    ) -> MetricsData {
+        let counters = self.counters.borrow();
+
+        let mut latencies =
+            counters.latencies.iter().cloned().collect::<Vec<_>>();
+        latencies.sort();
+
+        MetricsData {
+            soma: soma,
+            name: name,
+            impulses: counters.impulses,
+            updates: counters.updates,
+            update_time_us: micros(counters.update_time),
+            p50_update_us: percentile(&latencies, 50),
+            p99_update_us: percentile(&latencies, 99),
+            mailbox_depth: mailbox.depth(),
+            errors: counters.errors,
+        }
+    }
+}
+
+/// convert a duration to whole microseconds
+pub(crate) fn micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + duration.subsec_nanos() as u64 / 1_000
+}
+
+/// nearest-rank percentile of sorted latencies, in microseconds
+fn percentile(latencies: &[Duration], percent: usize) -> u64 {
+    if latencies.is_empty() {
+        return 0;
+    }
+
+    let rank = (percent * latencies.len() + 99) / 100;
+
+    micros(latencies[rank.max(1) - 1])
+}
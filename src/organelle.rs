@@ -1,18 +1,1164 @@
 use std;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
 use std::intrinsics;
 use std::mem;
+use std::rc::{Rc, Weak};
+use std::time;
 
-use futures::future;
+use futures::future::{self, Either};
 use futures::prelude::*;
-use futures::stream;
 use futures::unsync::{mpsc, oneshot};
 use tokio_core::reactor;
 use uuid::Uuid;
 
-use super::{Error, Result};
-use probe::{self, SomaData};
-use soma::{Impulse, Soma, Synapse};
+use super::{Error, ErrorKind, Result};
+use axon::{Constraint, Requirements};
+use context::SomaContext;
+use directory::Directory;
+use mailbox::{self, Inbox, Mailbox, PushError, Queue, Usage, Writer};
+use metrics::Metrics;
+use probe::{self, MailboxData, PortData, SomaData};
+use recorder::{Recorder, Tap};
+use validation::{IssueKind, ValidationIssue, ValidationReport};
+use soma::{
+    self,
+    attribute_error,
+    call_soma,
+    probe_soma,
+    Connections,
+    Impulse,
+    Mapping,
+    Mirror,
+    Soma,
+    SomaOrigin,
+    Synapse,
+};
+
+/// default amount of time an organelle waits for its somas to stop
+const DEFAULT_STOP_TIMEOUT: u64 = 5;
+
+/// default number of restarts allowed within the restart window
+const DEFAULT_MAX_RESTARTS: usize = 3;
+/// default length of the restart window in seconds
+const DEFAULT_RESTART_WINDOW: u64 = 5;
+
+/// the strategy used to restart supervised somas when one of them fails
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RestartStrategy {
+    /// only restart the soma that failed
+    OneForOne,
+    /// restart every supervised soma in the organelle
+    OneForAll,
+    /// restart the soma that failed and every supervised soma added after it
+    RestForOne,
+}
+
+/// settings used to restart supervised somas when they fail
+#[derive(Debug, Copy, Clone)]
+pub struct Supervisor {
+    strategy: RestartStrategy,
+    max_restarts: usize,
+    window: time::Duration,
+}
+
+impl Supervisor {
+    /// create a supervisor with the given restart strategy
+    pub fn new(strategy: RestartStrategy) -> Self {
+        Self {
+            strategy: strategy,
+            max_restarts: DEFAULT_MAX_RESTARTS,
+            window: time::Duration::from_secs(DEFAULT_RESTART_WINDOW),
+        }
+    }
+
+    /// give up after `max_restarts` restarts within the given window
+    ///
+    /// once the supervisor gives up, the error that caused the last failure
+    /// is propagated as if the soma was not supervised at all.
+    pub fn intensity(
+        self,
+        max_restarts: usize,
+        window: time::Duration,
+    ) -> Self {
+        Self {
+            max_restarts: max_restarts,
+            window: window,
+            ..self
+        }
+    }
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new(RestartStrategy::OneForOne)
+    }
+}
+
+/// rebuild a soma and spawn it under the same uuid
+type Respawn<S> = Rc<Fn(&Rc<RefCell<Registry<S>>>, Uuid)>;
+
+/// check the dendrites and terminals of a soma against its requirements
+///
+/// this gives the problems with the soma itself, followed by the problems
+/// found inside it if it is an organelle.
+type Audit<S> = Rc<
+    Fn(&[(Uuid, S)], &[(Uuid, S)], &Directory)
+        -> (Vec<(IssueKind, Option<S>, String)>, Vec<ValidationIssue>),
+>;
+
+struct SomaEntry<S: Synapse> {
+    tx: Inbox<S>,
+    stopped: Option<oneshot::Receiver<Result<()>>>,
+    started: bool,
+
+    name: String,
+    accepts: Rc<Fn(S) -> bool>,
+    audit: Option<Audit<S>>,
+    mailbox: Usage,
+    metrics: Metrics,
+
+    generation: usize,
+    respawn: Option<Respawn<S>>,
+}
+
+/// the somas and connections of an organelle
+///
+/// this is shared between the organelle and the tasks running its somas so
+/// that failed somas can be rebuilt and rewired while the organelle runs.
+struct Registry<S: Synapse> {
+    handle: reactor::Handle,
+
+    main_tx: mpsc::Sender<Impulse<S>>,
+    start_tx: Option<mpsc::Sender<Impulse<S>>>,
+
+    this: Weak<RefCell<Registry<S>>>,
+
+    nucleus: Option<Uuid>,
+    somas: HashMap<Uuid, SomaEntry<S>>,
+    order: Vec<Uuid>,
+    connections: Vec<(Uuid, Uuid, S)>,
+    /// synapses between inner somas and somas outside of the organelle,
+    /// given with `Organelle::add_dendrite` and `Organelle::add_terminal`
+    wired: Vec<(Uuid, Uuid, S)>,
+    directory: Directory,
+    tap: Tap,
+
+    supervisor: Supervisor,
+    restarts: VecDeque<time::Instant>,
+    stopping: bool,
+}
+
+impl<S: Synapse + 'static> Registry<S> {
+    fn new(
+        handle: reactor::Handle,
+        main_tx: mpsc::Sender<Impulse<S>>,
+    ) -> Self {
+        let directory = Directory::default();
+
+        Self {
+            handle: handle,
+
+            main_tx: main_tx,
+            start_tx: None,
+
+            this: Weak::new(),
+
+            nucleus: None,
+            somas: HashMap::new(),
+            order: vec![],
+            connections: vec![],
+            wired: vec![],
+            directory: directory.clone(),
+            tap: Tap::new(directory),
+
+            supervisor: Supervisor::default(),
+            restarts: VecDeque::new(),
+            stopping: false,
+        }
+    }
+
+    /// create the inbox that delivers the organelle's impulses to a soma
+    ///
+    /// impulses are converted to the soma's synapse and pushed straight into
+    /// its mailbox, so no tasks sit between the organelle and the soma. this
+    /// only covers the way down, see `redirect` for the way back up.
+    fn create_inbox<R, M>(
+        &self,
+        uuid: Uuid,
+        name: String,
+        mapping: Rc<M>,
+        mailbox: Writer<Impulse<R>>,
+        capacity: usize,
+        metrics: Metrics,
+    ) -> Inbox<S>
+    where
+        R: Synapse + 'static,
+        M: Mapping<S, R> + 'static,
+    {
+        let main_tx = self.main_tx.clone();
+        let handle = self.handle.clone();
+        let tap = self.tap.clone();
+        let origin = SomaOrigin::new(Some(uuid), name.clone());
+
+        Inbox::new(move |imp| {
+            let imp = match imp {
+                Impulse::Start(ctx) => Ok(Impulse::Start(Self::redirect(
+                    ctx,
+                    uuid,
+                    name.clone(),
+                    mapping.clone(),
+                    main_tx.clone(),
+                    capacity,
+                    metrics.clone(),
+                ))),
+                imp => Impulse::map_down(imp, &*mapping),
+            };
+
+            let imp = match imp {
+                Ok(imp) => imp,
+                Err(e) => {
+                    report(
+                        &handle,
+                        main_tx.clone(),
+                        attribute_error(e, uuid, &name),
+                    );
+
+                    return Box::new(future::ok(()));
+                },
+            };
+
+            let essential = mailbox::essential(&imp);
+            let event = tap.describe(&imp, uuid, &name);
+
+            let tap = tap.clone();
+            let handle = handle.clone();
+            let main_tx = main_tx.clone();
+            let origin = origin.clone();
+
+            Box::new(mailbox.push(imp, essential).then(
+                move |result| -> std::result::Result<(), ()> {
+                    let queued = match result {
+                        Ok(queued) => queued,
+                        Err(PushError::Overflow) => {
+                            report(
+                                &handle,
+                                main_tx,
+                                ErrorKind::MailboxOverflow(origin, capacity)
+                                    .into(),
+                            );
+
+                            false
+                        },
+                        // the soma has exited, which is handled elsewhere
+                        Err(PushError::Abandoned) => false,
+                    };
+
+                    // only impulses that made it into the mailbox are recorded
+                    if let (true, Some(event)) = (queued, event) {
+                        tap.delivered(event);
+                    }
+
+                    Ok(())
+                },
+            ))
+        })
+    }
+
+    /// convert the impulses a soma sends to its organelle
+    ///
+    /// unlike the inbox, this still spawns a task per soma, since the soma's
+    /// context sends its impulses over a plain channel. errors are attributed
+    /// to the soma and reported like any other failure in the organelle.
+    fn redirect<R, M>(
+        ctx: SomaContext<S>,
+        uuid: Uuid,
+        name: String,
+        mapping: Rc<M>,
+        main_tx: mpsc::Sender<Impulse<S>>,
+        capacity: usize,
+        metrics: Metrics,
+    ) -> SomaContext<R>
+    where
+        R: Synapse + 'static,
+        M: Mapping<S, R> + 'static,
+    {
+        let (tx, rx) = mpsc::channel::<Impulse<R>>(capacity);
+
+        let sender = ctx.sender();
+        let handle = ctx.handle();
+
+        ctx.handle().spawn(
+            sender
+                .send_all(rx.filter_map(move |imp| {
+                    let imp = match imp {
+                        Impulse::Error(e) => {
+                            metrics.failed();
+
+                            Err(e)
+                        },
+                        imp => Impulse::map_up(imp, &*mapping),
+                    };
+
+                    match imp {
+                        Ok(imp) => Some(imp),
+                        Err(e) => {
+                            report(
+                                &handle,
+                                main_tx.clone(),
+                                attribute_error(e, uuid, &name),
+                            );
+                            None
+                        },
+                    }
+                }).map_err(|_| unreachable!()))
+                .map(|_| ())
+                .map_err(|_| ()),
+        );
+
+        ctx.redirect(tx)
+    }
+
+    #[async]
+    fn run_soma<U: Soma + 'static>(
+        mut soma: U,
+        uuid: Uuid,
+        soma_rx: mailbox::Receiver<Impulse<U::Synapse>>,
+        metrics: Metrics,
+    ) -> std::result::Result<(), Error> {
+        let name = unsafe { intrinsics::type_name::<U>().to_string() };
+
+        // cancels the tasks spawned by the soma once it exits
+        let mut tasks = None;
+        let mut connections = Connections::new(uuid);
+
+        #[async]
+        for imp in soma_rx.map_err(|_| -> Error { unreachable!() }) {
+            metrics.received();
+
+            match imp {
+                Impulse::Start(ctx) => {
+                    tasks = Some(ctx.guard());
+
+                    soma = await!(metrics.update(call_soma(
+                        uuid,
+                        name.clone(),
+                        move || soma.update(Impulse::Start(ctx))
+                    )))?
+                },
+                Impulse::Stop => {
+                    tasks.take();
+
+                    await!(call_soma(uuid, name.clone(), move || {
+                        soma.on_stop()
+                    }))?;
+                    break;
+                },
+                Impulse::Probe(settings, tx) => {
+                    soma = await!(probe_soma(
+                        soma,
+                        uuid,
+                        name.clone(),
+                        connections.clone(),
+                        settings,
+                        tx
+                    ))?
+                },
+
+                _ => {
+                    connections.update(&imp);
+
+                    soma = await!(metrics.update(call_soma(
+                        uuid,
+                        name.clone(),
+                        move || soma.update(imp)
+                    )))?
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// spawn a soma under the given uuid, replacing any previous instance
+    fn spawn<U, M>(
+        registry: &Rc<RefCell<Self>>,
+        uuid: Uuid,
+        soma: U,
+        mapping: Rc<M>,
+        mailbox: Mailbox,
+    ) where
+        U: Soma + 'static,
+        M: Mapping<S, U::Synapse> + 'static,
+    {
+        let name = unsafe { intrinsics::type_name::<U>().to_string() };
+        let queue = Queue::new(mailbox);
+
+        let (handle, tx, metrics) = {
+            let registry = registry.borrow();
+
+            // a restarted soma keeps the metrics of its previous instance
+            let metrics = registry
+                .somas
+                .get(&uuid)
+                .map(|entry| entry.metrics.clone())
+                .unwrap_or_default();
+
+            let tx = registry.create_inbox(
+                uuid,
+                name.clone(),
+                mapping.clone(),
+                queue.writer(),
+                mailbox.capacity(),
+                metrics.clone(),
+            );
+
+            (registry.handle.clone(), tx, metrics)
+        };
+        let (stopped_tx, stopped_rx) = oneshot::channel();
+
+        let audit = soma.requirements().map(|requirements| {
+            let mapping = mapping.clone();
+
+            Rc::new(move |dendrites: &[(Uuid, S)],
+                          terminals: &[(Uuid, S)],
+                          directory: &Directory| {
+                let convert = |synapses: &[(Uuid, S)]| {
+                    synapses
+                        .iter()
+                        .filter_map(|&(uuid, synapse)| {
+                            mapping.synapse(synapse).map(|s| (uuid, s))
+                        })
+                        .collect::<Vec<_>>()
+                };
+
+                let dendrites = convert(dendrites);
+                let terminals = convert(terminals);
+
+                let issues = requirements
+                    .audit(&dendrites, &terminals, directory)
+                    .into_iter()
+                    .map(|(kind, synapse, message)| {
+                        (kind, mapping.parent_synapse(synapse), message)
+                    })
+                    .collect();
+
+                (issues, requirements.audit_inner(&dendrites, &terminals))
+            }) as Audit<S>
+        });
+
+        let accepts: Rc<Fn(S) -> bool> =
+            Rc::new(move |synapse| mapping.synapse(synapse).is_some());
+
+        let generation = {
+            let mut registry = registry.borrow_mut();
+
+            registry.directory.insert(uuid, name.clone());
+
+            let (generation, respawn) = match registry.somas.remove(&uuid) {
+                Some(entry) => (entry.generation + 1, entry.respawn),
+                None => {
+                    registry.order.push(uuid);
+                    (0, None)
+                },
+            };
+
+            registry.somas.insert(
+                uuid,
+                SomaEntry {
+                    tx: tx,
+                    stopped: Some(stopped_rx),
+                    started: false,
+
+                    name: name,
+                    accepts: accepts,
+                    audit: audit,
+                    mailbox: queue.usage(),
+                    metrics: metrics.clone(),
+
+                    generation: generation,
+                    respawn: respawn,
+                },
+            );
+
+            generation
+        };
+
+        let registry = registry.clone();
+
+        let soma_rx = queue.receiver();
+
+        handle.spawn(Self::run_soma(soma, uuid, soma_rx, metrics).then(
+            move |result| -> std::result::Result<(), ()> {
+                // a stopping organelle has nobody left to report to, so it
+                // collects the results of its somas itself
+                let result = if registry.borrow().stopping {
+                    match stopped_tx.send(result) {
+                        Ok(()) => Ok(()),
+                        Err(result) => result,
+                    }
+                } else {
+                    let _ = stopped_tx.send(Ok(()));
+                    result
+                };
+
+                if let Err(e) = result {
+                    Self::soma_failed(&registry, uuid, generation, e);
+                }
+
+                Ok(())
+            },
+        ));
+    }
+
+    fn sender(&self, uuid: Uuid) -> Result<Inbox<S>> {
+        if let Some(entry) = self.somas.get(&uuid) {
+            Ok(entry.tx.clone())
+        } else {
+            bail!("unable to find soma {}", uuid)
+        }
+    }
+
+    /// make sure that the soma has a mapping for the synapse
+    fn check_mapping(&self, uuid: Uuid, synapse: S) -> Result<()> {
+        if let Some(entry) = self.somas.get(&uuid) {
+            if !(entry.accepts)(synapse) {
+                bail!(ErrorKind::UnmappedSynapse(
+                    SomaOrigin::new(Some(uuid), entry.name.clone()),
+                    format!("{:?}", synapse)
+                ))
+            }
+
+            Ok(())
+        } else {
+            bail!("unable to find soma {}", uuid)
+        }
+    }
+
+    fn wire(&self, dendrite: Uuid, terminal: Uuid, synapse: S) -> Result<()> {
+        self.check_mapping(dendrite, synapse)?;
+        self.check_mapping(terminal, synapse)?;
+
+        let (tx, rx) = synapse.synapse();
+
+        let dendrite_sender = self.sender(dendrite)?;
+        let terminal_sender = self.sender(terminal)?;
+
+        self.handle.spawn(
+            dendrite_sender
+                .send(Impulse::AddTerminal(terminal, synapse, tx))
+                .map(|_| ())
+                .map_err(|_| ()),
+        );
+        self.handle.spawn(
+            terminal_sender
+                .send(Impulse::AddDendrite(dendrite, synapse, rx))
+                .map(|_| ())
+                .map_err(|_| ()),
+        );
+
+        Ok(())
+    }
+
+    fn start(
+        &mut self,
+        uuid: Uuid,
+        tx: mpsc::Sender<Impulse<S>>,
+    ) -> Result<()> {
+        let sender = self.sender(uuid)?;
+
+        let mut ctx = SomaContext::new(
+            uuid,
+            self.somas[&uuid].name.clone(),
+            tx,
+            self.handle.clone(),
+        );
+
+        if let Some(registry) = self.this.upgrade() {
+            ctx = ctx.with_control(Control { registry: registry });
+        }
+
+        ctx = ctx.with_directory(self.directory.clone())
+            .with_recorder(self.tap.recorder());
+
+        self.handle
+            .spawn(sender.send(Impulse::Start(ctx)).then(|_| future::ok(())));
+
+        if let Some(entry) = self.somas.get_mut(&uuid) {
+            entry.started = true;
+        }
+
+        Ok(())
+    }
+
+    /// tell the terminal soma that its dendrite is gone
+    fn unwire_terminal(&self, dendrite: Uuid, terminal: Uuid, synapse: S) {
+        self.notify(terminal, Impulse::RemoveDendrite(dendrite, synapse));
+    }
+
+    /// tell the dendrite soma that its terminal is gone
+    fn unwire_dendrite(&self, dendrite: Uuid, terminal: Uuid, synapse: S) {
+        self.notify(dendrite, Impulse::RemoveTerminal(terminal, synapse));
+    }
+
+    fn notify(&self, uuid: Uuid, imp: Impulse<S>) {
+        if let Ok(sender) = self.sender(uuid) {
+            // a soma that has exited no longer cares about its synapses
+            self.handle.spawn(sender.send(imp).map(|_| ()).map_err(|_| ()));
+        }
+    }
+
+    /// drop a recorded connection and tell both somas that it is gone
+    fn unwire(
+        &mut self,
+        dendrite: Uuid,
+        terminal: Uuid,
+        synapse: S,
+    ) -> Result<()> {
+        let connection = (dendrite, terminal, synapse);
+
+        if let Some(i) = self.connections.iter().position(|c| *c == connection)
+        {
+            self.connections.remove(i);
+        } else {
+            bail!(
+                "no {:?} synapse between {} and {}",
+                synapse,
+                dendrite,
+                terminal
+            )
+        }
+
+        self.unwire_dendrite(dendrite, terminal, synapse);
+        self.unwire_terminal(dendrite, terminal, synapse);
+
+        Ok(())
+    }
+
+    /// stop a soma and tell its peers that their synapses are gone
+    fn remove(&mut self, uuid: Uuid) -> Result<()> {
+        if self.nucleus == Some(uuid) {
+            bail!("unable to remove the nucleus of an organelle")
+        }
+
+        let entry = if let Some(entry) = self.somas.remove(&uuid) {
+            entry
+        } else {
+            bail!("unable to find soma {}", uuid)
+        };
+
+        self.order.retain(|soma| *soma != uuid);
+        self.wired.retain(|&(dendrite, terminal, _)| {
+            dendrite != uuid && terminal != uuid
+        });
+        self.directory.remove(uuid);
+
+        let (removed, connections) = self.connections
+            .iter()
+            .cloned()
+            .partition::<Vec<_>, _>(|&(dendrite, terminal, _)| {
+                dendrite == uuid || terminal == uuid
+            });
+
+        self.connections = connections;
+
+        for (dendrite, terminal, synapse) in removed {
+            if dendrite == uuid {
+                self.unwire_terminal(dendrite, terminal, synapse);
+            } else {
+                self.unwire_dendrite(dendrite, terminal, synapse);
+            }
+        }
+
+        self.handle.spawn(
+            entry.tx.send(Impulse::Stop).map(|_| ()).map_err(|_| ()),
+        );
+
+        Ok(())
+    }
+
+    /// check every soma against its requirements and the current wiring
+    ///
+    /// `ports` lists the somas that also receive synapses from outside the
+    /// organelle, so those synapses are not reported as missing. `external`
+    /// holds the synapses known to come from outside, routed to the inner
+    /// somas that handle them.
+    fn validate(
+        &self,
+        ports: &[(Uuid, S)],
+        external: &[(Uuid, Uuid, S)],
+    ) -> ValidationReport {
+        let mut report = ValidationReport::default();
+
+        let synapses = self.connections
+            .iter()
+            .chain(self.wired.iter())
+            .chain(external.iter())
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for uuid in &self.order {
+            let uuid = *uuid;
+            let entry = &self.somas[&uuid];
+            let origin = SomaOrigin::new(Some(uuid), entry.name.clone());
+
+            let terminals = synapses
+                .iter()
+                .filter(|&&(soma, _, _)| soma == uuid)
+                .map(|&(_, peer, synapse)| (peer, synapse))
+                .collect::<Vec<_>>();
+            let dendrites = synapses
+                .iter()
+                .filter(|&&(_, soma, _)| soma == uuid)
+                .map(|&(peer, _, synapse)| (peer, synapse))
+                .collect::<Vec<_>>();
+
+            let is_port = ports.iter().any(|&(soma, _)| soma == uuid);
+
+            if terminals.is_empty() && dendrites.is_empty()
+                && self.nucleus != Some(uuid) && !is_port
+            {
+                report.push(
+                    IssueKind::UnusedSoma,
+                    &origin,
+                    "soma has no synapses".to_string(),
+                );
+            }
+
+            if let Some(ref audit) = entry.audit {
+                let (issues, inner) =
+                    audit(&dendrites, &terminals, &self.directory);
+
+                for (kind, synapse, message) in issues {
+                    let outside = match (kind, synapse) {
+                        (IssueKind::MissingSynapse, Some(synapse))
+                        | (IssueKind::UnconnectedPort, Some(synapse)) => {
+                            ports.contains(&(uuid, synapse))
+                        },
+                        _ => false,
+                    };
+
+                    if !outside {
+                        report.push(kind, &origin, message);
+                    }
+                }
+
+                report.issues.extend(inner);
+            }
+        }
+
+        report
+    }
+
+    /// record a restart and check whether it exceeds the supervisor intensity
+    fn check_intensity(&mut self) -> bool {
+        let now = time::Instant::now();
+        let window = self.supervisor.window;
+
+        loop {
+            match self.restarts.front() {
+                Some(oldest) if now.duration_since(*oldest) > window => (),
+                _ => break,
+            }
+
+            self.restarts.pop_front();
+        }
+
+        if self.restarts.len() < self.supervisor.max_restarts {
+            self.restarts.push_back(now);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn is_supervised(&self, uuid: Uuid) -> bool {
+        self.somas
+            .get(&uuid)
+            .map_or(false, |entry| entry.respawn.is_some())
+    }
+
+    /// get the somas that need to be restarted when the given soma fails
+    fn restart_set(&self, failed: Uuid) -> Vec<Uuid> {
+        match self.supervisor.strategy {
+            RestartStrategy::OneForOne => vec![failed],
+            RestartStrategy::OneForAll => self.order
+                .iter()
+                .filter(|uuid| self.is_supervised(**uuid))
+                .cloned()
+                .collect(),
+            RestartStrategy::RestForOne => self.order
+                .iter()
+                .skip_while(|uuid| **uuid != failed)
+                .filter(|uuid| self.is_supervised(**uuid))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    fn soma_failed(
+        registry: &Rc<RefCell<Self>>,
+        uuid: Uuid,
+        generation: usize,
+        e: Error,
+    ) {
+        let (handle, main_tx, restart) = {
+            let mut registry = registry.borrow_mut();
+
+            match registry.somas.get(&uuid) {
+                Some(entry) if entry.generation == generation => (),
+                // this instance has already been replaced or removed
+                _ => return,
+            }
+
+            let restart = if registry.stopping || !registry.is_supervised(uuid)
+            {
+                Err(e)
+            } else if registry.check_intensity() {
+                Ok(registry.restart_set(uuid))
+            } else {
+                let restarts = registry.supervisor.max_restarts;
+
+                Err(Error::with_chain(e, ErrorKind::RestartLimit(restarts)))
+            };
+
+            (registry.handle.clone(), registry.main_tx.clone(), restart)
+        };
+
+        match restart {
+            Ok(uuids) => Self::restart(registry, uuid, uuids),
+            Err(e) => handle.spawn(
+                main_tx.send(Impulse::Error(e)).map(|_| ()).map_err(|_| ()),
+            ),
+        }
+    }
+
+    /// rebuild the given somas, rewire their synapses and start them again
+    fn restart(registry: &Rc<RefCell<Self>>, failed: Uuid, uuids: Vec<Uuid>) {
+        let (handle, respawns, connections) = {
+            let registry = registry.borrow();
+
+            let respawns = uuids
+                .iter()
+                .filter_map(|uuid| {
+                    registry.somas.get(uuid).and_then(|entry| {
+                        entry
+                            .respawn
+                            .clone()
+                            .map(|respawn| (*uuid, entry.tx.clone(), respawn))
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let connections = registry
+                .connections
+                .iter()
+                .filter(|&&(dendrite, terminal, _)| {
+                    uuids.contains(&dendrite) || uuids.contains(&terminal)
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            (registry.handle.clone(), respawns, connections)
+        };
+
+        for (uuid, old_tx, respawn) in respawns {
+            if uuid != failed {
+                // the old instance is still running, so stop it gracefully
+                handle.spawn(
+                    old_tx.send(Impulse::Stop).map(|_| ()).map_err(|_| ()),
+                );
+            }
+
+            respawn(registry, uuid);
+        }
+
+        let mut registry = registry.borrow_mut();
+
+        // a restarted soma that cannot be rewired or started is no better
+        // than one that keeps failing, so the error goes up to the parent
+        if let Err(e) = registry.resume(connections, uuids) {
+            report(&handle, registry.main_tx.clone(), e);
+        }
+    }
+
+    /// rewire and start somas that were rebuilt after a failure
+    fn resume(
+        &mut self,
+        connections: Vec<(Uuid, Uuid, S)>,
+        uuids: Vec<Uuid>,
+    ) -> Result<()> {
+        for (dendrite, terminal, synapse) in connections {
+            self.wire(dendrite, terminal, synapse)?;
+        }
+
+        if let Some(tx) = self.start_tx.clone() {
+            for uuid in uuids {
+                self.start(uuid, tx.clone())?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// probe a single soma, reporting it as unresponsive if it does not reply in
+/// time or drops the probe without replying
+///
+/// the soma is given the settings for the next level down, which have a
+/// shorter timeout than the one used to wait for it.
+#[async]
+fn probe_entry<S: Synapse + 'static>(
+    uuid: Uuid,
+    name: String,
+    sender: Inbox<S>,
+    settings: probe::Settings,
+    timeout: time::Duration,
+    handle: reactor::Handle,
+) -> Result<SomaData> {
+    let (tx, rx) = oneshot::channel();
+    let timeout = reactor::Timeout::new(timeout, &handle)?;
+
+    let probe = sender
+        .send(Impulse::Probe(settings, tx))
+        .map_err(|_| Error::from("unable to send probe impulse"))
+        .and_then(|_| rx.then(|reply| Ok(reply.ok())));
+
+    match await!(probe.select2(timeout)) {
+        Ok(Either::A((Some(data), _))) => Ok(data),
+        Ok(Either::A((None, _))) | Ok(Either::B(_)) => {
+            Ok(SomaData::Unresponsive {
+                uuid: uuid,
+                name: name,
+            })
+        },
+
+        Err(Either::A((e, _))) => bail!(e),
+        Err(Either::B((e, _))) => bail!(e),
+    }
+}
+
+/// describe the inner somas handling the exposed ports of an organelle
+fn port_data<S: Synapse>(ports: &HashMap<S, Uuid>) -> Vec<PortData> {
+    let mut data = ports
+        .iter()
+        .map(|(synapse, soma)| PortData {
+            variant: format!("{:?}", synapse),
+            soma: *soma,
+        })
+        .collect::<Vec<_>>();
+
+    data.sort_by(|a, b| a.variant.cmp(&b.variant));
+
+    data
+}
+
+/// report an error to an organelle without waiting for it to be received
+fn report<S: Synapse + 'static>(
+    handle: &reactor::Handle,
+    tx: mpsc::Sender<Impulse<S>>,
+    e: Error,
+) {
+    handle.spawn(tx.send(Impulse::Error(e)).map(|_| ()).map_err(|_| ()));
+}
+
+/// a handle used to add, remove, and connect somas while an organelle runs
+///
+/// somas added after the organelle has started are not started right away so
+/// that they can be connected to their peers first. call `start` once they
+/// have been wired up.
+#[derive(Clone)]
+pub struct Control<S: Synapse> {
+    registry: Rc<RefCell<Registry<S>>>,
+}
+
+impl<S: Synapse + 'static> Control<S> {
+    /// add a soma to the organelle
+    pub fn add_soma<U: Soma + 'static>(&self, soma: U) -> Uuid
+    where
+        Mirror: Mapping<S, U::Synapse>,
+    {
+        self.add_soma_with(soma, Mirror)
+    }
+
+    /// add a soma that only understands part of the organelle's synapses
+    ///
+    /// synapses that the mapping does not cover are rejected when they are
+    /// connected to the soma.
+    pub fn add_soma_with<U, M>(&self, soma: U, mapping: M) -> Uuid
+    where
+        U: Soma + 'static,
+        M: Mapping<S, U::Synapse> + 'static,
+    {
+        self.add_soma_with_mailbox(soma, mapping, Mailbox::default())
+    }
+
+    /// add a soma with its own mailbox capacity and overflow policy
+    ///
+    /// use `Mirror` as the mapping for somas that share the organelle's
+    /// synapse.
+    pub fn add_soma_with_mailbox<U, M>(
+        &self,
+        soma: U,
+        mapping: M,
+        mailbox: Mailbox,
+    ) -> Uuid
+    where
+        U: Soma + 'static,
+        M: Mapping<S, U::Synapse> + 'static,
+    {
+        let uuid = Uuid::new_v4();
+
+        Registry::spawn(
+            &self.registry,
+            uuid,
+            soma,
+            Rc::new(mapping),
+            mailbox,
+        );
+
+        uuid
+    }
+
+    /// add a soma that is rebuilt by the factory whenever it fails
+    ///
+    /// when a supervised soma fails, the organelle's supervisor decides which
+    /// somas to restart. restarted somas keep their uuid and are rewired with
+    /// the synapses they were originally connected with.
+    pub fn add_supervised_soma<U, F>(&self, factory: F) -> Uuid
+    where
+        U: Soma + 'static,
+        F: Fn() -> U + 'static,
+        Mirror: Mapping<S, U::Synapse>,
+    {
+        self.add_supervised_soma_with(factory, Mirror)
+    }
+
+    /// add a supervised soma that only understands part of the organelle's
+    /// synapses
+    pub fn add_supervised_soma_with<U, F, M>(
+        &self,
+        factory: F,
+        mapping: M,
+    ) -> Uuid
+    where
+        U: Soma + 'static,
+        F: Fn() -> U + 'static,
+        M: Mapping<S, U::Synapse> + 'static,
+    {
+        self.add_supervised_soma_with_mailbox(
+            factory,
+            mapping,
+            Mailbox::default(),
+        )
+    }
+
+    /// add a supervised soma with its own mailbox capacity and overflow
+    /// policy
+    ///
+    /// every instance built by the factory gets a mailbox with the same
+    /// settings.
+    pub fn add_supervised_soma_with_mailbox<U, F, M>(
+        &self,
+        factory: F,
+        mapping: M,
+        mailbox: Mailbox,
+    ) -> Uuid
+    where
+        U: Soma + 'static,
+        F: Fn() -> U + 'static,
+        M: Mapping<S, U::Synapse> + 'static,
+    {
+        let uuid = Uuid::new_v4();
+        let mapping = Rc::new(mapping);
+
+        Registry::spawn(
+            &self.registry,
+            uuid,
+            factory(),
+            mapping.clone(),
+            mailbox,
+        );
+
+        let respawn: Respawn<S> = Rc::new(
+            move |registry: &Rc<RefCell<Registry<S>>>, uuid: Uuid| {
+                Registry::spawn(
+                    registry,
+                    uuid,
+                    factory(),
+                    mapping.clone(),
+                    mailbox,
+                )
+            },
+        );
+
+        if let Some(entry) = self.registry.borrow_mut().somas.get_mut(&uuid) {
+            entry.respawn = Some(respawn);
+        }
+
+        uuid
+    }
+
+    /// connect two somas together using the specified synapse
+    ///
+    /// this can be used on a running organelle as well. both somas receive
+    /// the new synapse right away.
+    pub fn connect(
+        &self,
+        dendrite: Uuid,
+        terminal: Uuid,
+        synapse: S,
+    ) -> Result<()> {
+        let mut registry = self.registry.borrow_mut();
+
+        registry.wire(dendrite, terminal, synapse)?;
+        registry.connections.push((dendrite, terminal, synapse));
+
+        Ok(())
+    }
+
+    /// remove a synapse between two somas
+    ///
+    /// the dendrite soma receives a `RemoveTerminal` impulse and the terminal
+    /// soma receives a `RemoveDendrite` impulse.
+    pub fn disconnect(
+        &self,
+        dendrite: Uuid,
+        terminal: Uuid,
+        synapse: S,
+    ) -> Result<()> {
+        self.registry.borrow_mut().unwire(dendrite, terminal, synapse)
+    }
+
+    /// start a soma that was added after the organelle started
+    pub fn start(&self, uuid: Uuid) -> Result<()> {
+        let mut registry = self.registry.borrow_mut();
+
+        let tx = if let Some(ref tx) = registry.start_tx {
+            tx.clone()
+        } else {
+            bail!("organelle has not started yet")
+        };
+
+        match registry.somas.get(&uuid) {
+            Some(entry) if entry.started => {
+                bail!("soma {} has already started", uuid)
+            },
+            Some(_) => (),
+            None => bail!("unable to find soma {}", uuid),
+        }
+
+        registry.start(uuid, tx)
+    }
+
+    /// stop a soma and remove it from the organelle
+    ///
+    /// every soma connected to the removed soma receives a `RemoveDendrite`
+    /// or `RemoveTerminal` impulse for each synapse it shared with it.
+    pub fn remove_soma(&self, uuid: Uuid) -> Result<()> {
+        self.registry.borrow_mut().remove(uuid)
+    }
+}
 
 /// a soma designed to facilitate connections between other somas
 ///
@@ -24,36 +1170,50 @@ pub struct Organelle<T: Soma>
 where
     T: Soma,
 {
-    handle: reactor::Handle,
-
     uuid: Option<Uuid>,
+    name: String,
 
     main: Uuid,
-    main_tx: mpsc::Sender<Impulse<T::Synapse>>,
     main_rx: Option<mpsc::Receiver<Impulse<T::Synapse>>>,
 
-    somas: HashMap<Uuid, mpsc::Sender<Impulse<T::Synapse>>>,
+    registry: Rc<RefCell<Registry<T::Synapse>>>,
+
+    ports: Requirements<T::Synapse>,
+    dendrite_ports: HashMap<T::Synapse, Uuid>,
+    terminal_ports: HashMap<T::Synapse, Uuid>,
+
+    stop_timeout: time::Duration,
 }
 
 impl<T: Soma + 'static> Organelle<T> {
     /// create a new organelle
     pub fn new(main: T, handle: reactor::Handle) -> Self {
-        let (tx, rx) = mpsc::channel(100);
+        // somas report to the organelle through a channel that blocks like a
+        // default mailbox
+        let (tx, rx) = mpsc::channel(Mailbox::default().capacity());
 
         let mut organelle = Self {
-            handle: handle,
-
             uuid: None,
+            name: soma::short_name(unsafe { intrinsics::type_name::<Self>() }),
 
             main: Uuid::new_v4(),
-            main_tx: tx,
             main_rx: Some(rx),
 
-            somas: HashMap::new(),
+            registry: Rc::new(RefCell::new(Registry::new(handle, tx))),
+
+            ports: Requirements::new(vec![], vec![]),
+            dendrite_ports: HashMap::new(),
+            terminal_ports: HashMap::new(),
+
+            stop_timeout: time::Duration::from_secs(DEFAULT_STOP_TIMEOUT),
         };
 
+        let this = Rc::downgrade(&organelle.registry);
+        organelle.registry.borrow_mut().this = this;
+
         let main = organelle.add_soma(main);
         organelle.main = main;
+        organelle.registry.borrow_mut().nucleus = Some(main);
 
         organelle
     }
@@ -63,85 +1223,305 @@ impl<T: Soma + 'static> Organelle<T> {
         self.main
     }
 
-    fn create_soma_channel<R>(&mut self) -> (Uuid, mpsc::Receiver<Impulse<R>>)
-    where
-        R: Synapse + From<T::Synapse> + Into<T::Synapse> + 'static,
-        R::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
-            + Into<<T::Synapse as Synapse>::Dendrite>
-            + 'static,
-        R::Terminal: From<<T::Synapse as Synapse>::Terminal>
-            + Into<<T::Synapse as Synapse>::Terminal>
-            + 'static,
-    {
-        let uuid = Uuid::new_v4();
+    /// set the name used to identify this organelle in error paths
+    ///
+    /// errors raised by somas inside this organelle will include this name in
+    /// their `SomaOrigin::path`.
+    pub fn set_name<S: Into<String>>(&mut self, name: S) {
+        self.name = name.into();
+    }
 
-        let (tx, rx) = mpsc::channel::<Impulse<T::Synapse>>(10);
+    /// record every impulse delivered inside this organelle
+    ///
+    /// nested organelles use the same recorder once they start. impulses they
+    /// deliver before starting are only recorded if they were given a recorder
+    /// of their own.
+    pub fn set_recorder(&mut self, recorder: Recorder) {
+        self.registry.borrow().tap.set_recorder(recorder);
+    }
 
-        let (soma_tx, soma_rx) = mpsc::channel::<Impulse<R>>(1);
+    /// set the amount of time to wait for somas to stop
+    ///
+    /// when the organelle receives a stop impulse, it forwards it to each of
+    /// its somas and waits for them to finish `Soma::on_stop`. any somas that
+    /// have not stopped by the time this expires are abandoned, and the
+    /// organelle fails with `ErrorKind::StopTimeout`.
+    pub fn set_stop_timeout(&mut self, timeout: time::Duration) {
+        self.stop_timeout = timeout;
+    }
 
-        self.handle.spawn(
-            soma_tx
-                .send_all(rx.map(|imp| match imp {
-                    Impulse::Start(uuid, sender, handle) => {
-                        let (tx, rx) = mpsc::channel::<Impulse<R>>(1);
-
-                        handle.spawn(
-                            sender
-                                .send_all(rx.map(move |imp| {
-                                    Impulse::<T::Synapse>::convert_from(imp)
-                                }).map_err(|_| unreachable!()))
-                                .map(|_| ())
-                                .map_err(|_| ()),
-                        );
-
-                        Impulse::Start(uuid, tx, handle)
-                    },
-                    _ => Impulse::<R>::convert_from(imp),
-                }).map_err(|_| unreachable!()))
-                .map(|_| ())
-                .map_err(|_| ()),
-        );
+    /// set the supervisor used to restart supervised somas
+    pub fn set_supervisor(&mut self, supervisor: Supervisor) {
+        self.registry.borrow_mut().supervisor = supervisor;
+    }
+
+    /// check the wiring of the organelle before running it
+    ///
+    /// every soma with requirements, such as an `Axon`, is checked against the
+    /// synapses connected so far, and nested organelles are checked against
+    /// their exposed ports and their own wiring. all of the problems are
+    /// reported at once rather than one at a time as the somas start.
+    /// synapses handled by exposed ports come from outside the organelle, so
+    /// they are never reported as missing.
+    pub fn validate(&self) -> ValidationReport {
+        Self::audit(
+            &self.registry.borrow(),
+            &self.dendrite_ports,
+            &self.terminal_ports,
+            &[],
+            &[],
+        )
+    }
+
+    /// check the wiring given the synapses connected to the organelle
+    ///
+    /// each synapse is routed to the inner soma that would receive it, just
+    /// like `route` does once the organelle runs.
+    fn audit(
+        registry: &Registry<T::Synapse>,
+        dendrite_ports: &HashMap<T::Synapse, Uuid>,
+        terminal_ports: &HashMap<T::Synapse, Uuid>,
+        dendrites: &[(Uuid, T::Synapse)],
+        terminals: &[(Uuid, T::Synapse)],
+    ) -> ValidationReport {
+        let nucleus = registry.nucleus.unwrap();
+
+        let ports = dendrite_ports
+            .iter()
+            .chain(terminal_ports.iter())
+            .map(|(synapse, soma)| (*soma, *synapse))
+            .collect::<Vec<_>>();
+
+        let handler = |ports: &HashMap<T::Synapse, Uuid>, synapse: T::Synapse| {
+            ports.get(&synapse).cloned().unwrap_or(nucleus)
+        };
 
-        self.somas.insert(uuid, tx);
+        let external = dendrites
+            .iter()
+            .map(|&(peer, synapse)| {
+                (peer, handler(dendrite_ports, synapse), synapse)
+            })
+            .chain(terminals.iter().map(|&(peer, synapse)| {
+                (handler(terminal_ports, synapse), peer, synapse)
+            }))
+            .collect::<Vec<_>>();
 
-        (uuid, soma_rx)
+        registry.validate(&ports, &external)
     }
 
-    #[async]
-    fn run_soma<U: Soma + 'static>(
-        mut soma: U,
-        soma_rx: mpsc::Receiver<Impulse<U::Synapse>>,
-    ) -> std::result::Result<(), Error> {
-        #[async]
-        for imp in soma_rx.map_err(|_| -> Error { unreachable!() }) {
-            soma = await!(soma.update(imp)).map_err(|e| e.into())?;
+    /// tag a soma so that peer constraints can identify it
+    ///
+    /// see `PeerConstraint::Tag`
+    pub fn add_tag<S: Into<String>>(
+        &mut self,
+        uuid: Uuid,
+        tag: S,
+    ) -> Result<()> {
+        if !self.registry.borrow().directory.add_tag(uuid, tag.into()) {
+            bail!("unable to find soma {}", uuid)
+        }
+
+        Ok(())
+    }
+
+    /// expose a dendrite of an inner soma as a dendrite of the organelle
+    ///
+    /// dendrites given to the organelle for the constraint's synapse are
+    /// forwarded to the soma instead of the nucleus. the constraint is checked
+    /// the same way an `Axon` would check it.
+    pub fn expose_dendrite(
+        &mut self,
+        constraint: Constraint<T::Synapse>,
+        soma: Uuid,
+    ) -> Result<()> {
+        let synapse = constraint.synapse();
+
+        self.registry.borrow().sender(soma)?;
+
+        if self.dendrite_ports.contains_key(&synapse) {
+            bail!("dendrite {:?} is already exposed", synapse)
+        }
+
+        self.dendrite_ports.insert(synapse, soma);
+        self.ports.add_dendrite_constraint(constraint);
+
+        Ok(())
+    }
+
+    /// expose a terminal of an inner soma as a terminal of the organelle
+    ///
+    /// see `expose_dendrite`
+    pub fn expose_terminal(
+        &mut self,
+        constraint: Constraint<T::Synapse>,
+        soma: Uuid,
+    ) -> Result<()> {
+        let synapse = constraint.synapse();
+
+        self.registry.borrow().sender(soma)?;
+
+        if self.terminal_ports.contains_key(&synapse) {
+            bail!("terminal {:?} is already exposed", synapse)
         }
 
+        self.terminal_ports.insert(synapse, soma);
+        self.ports.add_terminal_constraint(constraint);
+
         Ok(())
     }
 
+    fn origin(&self) -> SomaOrigin {
+        SomaOrigin::new(self.uuid, unsafe { intrinsics::type_name::<Self>() })
+    }
+
+    /// find the soma that handles a synapse given to the organelle
+    ///
+    /// exposed synapses are checked against their port constraints, and
+    /// everything else goes to the nucleus.
+    fn route(
+        &mut self,
+        imp: &Impulse<T::Synapse>,
+    ) -> Result<Inbox<T::Synapse>> {
+        let origin = self.origin();
+
+        let port = match imp {
+            &Impulse::AddDendrite(uuid, synapse, _) => {
+                match self.dendrite_ports.get(&synapse) {
+                    Some(port) => {
+                        self.ports.add_dendrite(&origin, uuid, synapse)?;
+                        Some(*port)
+                    },
+                    None => None,
+                }
+            },
+            &Impulse::AddTerminal(uuid, synapse, _) => {
+                match self.terminal_ports.get(&synapse) {
+                    Some(port) => {
+                        self.ports.add_terminal(&origin, uuid, synapse)?;
+                        Some(*port)
+                    },
+                    None => None,
+                }
+            },
+            &Impulse::RemoveDendrite(uuid, synapse) => {
+                match self.dendrite_ports.get(&synapse) {
+                    Some(port) => {
+                        self.ports.remove_dendrite(&origin, uuid, synapse);
+                        Some(*port)
+                    },
+                    None => None,
+                }
+            },
+            &Impulse::RemoveTerminal(uuid, synapse) => {
+                match self.terminal_ports.get(&synapse) {
+                    Some(port) => {
+                        self.ports.remove_terminal(&origin, uuid, synapse);
+                        Some(*port)
+                    },
+                    None => None,
+                }
+            },
+            _ => None,
+        };
+
+        self.registry.borrow().sender(port.unwrap_or(self.main))
+    }
+
+    /// get a handle that can modify the organelle after it has started
+    pub fn control(&self) -> Control<T::Synapse> {
+        Control {
+            registry: self.registry.clone(),
+        }
+    }
+
     /// add a soma to the organelle
     pub fn add_soma<U: Soma + 'static>(&mut self, soma: U) -> Uuid
     where
-        U::Synapse: From<T::Synapse> + Into<T::Synapse>,
-        <U::Synapse as Synapse>::Dendrite: From<<T::Synapse as Synapse>::Dendrite>
-            + Into<<T::Synapse as Synapse>::Dendrite>,
-        <U::Synapse as Synapse>::Terminal: From<<T::Synapse as Synapse>::Terminal>
-            + Into<<T::Synapse as Synapse>::Terminal>,
+        Mirror: Mapping<T::Synapse, U::Synapse>,
     {
-        let (uuid, soma_rx) = self.create_soma_channel::<U::Synapse>();
+        self.control().add_soma(soma)
+    }
 
-        let main_tx = self.main_tx.clone();
+    /// add a soma that only understands part of the organelle's synapses
+    ///
+    /// see `Control::add_soma_with`
+    pub fn add_soma_with<U, M>(&mut self, soma: U, mapping: M) -> Uuid
+    where
+        U: Soma + 'static,
+        M: Mapping<T::Synapse, U::Synapse> + 'static,
+    {
+        self.control().add_soma_with(soma, mapping)
+    }
 
-        self.handle
-            .spawn(Self::run_soma(soma, soma_rx).or_else(move |e| {
-                main_tx
-                    .send(Impulse::Error(e.into()))
-                    .map(|_| ())
-                    .map_err(|_| ())
-            }));
+    /// add a soma with its own mailbox capacity and overflow policy
+    ///
+    /// see `Control::add_soma_with_mailbox`
+    pub fn add_soma_with_mailbox<U, M>(
+        &mut self,
+        soma: U,
+        mapping: M,
+        mailbox: Mailbox,
+    ) -> Uuid
+    where
+        U: Soma + 'static,
+        M: Mapping<T::Synapse, U::Synapse> + 'static,
+    {
+        self.control().add_soma_with_mailbox(soma, mapping, mailbox)
+    }
 
-        uuid
+    /// add a soma that is rebuilt by the factory whenever it fails
+    ///
+    /// see `Control::add_supervised_soma`
+    pub fn add_supervised_soma<U, F>(&mut self, factory: F) -> Uuid
+    where
+        U: Soma + 'static,
+        F: Fn() -> U + 'static,
+        Mirror: Mapping<T::Synapse, U::Synapse>,
+    {
+        self.control().add_supervised_soma(factory)
+    }
+
+    /// add a supervised soma that only understands part of the organelle's
+    /// synapses
+    ///
+    /// see `Control::add_supervised_soma_with`
+    pub fn add_supervised_soma_with<U, F, M>(
+        &mut self,
+        factory: F,
+        mapping: M,
+    ) -> Uuid
+    where
+        U: Soma + 'static,
+        F: Fn() -> U + 'static,
+        M: Mapping<T::Synapse, U::Synapse> + 'static,
+    {
+        self.control().add_supervised_soma_with(factory, mapping)
+    }
+
+    /// add a supervised soma with its own mailbox capacity and overflow
+    /// policy
+    ///
+    /// see `Control::add_supervised_soma_with_mailbox`
+    pub fn add_supervised_soma_with_mailbox<U, F, M>(
+        &mut self,
+        factory: F,
+        mapping: M,
+        mailbox: Mailbox,
+    ) -> Uuid
+    where
+        U: Soma + 'static,
+        F: Fn() -> U + 'static,
+        M: Mapping<T::Synapse, U::Synapse> + 'static,
+    {
+        self.control()
+            .add_supervised_soma_with_mailbox(factory, mapping, mailbox)
+    }
+
+    /// remove a soma from the organelle
+    ///
+    /// see `Control::remove_soma`
+    pub fn remove_soma(&mut self, uuid: Uuid) -> Result<()> {
+        self.control().remove_soma(uuid)
     }
 
     /// connect two somas together using the specified synapse
@@ -151,12 +1531,19 @@ impl<T: Soma + 'static> Organelle<T> {
         terminal: Uuid,
         synapse: T::Synapse,
     ) -> Result<()> {
-        let (tx, rx) = synapse.synapse();
-
-        self.add_terminal((terminal, tx), dendrite, synapse)?;
-        self.add_dendrite((dendrite, rx), terminal, synapse)?;
+        self.control().connect(dendrite, terminal, synapse)
+    }
 
-        Ok(())
+    /// remove a synapse between two somas
+    ///
+    /// see `Control::disconnect`
+    pub fn disconnect(
+        &self,
+        dendrite: Uuid,
+        terminal: Uuid,
+        synapse: T::Synapse,
+    ) -> Result<()> {
+        self.control().disconnect(dendrite, terminal, synapse)
     }
 
     /// send a dendrite to the specified soma
@@ -166,19 +1553,19 @@ impl<T: Soma + 'static> Organelle<T> {
         terminal: Uuid,
         synapse: T::Synapse,
     ) -> Result<()> {
-        let terminal_sender = if let Some(sender) = self.somas.get(&terminal) {
-            sender.clone()
-        } else {
-            bail!("unable to find terminal")
-        };
+        let mut registry = self.registry.borrow_mut();
 
-        self.handle.spawn(
+        let terminal_sender = registry
+            .sender(terminal)
+            .map_err(|_| Error::from("unable to find terminal"))?;
+
+        registry.wired.push((dendrite.0, terminal, synapse));
+
+        registry.handle.spawn(
             terminal_sender
                 .send(Impulse::AddDendrite(dendrite.0, synapse, dendrite.1))
                 .map(|_| ())
-                .map_err(|_| {
-                    eprintln!("unable to add dendrite");
-                }),
+                .map_err(|_| ()),
         );
 
         Ok(())
@@ -191,54 +1578,41 @@ impl<T: Soma + 'static> Organelle<T> {
         dendrite: Uuid,
         synapse: T::Synapse,
     ) -> Result<()> {
-        let dendrite_sender = if let Some(sender) = self.somas.get(&dendrite) {
-            sender.clone()
-        } else {
-            bail!("unable to find dendrite")
-        };
+        let mut registry = self.registry.borrow_mut();
 
-        self.handle.spawn(
+        let dendrite_sender = registry
+            .sender(dendrite)
+            .map_err(|_| Error::from("unable to find dendrite"))?;
+
+        registry.wired.push((dendrite, terminal.0, synapse));
+
+        registry.handle.spawn(
             dendrite_sender
                 .send(Impulse::AddTerminal(terminal.0, synapse, terminal.1))
                 .map(|_| ())
-                .map_err(|_| {
-                    eprintln!("unable to add terminal");
-                }),
+                .map_err(|_| ()),
         );
 
         Ok(())
     }
 
-    fn start_all(
-        &self,
-        tx: mpsc::Sender<Impulse<T::Synapse>>,
-        handle: reactor::Handle,
-    ) -> Result<()> {
-        for (uuid, sender) in &self.somas {
-            self.handle.spawn(
-                sender
-                    .clone()
-                    .send(Impulse::Start(*uuid, tx.clone(), handle.clone()))
-                    .then(|_| future::ok(())),
-            );
-        }
+    fn start_all(&self, tx: mpsc::Sender<Impulse<T::Synapse>>) -> Result<()> {
+        let mut registry = self.registry.borrow_mut();
 
-        Ok(())
-    }
+        registry.start_tx = Some(tx.clone());
 
-    #[async]
-    fn perform_probe(
-        self,
-        settings: probe::Settings,
-        tx: oneshot::Sender<SomaData>,
-    ) -> Result<Self> {
-        let (organelle, data) = await!(self.probe(settings))?;
+        for uuid in registry.order.clone() {
+            let started = registry
+                .somas
+                .get(&uuid)
+                .map_or(true, |entry| entry.started);
 
-        if let Err(_) = tx.send(data) {
-            // rx does not care anymore
+            if !started {
+                registry.start(uuid, tx.clone())?;
+            }
         }
 
-        Ok(organelle)
+        Ok(())
     }
 }
 
@@ -246,27 +1620,95 @@ impl<T: Soma + 'static> Soma for Organelle<T> {
     type Synapse = T::Synapse;
     type Error = Error;
 
+    fn requirements(&self) -> Option<Requirements<Self::Synapse>> {
+        let registry = self.registry.clone();
+        let dendrite_ports = self.dendrite_ports.clone();
+        let terminal_ports = self.terminal_ports.clone();
+        let name = self.name.clone();
+
+        // the ports are checked like an axon, and everything else is left to
+        // the somas inside
+        let requirements =
+            self.ports.clone().with_inner(move |dendrites, terminals| {
+                let report = Self::audit(
+                    &registry.borrow(),
+                    &dendrite_ports,
+                    &terminal_ports,
+                    dendrites,
+                    terminals,
+                );
+
+                report
+                    .issues
+                    .into_iter()
+                    .map(|mut issue| {
+                        issue.origin.path.insert(0, name.clone());
+                        issue
+                    })
+                    .collect()
+            });
+
+        Some(requirements)
+    }
+
     #[async(boxed)]
     fn probe(self, settings: probe::Settings) -> Result<(Self, SomaData)> {
-        let results = await!(
-            stream::iter_ok(self.somas.clone())
-                .map(move |(uuid, sender)| {
-                    let (tx, rx) = oneshot::channel();
-
-                    sender
-                        .send(Impulse::Probe(settings.clone(), tx))
-                        .map_err(|_| {
-                            Error::from("unable to send probe impulse")
-                        })
-                        .and_then(move |_| {
-                            rx.map(move |rx| (uuid, rx)).map_err(|e| e.into())
-                        })
+        let nucleus_uuid = self.nucleus();
+
+        // past the maximum depth, only the nucleus is probed
+        let walk = settings.within_depth();
+
+        let (handle, somas, mut mailboxes) = {
+            let registry = self.registry.borrow();
+
+            let entries = registry
+                .somas
+                .iter()
+                .filter(|&(uuid, _)| walk || *uuid == nucleus_uuid)
+                .collect::<Vec<_>>();
+
+            let somas = entries
+                .iter()
+                .map(|&(uuid, entry)| {
+                    (*uuid, entry.name.clone(), entry.tx.clone())
                 })
-                .collect()
-                .and_then(|receivers| future::join_all(receivers))
-        )?;
+                .collect::<Vec<_>>();
+            let mailboxes = entries
+                .iter()
+                .map(|&(uuid, entry)| {
+                    let mailbox = entry.mailbox.mailbox();
+
+                    MailboxData {
+                        soma: *uuid,
+                        capacity: mailbox.capacity(),
+                        overflow: mailbox.overflow_policy(),
+                        dropped: entry.mailbox.dropped(),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            (registry.handle.clone(), somas, mailboxes)
+        };
+
+        let nested = settings.nested();
+        let timeout = settings.timeout;
+
+        let results = await!(future::join_all(
+            somas
+                .into_iter()
+                .map(|(uuid, name, sender)| {
+                    probe_entry(
+                        uuid,
+                        name,
+                        sender,
+                        nested.clone(),
+                        timeout,
+                        handle.clone(),
+                    ).map(move |data| (uuid, data))
+                })
+                .collect::<Vec<_>>()
+        ))?;
 
-        let nucleus_uuid = self.nucleus();
         let mut nucleus = None;
 
         let somas = results
@@ -275,93 +1717,194 @@ impl<T: Soma + 'static> Soma for Organelle<T> {
                 if uuid == nucleus_uuid {
                     nucleus = Some(data);
                     None
-                } else {
+                } else if settings.matches(&data) {
                     Some(data)
+                } else {
+                    mailboxes.retain(|mailbox| mailbox.soma != uuid);
+                    None
                 }
             })
             .collect();
 
-        let uuid = self.uuid.unwrap();
+        // metrics are taken once the somas have answered, so they include
+        // everything handled before the probe
+        let metrics = if settings.include_metrics {
+            let registry = self.registry.borrow();
+
+            mailboxes
+                .iter()
+                .filter_map(|mailbox| {
+                    registry.somas.get(&mailbox.soma).map(|entry| {
+                        entry.metrics.data(
+                            mailbox.soma,
+                            entry.name.clone(),
+                            &entry.mailbox,
+                        )
+                    })
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+
+        let nucleus = nucleus.ok_or_else(|| {
+            Error::from("nucleus did not respond to the probe")
+        })?;
+        let dendrite_ports = port_data(&self.dendrite_ports);
+        let terminal_ports = port_data(&self.terminal_ports);
+        // the runtime fills in the uuid of an organelle that has not started
+        let uuid = self.uuid.unwrap_or_else(Uuid::nil);
 
         Ok((
             self,
             SomaData::Organelle {
-                nucleus: Box::new(nucleus.unwrap()),
+                nucleus: Box::new(nucleus),
                 somas: somas,
+                mailboxes: mailboxes,
+                metrics: metrics,
+                dendrite_ports: dendrite_ports,
+                terminal_ports: terminal_ports,
                 uuid: uuid,
                 name: unsafe { intrinsics::type_name::<Self>().into() },
+                state: None,
             },
         ))
     }
 
+    #[async(boxed)]
+    fn on_stop(self) -> Result<()> {
+        let origin = self.origin();
+        let name = self.name.clone();
+        let stop_timeout = self.stop_timeout;
+
+        let (handle, tap, senders, stopped) = {
+            let mut registry = self.registry.borrow_mut();
+
+            registry.stopping = true;
+
+            let senders = registry
+                .somas
+                .values()
+                .map(|entry| entry.tx.clone())
+                .collect::<Vec<_>>();
+            let stopped = registry
+                .somas
+                .values_mut()
+                .filter_map(|entry| mem::replace(&mut entry.stopped, None))
+                .collect::<Vec<_>>();
+
+            (registry.handle.clone(), registry.tap.clone(), senders, stopped)
+        };
+
+        let timeout = reactor::Timeout::new(stop_timeout, &handle)?;
+
+        let signals = senders
+            .into_iter()
+            .map(|sender| {
+                sender.send(Impulse::Stop).then(|_| -> Result<()> { Ok(()) })
+            })
+            .collect::<Vec<_>>();
+
+        // somas that were already gone have nothing left to report
+        let drained = future::join_all(signals).and_then(move |_| {
+            future::join_all(stopped.into_iter().map(|rx| {
+                rx.then(|result| -> Result<Result<()>> {
+                    Ok(result.unwrap_or(Ok(())))
+                })
+            }))
+        });
+
+        match await!(drained.select2(timeout)) {
+            Ok(Either::A((results, _))) => {
+                match results.into_iter().find(|result| result.is_err()) {
+                    // errors raised while stopping bypass the main loop, so
+                    // they are recorded and attributed here
+                    Some(Err(e)) => {
+                        tap.reported(&e);
+
+                        Err(soma::nest_error(e, &name))
+                    },
+                    _ => Ok(()),
+                }
+            },
+            // like any other error of the organelle, the timeout is recorded
+            // by the organelle running it
+            Ok(Either::B(_)) => {
+                bail!(ErrorKind::StopTimeout(origin, stop_timeout))
+            },
+
+            Err(Either::A((e, _))) => bail!(e),
+            Err(Either::B((e, _))) => bail!(e),
+        }
+    }
+
     #[async(boxed)]
     fn update(mut self, imp: Impulse<T::Synapse>) -> Result<Self> {
         match imp {
-            Impulse::AddDendrite(_, _, _) | Impulse::AddTerminal(_, _, _) => {
+            Impulse::AddDendrite(_, _, _)
+            | Impulse::AddTerminal(_, _, _)
+            | Impulse::RemoveDendrite(_, _)
+            | Impulse::RemoveTerminal(_, _) => {
+                let soma = self.route(&imp)?;
+
                 await!(
-                    self.somas
-                        .get(&self.nucleus())
-                        .unwrap()
-                        .clone()
-                        .send(imp)
+                    soma.send(imp)
                         .map_err(|_| Error::from("unable to forward impulse"))
                 )?;
                 Ok(self)
             },
-            Impulse::Start(uuid, tx, handle) => {
-                self.uuid = Some(uuid);
+            Impulse::Start(ctx) => {
+                self.uuid = Some(ctx.uuid());
+
+                let tap = {
+                    let registry = self.registry.borrow();
+
+                    registry.directory.set_parent(ctx.directory());
+                    registry.tap.set_organelle(ctx.uuid(), self.name.clone());
+
+                    // nested organelles record to the outermost recorder
+                    if let (None, Some(recorder)) =
+                        (registry.tap.recorder(), ctx.recorder())
+                    {
+                        registry.tap.set_recorder(recorder);
+                    }
+
+                    registry.tap.clone()
+                };
 
-                let rx = mem::replace(&mut self.main_rx, None).unwrap();
+                let tx = ctx.sender();
+                let handle = ctx.handle();
+
+                let origin = self.origin();
+                self.ports.start(&origin)?;
+
+                let rx = mem::replace(&mut self.main_rx, None)
+                    .ok_or_else(|| Error::from("organelle was started twice"))?;
+
+                let name = self.name.clone();
 
                 handle.spawn(
                     tx.clone()
-                        .send_all(rx.map_err(|_| unreachable!()))
+                        .send_all(rx.map(move |imp| match imp {
+                            Impulse::Error(e) => {
+                                tap.reported(&e);
+
+                                Impulse::Error(soma::nest_error(e, &name))
+                            },
+                            imp => imp,
+                        }).map_err(|_| unreachable!()))
                         .map(|_| ())
                         .map_err(|_| ()),
                 );
 
-                self.start_all(tx, handle)?;
+                self.start_all(tx)?;
 
                 Ok(self)
             },
 
-            Impulse::Probe(settings, tx) => {
-                await!(self.perform_probe(settings, tx))
+            Impulse::Probe(_, _) | Impulse::Stop | Impulse::Error(_) => {
+                bail!("unexpected impulse in organelle")
             },
-
-            Impulse::Stop | Impulse::Error(_) => unreachable!(),
-        }
-    }
-
-    /// convert this soma into a future that can be passed to an event loop
-    #[async(boxed)]
-    fn run(mut self, handle: reactor::Handle) -> Result<()>
-    where
-        Self: 'static,
-    {
-        let (tx, rx) = mpsc::channel(1);
-
-        let uuid = Uuid::new_v4();
-
-        await!(
-            tx.clone()
-                .send(Impulse::Start(uuid, tx, handle))
-                .map_err(|_| Error::from("unable to send start signal"))
-        )?;
-
-        #[async]
-        for imp in rx.map_err(|_| -> Error { unreachable!() }) {
-            match imp {
-                Impulse::Error(e) => bail!(e),
-                Impulse::Stop => break,
-
-                _ => {
-                    self = await!(self.update(imp))
-                        .map_err(|e| -> Error { e.into() })?
-                },
-            }
         }
-
-        Ok(())
     }
 }
@@ -0,0 +1,152 @@
+use uuid::Uuid;
+
+use super::graph::{Edge, Graph, Node};
+use super::SomaData;
+
+/// a soma that was added or removed between two probes
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct SomaChange {
+    /// unique id of the soma
+    pub uuid: Uuid,
+    /// name of the soma
+    pub name: String,
+}
+
+/// constraints of an axon that were added, removed, violated or satisfied
+/// between two probes
+///
+/// axons that were added or removed report all of their constraints and
+/// violations.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ConstraintChange {
+    /// unique id of the axon
+    pub uuid: Uuid,
+    /// name of the axon
+    pub name: String,
+    /// constraints that only appear in the newer probe
+    pub added: Vec<String>,
+    /// constraints that only appear in the older probe
+    pub removed: Vec<String>,
+    /// violations that only appear in the newer probe
+    pub violated: Vec<String>,
+    /// violations that only appear in the older probe
+    pub resolved: Vec<String>,
+}
+
+/// changes to the topology of an organelle between two probes
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Diff {
+    /// somas that only appear in the newer probe
+    pub added: Vec<SomaChange>,
+    /// somas that only appear in the older probe
+    pub removed: Vec<SomaChange>,
+    /// axons whose constraints or constraint violations changed
+    pub constraints: Vec<ConstraintChange>,
+    /// synapses that only appear in the newer probe
+    pub connected: Vec<Edge>,
+    /// synapses that only appear in the older probe
+    pub disconnected: Vec<Edge>,
+}
+
+impl Diff {
+    /// check whether the probes describe the same topology
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+            && self.constraints.is_empty()
+            && self.connected.is_empty()
+            && self.disconnected.is_empty()
+    }
+}
+
+/// compare two probes of the same organelle
+///
+/// somas are matched by uuid at any depth, so somas that moved between nested
+/// organelles are not reported as changes.
+pub fn diff(before: &SomaData, after: &SomaData) -> Diff {
+    let before = Graph::new(before);
+    let after = Graph::new(after);
+
+    let mut diff = Diff::default();
+
+    for node in after.nodes() {
+        let old = before.node(node.uuid);
+
+        if old.is_none() {
+            diff.added.push(SomaChange {
+                uuid: node.uuid,
+                name: node.name.clone(),
+            });
+        }
+
+        diff.constraints.extend(constraints(old, Some(node)));
+    }
+
+    for node in before.nodes() {
+        if after.node(node.uuid).is_none() {
+            diff.removed.push(SomaChange {
+                uuid: node.uuid,
+                name: node.name.clone(),
+            });
+
+            diff.constraints.extend(constraints(Some(node), None));
+        }
+    }
+
+    diff.connected = synapses(after.edges(), before.edges());
+    diff.disconnected = synapses(before.edges(), after.edges());
+
+    diff
+}
+
+/// compare the constraints of a soma that may only appear in one probe
+fn constraints(
+    before: Option<&Node>,
+    after: Option<&Node>,
+) -> Option<ConstraintChange> {
+    let node = after.or(before)?;
+
+    let empty: Vec<String> = vec![];
+    let (old_constraints, old_violations) = before
+        .map(|node| (&node.constraints, &node.violations))
+        .unwrap_or((&empty, &empty));
+    let (new_constraints, new_violations) = after
+        .map(|node| (&node.constraints, &node.violations))
+        .unwrap_or((&empty, &empty));
+
+    let change = ConstraintChange {
+        uuid: node.uuid,
+        name: node.name.clone(),
+        added: only(new_constraints, old_constraints),
+        removed: only(old_constraints, new_constraints),
+        violated: only(new_violations, old_violations),
+        resolved: only(old_violations, new_violations),
+    };
+
+    let unchanged = change.added.is_empty() && change.removed.is_empty()
+        && change.violated.is_empty()
+        && change.resolved.is_empty();
+
+    if unchanged {
+        None
+    } else {
+        Some(change)
+    }
+}
+
+/// the strings that are only found in the first list
+fn only(items: &[String], others: &[String]) -> Vec<String> {
+    items
+        .iter()
+        .filter(|item| !others.contains(item))
+        .cloned()
+        .collect()
+}
+
+/// the edges that are only found in the first list
+fn synapses(edges: &[Edge], others: &[Edge]) -> Vec<Edge> {
+    edges
+        .iter()
+        .filter(|edge| !others.contains(edge))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,446 @@
+use std::collections::{BTreeSet, HashMap};
+
+use uuid::Uuid;
+
+use super::{ConstraintData, PortData, SomaData};
+
+/// the kind of soma a node was probed from
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum NodeKind {
+    /// an organelle containing other somas
+    #[serde(rename = "organelle")]
+    Organelle,
+    /// a soma wrapped in an axon
+    #[serde(rename = "axon")]
+    Axon,
+    /// a plain soma
+    #[serde(rename = "soma")]
+    Soma,
+    /// a soma that did not respond to the probe
+    #[serde(rename = "unresponsive")]
+    Unresponsive,
+}
+
+/// a single soma in the graph
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct Node {
+    /// unique id of the soma
+    pub uuid: Uuid,
+    /// name of the soma
+    pub name: String,
+    /// the kind of soma
+    pub kind: NodeKind,
+    /// the organelle containing the soma, if any
+    pub parent: Option<Uuid>,
+    /// the nucleus of the organelle, if the soma is an organelle
+    pub nucleus: Option<Uuid>,
+    /// the rest of the somas in the organelle, if the soma is an organelle
+    pub somas: Vec<Uuid>,
+    /// the inner somas that handle dendrites given to the soma, if it is an
+    /// organelle
+    pub dendrite_ports: Vec<PortData>,
+    /// the inner somas that handle terminals given to the soma, if it is an
+    /// organelle
+    pub terminal_ports: Vec<PortData>,
+    /// constraints on the soma's synapses, if it is an axon
+    pub constraints: Vec<String>,
+    /// constraints violated by the soma, if it is an axon
+    pub violations: Vec<String>,
+}
+
+/// a synapse between two somas
+#[derive(
+    Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Ord, PartialOrd,
+)]
+pub struct Edge {
+    /// the soma holding the terminal of the synapse
+    pub terminal: Uuid,
+    /// the soma holding the dendrite of the synapse
+    pub dendrite: Uuid,
+    /// the enum variant for the synapse
+    pub variant: String,
+}
+
+/// flat view of a probe for querying its topology
+///
+/// both ends of a synapse report it in the probe, and ends given to an
+/// organelle are followed to the inner soma handling them, so every synapse
+/// appears as a single edge no matter which end was probed.
+#[derive(Debug, Clone)]
+pub struct Graph {
+    nodes: Vec<Node>,
+    index: HashMap<Uuid, usize>,
+
+    edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// build a graph from the probe of a soma
+    pub fn new(data: &SomaData) -> Self {
+        let mut nodes = vec![];
+        let mut edges = vec![];
+
+        Self::collect(data, None, &mut nodes, &mut edges);
+
+        let index = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.uuid, i))
+            .collect();
+
+        let mut graph = Self {
+            nodes: nodes,
+            index: index,
+
+            edges: vec![],
+        };
+
+        // each end only knows the outermost soma on the other side, so both
+        // ends are resolved to the somas that actually handle the synapse
+        let edges = edges
+            .into_iter()
+            .map(|edge: Edge| {
+                Edge::new(
+                    graph.terminal_handler(edge.terminal, &edge.variant),
+                    graph.dendrite_handler(edge.dendrite, &edge.variant),
+                    edge.variant,
+                )
+            })
+            .collect::<BTreeSet<_>>();
+
+        graph.edges = edges.into_iter().collect();
+
+        graph
+    }
+
+    fn collect(
+        data: &SomaData,
+        parent: Option<Uuid>,
+        nodes: &mut Vec<Node>,
+        edges: &mut Vec<Edge>,
+    ) -> Uuid {
+        let mut node = match data {
+            &SomaData::Organelle {
+                uuid,
+                ref name,
+                ref dendrite_ports,
+                ref terminal_ports,
+                ..
+            } => Node {
+                dendrite_ports: dendrite_ports.clone(),
+                terminal_ports: terminal_ports.clone(),
+                ..Node::new(uuid, name, NodeKind::Organelle, parent)
+            },
+            &SomaData::Axon {
+                uuid,
+                ref name,
+                ref terminals,
+                ref dendrites,
+                ref violations,
+                ..
+            } => {
+                for (variant, soma) in peers(terminals) {
+                    edges.push(Edge::new(uuid, soma, variant));
+                }
+                for (variant, soma) in peers(dendrites) {
+                    edges.push(Edge::new(soma, uuid, variant));
+                }
+
+                let constraints = terminals
+                    .iter()
+                    .map(|c| describe("terminal", c))
+                    .chain(dendrites.iter().map(|c| describe("dendrite", c)))
+                    .collect::<BTreeSet<_>>();
+
+                Node {
+                    constraints: constraints.into_iter().collect(),
+                    violations: violations.clone(),
+                    ..Node::new(uuid, name, NodeKind::Axon, parent)
+                }
+            },
+            &SomaData::Soma {
+                uuid,
+                ref name,
+                ref terminals,
+                ref dendrites,
+                ..
+            } => {
+                for t in terminals {
+                    edges.push(Edge::new(uuid, t.soma, t.variant.clone()));
+                }
+                for d in dendrites {
+                    edges.push(Edge::new(d.soma, uuid, d.variant.clone()));
+                }
+
+                Node::new(uuid, name, NodeKind::Soma, parent)
+            },
+            &SomaData::Unresponsive { uuid, ref name } => {
+                Node::new(uuid, name, NodeKind::Unresponsive, parent)
+            },
+        };
+
+        let uuid = node.uuid;
+        let i = nodes.len();
+        nodes.push(node.clone());
+
+        if let &SomaData::Organelle {
+            ref nucleus,
+            ref somas,
+            ..
+        } = data
+        {
+            node.nucleus =
+                Some(Self::collect(nucleus, Some(uuid), nodes, edges));
+
+            for soma in somas {
+                node.somas
+                    .push(Self::collect(soma, Some(uuid), nodes, edges));
+            }
+
+            nodes[i] = node;
+        }
+
+        uuid
+    }
+
+    /// the soma that was probed
+    pub fn root(&self) -> &Node {
+        &self.nodes[0]
+    }
+
+    /// every soma in the graph, parents before their children
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// every synapse in the graph
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// look up a soma by uuid
+    pub fn node(&self, uuid: Uuid) -> Option<&Node> {
+        self.index.get(&uuid).map(|i| &self.nodes[*i])
+    }
+
+    /// find the somas with the given name
+    ///
+    /// names match either in full or without their module path, so `Foo`
+    /// matches a soma named `my_crate::Foo`.
+    pub fn find(&self, name: &str) -> Vec<&Node> {
+        self.nodes
+            .iter()
+            .filter(|node| node.matches(name))
+            .collect()
+    }
+
+    /// the organelle containing a soma
+    pub fn parent(&self, uuid: Uuid) -> Option<&Node> {
+        self.node(uuid)
+            .and_then(|node| node.parent)
+            .and_then(|parent| self.node(parent))
+    }
+
+    /// the somas contained in an organelle, starting with its nucleus
+    pub fn children(&self, uuid: Uuid) -> Vec<&Node> {
+        match self.node(uuid) {
+            Some(node) => node.nucleus
+                .iter()
+                .chain(node.somas.iter())
+                .filter_map(|child| self.node(*child))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// the synapses that a soma is part of
+    pub fn edges_of(&self, uuid: Uuid) -> Vec<&Edge> {
+        self.edges
+            .iter()
+            .filter(|edge| edge.terminal == uuid || edge.dendrite == uuid)
+            .collect()
+    }
+
+    /// the somas connected to a soma by a synapse
+    pub fn neighbours(&self, uuid: Uuid) -> Vec<&Node> {
+        let mut neighbours: Vec<&Node> = vec![];
+
+        for edge in self.edges_of(uuid) {
+            let other = if edge.terminal == uuid {
+                edge.dendrite
+            } else {
+                edge.terminal
+            };
+
+            if let Some(node) = self.node(other) {
+                if !neighbours.iter().any(|n| n.uuid == node.uuid) {
+                    neighbours.push(node);
+                }
+            }
+        }
+
+        neighbours
+    }
+
+    /// the somas connected to any soma with the given name
+    ///
+    /// see `find` for how names are matched.
+    pub fn neighbours_by_name(&self, name: &str) -> Vec<&Node> {
+        let mut neighbours: Vec<&Node> = vec![];
+
+        for node in self.find(name) {
+            for neighbour in self.neighbours(node.uuid) {
+                if !neighbours.iter().any(|n| n.uuid == neighbour.uuid) {
+                    neighbours.push(neighbour);
+                }
+            }
+        }
+
+        neighbours
+    }
+
+    /// the organelles leading from the root down to a soma, including the
+    /// soma itself
+    pub fn path(&self, uuid: Uuid) -> Vec<&Node> {
+        let mut path = vec![];
+        let mut current = self.node(uuid);
+
+        while let Some(node) = current {
+            path.insert(0, node);
+            current = self.parent(node.uuid);
+        }
+
+        path
+    }
+
+    /// resolve a path of names through nested organelles
+    ///
+    /// each name selects a soma inside the organelle selected by the previous
+    /// one, starting with the somas of the root. see `find` for how names are
+    /// matched.
+    pub fn resolve(&self, path: &[&str]) -> Option<&Node> {
+        let mut current = Some(self.root());
+
+        for name in path {
+            current = current.and_then(|node| {
+                self.children(node.uuid)
+                    .into_iter()
+                    .find(|child| child.matches(name))
+            });
+        }
+
+        current
+    }
+
+    /// follow the nuclei of nested organelles down to a soma
+    ///
+    /// synapses connected to an organelle are handled by its nucleus unless
+    /// an inner soma exposes them. see `dendrite_handler` and
+    /// `terminal_handler` for lookups that take exposed ports into account.
+    pub fn innermost_nucleus(&self, uuid: Uuid) -> Uuid {
+        match self.node(uuid).and_then(|node| node.nucleus) {
+            Some(nucleus) => self.innermost_nucleus(nucleus),
+            None => uuid,
+        }
+    }
+
+    /// find the soma that handles a dendrite given to a soma
+    ///
+    /// an organelle hands the dendrite to the inner soma exposing it, or to
+    /// its nucleus otherwise. nested organelles are followed down to the soma
+    /// that actually sits at the end of the synapse.
+    pub fn dendrite_handler(&self, uuid: Uuid, variant: &str) -> Uuid {
+        self.handler(uuid, variant, |node| &node.dendrite_ports)
+    }
+
+    /// find the soma that handles a terminal given to a soma
+    ///
+    /// see `dendrite_handler`, which follows exposed dendrites the same way.
+    pub fn terminal_handler(&self, uuid: Uuid, variant: &str) -> Uuid {
+        self.handler(uuid, variant, |node| &node.terminal_ports)
+    }
+
+    fn handler<F>(&self, uuid: Uuid, variant: &str, ports: F) -> Uuid
+    where
+        F: Fn(&Node) -> &Vec<PortData>,
+    {
+        let node = match self.node(uuid) {
+            Some(node) => node,
+            None => return uuid,
+        };
+
+        let port = ports(node)
+            .iter()
+            .find(|port| port.variant == variant)
+            .map(|port| port.soma);
+
+        match port.or(node.nucleus) {
+            Some(inner) => self.handler(inner, variant, ports),
+            None => uuid,
+        }
+    }
+}
+
+impl Node {
+    fn new(
+        uuid: Uuid,
+        name: &str,
+        kind: NodeKind,
+        parent: Option<Uuid>,
+    ) -> Self {
+        Self {
+            uuid: uuid,
+            name: name.to_string(),
+            kind: kind,
+            parent: parent,
+            nucleus: None,
+            somas: vec![],
+            dendrite_ports: vec![],
+            terminal_ports: vec![],
+            constraints: vec![],
+            violations: vec![],
+        }
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        self.name == name || self.name.ends_with(&format!("::{}", name))
+    }
+}
+
+impl Edge {
+    fn new(terminal: Uuid, dendrite: Uuid, variant: String) -> Self {
+        Self {
+            terminal: terminal,
+            dendrite: dendrite,
+            variant: variant,
+        }
+    }
+}
+
+fn peers(constraints: &[ConstraintData]) -> Vec<(String, Uuid)> {
+    let mut peers = vec![];
+
+    for c in constraints {
+        for soma in c.somas() {
+            peers.push((c.variant().to_string(), soma));
+        }
+    }
+
+    peers
+}
+
+/// describe a constraint without the somas connected to it
+fn describe(kind: &str, constraint: &ConstraintData) -> String {
+    let bounds = match constraint {
+        &ConstraintData::One { .. } => "one".to_string(),
+        &ConstraintData::Variadic { .. } => "any number".to_string(),
+        &ConstraintData::Optional { .. } => "optional".to_string(),
+        &ConstraintData::AtLeast { min, .. } => format!("at least {}", min),
+        &ConstraintData::AtMost { max, .. } => format!("at most {}", max),
+        &ConstraintData::Range { min, max, .. } => {
+            format!("{} to {}", min, max)
+        },
+    };
+
+    format!("{} {} ({})", kind, constraint.variant(), bounds)
+}
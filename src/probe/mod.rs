@@ -0,0 +1,613 @@
+use std::intrinsics;
+use std::time;
+
+use futures::prelude::*;
+use futures::unsync::{mpsc, oneshot};
+use serde_json;
+use tokio_core::reactor;
+use uuid::Uuid;
+
+use super::{Error, ErrorKind, Result};
+use axon::{Axon, Constraint};
+use mailbox::Overflow;
+use soma::{self, Impulse};
+
+mod diff;
+mod graph;
+pub(crate) mod schema;
+
+pub use self::diff::{diff, ConstraintChange, Diff, SomaChange};
+pub use self::graph::{Edge, Graph, Node, NodeKind};
+pub use self::schema::schema;
+
+/// number of probe requests that can wait on a probe soma
+const PROBE_CHANNEL_CAPACITY: usize = 10;
+
+/// version of the probe document format
+///
+/// this is bumped whenever a change to the probe data would prevent older
+/// documents from being loaded.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// data associated with a synapse type
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Describe)]
+#[serde(deny_unknown_fields)]
+pub struct SynapseData {
+    /// name of the synapse type
+    pub name: String,
+    /// the variants of the synapse type, if they are known
+    pub variants: Vec<VariantData>,
+}
+
+impl SynapseData {
+    /// create data for the synapse type `S`
+    pub fn new<S: ?Sized>(variants: Vec<VariantData>) -> Self {
+        Self {
+            name: unsafe { intrinsics::type_name::<S>().to_string() },
+            variants: variants,
+        }
+    }
+}
+
+/// data associated with a single synapse variant
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Describe)]
+#[serde(deny_unknown_fields)]
+pub struct VariantData {
+    /// the enum variant for the synapse
+    pub variant: String,
+    /// the kind of channel formed by the synapse
+    pub channel: String,
+    /// the type of message sent across the synapse
+    pub message: String,
+}
+
+/// data associated with a synapse connected to a soma
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Describe)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectionData {
+    /// the enum variant for the synapse
+    pub variant: String,
+    /// the other soma involved in the synapse
+    pub soma: Uuid,
+}
+
+/// an inner soma that handles a synapse given to its organelle
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Describe)]
+#[serde(deny_unknown_fields)]
+pub struct PortData {
+    /// the enum variant for the synapse
+    pub variant: String,
+    /// the inner soma that handles the synapse
+    pub soma: Uuid,
+}
+
+/// data associated with a synapse constraint
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Describe)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum ConstraintData {
+    /// only one synapse of the given variant
+    #[serde(rename = "one")]
+    One {
+        /// the enum variant for the synapse
+        variant: String,
+        /// the other soma involved in the synapse
+        soma: Uuid,
+    },
+
+    /// any number of synapses of the given variant
+    #[serde(rename = "variadic")]
+    Variadic {
+        /// the enum variant for the synapse
+        variant: String,
+        /// the other somas involved in the synapses
+        somas: Vec<Uuid>,
+    },
+
+    /// zero or one synapse of the given variant
+    #[serde(rename = "optional")]
+    Optional {
+        /// the enum variant for the synapse
+        variant: String,
+        /// the other soma involved in the synapse, if connected
+        soma: Option<Uuid>,
+    },
+
+    /// a minimum number of synapses of the given variant
+    #[serde(rename = "at_least")]
+    AtLeast {
+        /// the enum variant for the synapse
+        variant: String,
+        /// the minimum number of synapses
+        min: usize,
+        /// the other somas involved in the synapses
+        somas: Vec<Uuid>,
+    },
+
+    /// a maximum number of synapses of the given variant
+    #[serde(rename = "at_most")]
+    AtMost {
+        /// the enum variant for the synapse
+        variant: String,
+        /// the maximum number of synapses
+        max: usize,
+        /// the other somas involved in the synapses
+        somas: Vec<Uuid>,
+    },
+
+    /// a bounded number of synapses of the given variant
+    #[serde(rename = "range")]
+    Range {
+        /// the enum variant for the synapse
+        variant: String,
+        /// the minimum number of synapses
+        min: usize,
+        /// the maximum number of synapses
+        max: usize,
+        /// the other somas involved in the synapses
+        somas: Vec<Uuid>,
+    },
+}
+
+impl ConstraintData {
+    /// the enum variant for the synapse
+    pub fn variant(&self) -> &str {
+        match self {
+            &ConstraintData::One { ref variant, .. }
+            | &ConstraintData::Variadic { ref variant, .. }
+            | &ConstraintData::Optional { ref variant, .. }
+            | &ConstraintData::AtLeast { ref variant, .. }
+            | &ConstraintData::AtMost { ref variant, .. }
+            | &ConstraintData::Range { ref variant, .. } => variant,
+        }
+    }
+
+    /// the other somas involved in the synapses
+    pub fn somas(&self) -> Vec<Uuid> {
+        match self {
+            &ConstraintData::One { soma, .. } => vec![soma],
+            &ConstraintData::Optional { soma, .. } => {
+                soma.into_iter().collect()
+            },
+
+            &ConstraintData::Variadic { ref somas, .. }
+            | &ConstraintData::AtLeast { ref somas, .. }
+            | &ConstraintData::AtMost { ref somas, .. }
+            | &ConstraintData::Range { ref somas, .. } => somas.clone(),
+        }
+    }
+
+    /// the number of synapses accepted, such as `1`, `0..1` or `2..`
+    pub fn bounds(&self) -> String {
+        match self {
+            &ConstraintData::One { .. } => "1".to_string(),
+            &ConstraintData::Variadic { .. } => "0..".to_string(),
+            &ConstraintData::Optional { .. } => "0..1".to_string(),
+            &ConstraintData::AtLeast { min, .. } => format!("{}..", min),
+            &ConstraintData::AtMost { max, .. } => format!("0..{}", max),
+            &ConstraintData::Range { min, max, .. } => {
+                format!("{}..{}", min, max)
+            },
+        }
+    }
+}
+
+/// data associated with the mailbox of a soma in an organelle
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Describe)]
+#[serde(deny_unknown_fields)]
+pub struct MailboxData {
+    /// the soma the mailbox belongs to
+    pub soma: Uuid,
+    /// the number of impulses the mailbox can hold
+    pub capacity: usize,
+    /// what happens once the mailbox is full
+    pub overflow: Overflow,
+    /// the number of impulses dropped because the mailbox was full
+    pub dropped: usize,
+}
+
+/// run-time metrics of a soma in an organelle
+///
+/// latencies are given in microseconds, and the percentiles cover the most
+/// recent calls to `update`.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Describe)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsData {
+    /// the soma the metrics belong to
+    pub soma: Uuid,
+    /// the name of the soma
+    pub name: String,
+    /// the number of impulses taken from the soma's mailbox
+    pub impulses: u64,
+    /// the number of calls to the soma's `update`
+    pub updates: u64,
+    /// the total time spent in `update`
+    pub update_time_us: u64,
+    /// the median time spent in `update`
+    pub p50_update_us: u64,
+    /// the 99th percentile of the time spent in `update`
+    pub p99_update_us: u64,
+    /// the number of impulses waiting in the soma's mailbox
+    pub mailbox_depth: usize,
+    /// the number of errors raised by the soma
+    pub errors: u64,
+}
+
+/// data associated with a soma, organelle, or axon
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Describe)]
+#[serde(tag = "type", deny_unknown_fields)]
+pub enum SomaData {
+    /// data associated with an organelle
+    #[serde(rename = "organelle")]
+    Organelle {
+        /// the soma at the center of an organelle
+        nucleus: Box<SomaData>,
+        /// the rest of the somas contained in the organelle
+        somas: Vec<SomaData>,
+        /// the mailboxes of the probed somas
+        #[serde(default)]
+        mailboxes: Vec<MailboxData>,
+        /// the run-time metrics of the probed somas
+        #[serde(default)]
+        metrics: Vec<MetricsData>,
+        /// the inner somas that handle dendrites given to the organelle
+        #[serde(default)]
+        dendrite_ports: Vec<PortData>,
+        /// the inner somas that handle terminals given to the organelle
+        #[serde(default)]
+        terminal_ports: Vec<PortData>,
+        /// unique id of the organelle
+        uuid: Uuid,
+        /// name of the organelle
+        name: String,
+        /// snapshot of the organelle's internal state
+        state: Option<serde_json::Value>,
+    },
+
+    /// data associated with the axon of a soma
+    #[serde(rename = "axon")]
+    Axon {
+        /// data associated with the terminals for this soma
+        terminals: Vec<ConstraintData>,
+        /// data associated with the dendrites for this soma
+        dendrites: Vec<ConstraintData>,
+        /// constraints that were violated while rewiring a running organelle
+        violations: Vec<String>,
+        /// unique id of the axon
+        uuid: Uuid,
+        /// name of the axon
+        name: String,
+        /// snapshot of the wrapped soma's internal state
+        state: Option<serde_json::Value>,
+    },
+
+    /// data associated with a custom soma
+    #[serde(rename = "soma")]
+    Soma {
+        /// the type of synapse used by this soma
+        synapse: SynapseData,
+        /// the dendrites connected to this soma
+        dendrites: Vec<ConnectionData>,
+        /// the terminals connected to this soma
+        terminals: Vec<ConnectionData>,
+        /// unique id of the soma
+        uuid: Uuid,
+        /// the name of the soma
+        name: String,
+        /// snapshot of the soma's internal state
+        state: Option<serde_json::Value>,
+    },
+
+    /// a soma that did not respond to the probe in time
+    #[serde(rename = "unresponsive")]
+    Unresponsive {
+        /// unique id of the soma
+        uuid: Uuid,
+        /// the name of the soma
+        name: String,
+    },
+}
+
+/// a versioned probe result that can be saved and loaded again
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Describe)]
+#[serde(deny_unknown_fields)]
+pub struct Document {
+    /// version of the format the document was written in
+    pub version: u32,
+    /// the data of the probed soma
+    pub root: SomaData,
+}
+
+impl Document {
+    /// wrap probe data in a document using the current format version
+    pub fn new(root: SomaData) -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            root: root,
+        }
+    }
+
+    /// serialize the document to a JSON string
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// load a document from a JSON string
+    ///
+    /// documents written in a different format version are rejected.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+
+        match value.get("version").and_then(|v| v.as_u64()) {
+            Some(version) if version == FORMAT_VERSION as u64 => (),
+            Some(version) => bail!(ErrorKind::ProbeVersion(version as u32)),
+            None => bail!("probe document has no format version"),
+        }
+
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
+/// soma that probes the internal structure of an organelle
+pub struct Soma {
+    dendrites: Vec<Dendrite>,
+}
+
+impl Soma {
+    /// create a new probe soma
+    pub fn axon() -> Axon<Self> {
+        Axon::new(
+            Self { dendrites: vec![] },
+            vec![Constraint::Variadic(Synapse::Probe)],
+            vec![],
+        )
+    }
+}
+
+/// the synapse for a probe
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Synapse {
+    /// a synapse used to perform probes
+    Probe,
+}
+
+/// default amount of time to wait for each soma to respond to a probe
+const DEFAULT_PROBE_TIMEOUT: u64 = 5;
+
+/// each level of nesting gets this fraction less of its parent's timeout
+const NESTED_TIMEOUT_MARGIN: u32 = 4;
+
+/// settings for a probe operation
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub(crate) depth: usize,
+    pub(crate) max_depth: Option<usize>,
+
+    pub(crate) names: Vec<String>,
+    pub(crate) uuids: Vec<Uuid>,
+
+    pub(crate) include_state: bool,
+    pub(crate) include_metrics: bool,
+    pub(crate) timeout: time::Duration,
+}
+
+impl Settings {
+    /// create settings
+    pub fn new() -> Self {
+        Self {
+            depth: 0,
+            max_depth: None,
+
+            names: vec![],
+            uuids: vec![],
+
+            include_state: true,
+            include_metrics: true,
+            timeout: time::Duration::from_secs(DEFAULT_PROBE_TIMEOUT),
+        }
+    }
+
+    /// limit how many levels of nested organelles are walked
+    ///
+    /// the probed organelle is at depth 0. organelles nested deeper than this
+    /// only report their nucleus.
+    pub fn max_depth(self, depth: usize) -> Self {
+        Self {
+            max_depth: Some(depth),
+            ..self
+        }
+    }
+
+    /// only report somas whose name contains the given string
+    ///
+    /// filters are combined, so a soma is reported if it matches any of them.
+    /// organelles and their nuclei are always reported.
+    pub fn filter_name<T: Into<String>>(mut self, name: T) -> Self {
+        self.names.push(name.into());
+        self
+    }
+
+    /// only report the soma with the given uuid
+    ///
+    /// see `filter_name` for how filters are combined.
+    pub fn filter_uuid(mut self, uuid: Uuid) -> Self {
+        self.uuids.push(uuid);
+        self
+    }
+
+    /// include snapshots of each soma's state (on by default)
+    pub fn include_state(self, flag: bool) -> Self {
+        Self {
+            include_state: flag,
+            ..self
+        }
+    }
+
+    /// include the run-time metrics of each soma (on by default)
+    pub fn include_metrics(self, flag: bool) -> Self {
+        Self {
+            include_metrics: flag,
+            ..self
+        }
+    }
+
+    /// set how long to wait for each soma before reporting it as unresponsive
+    ///
+    /// somas in nested organelles are given a quarter less time at each
+    /// level, so a nested organelle can still report its own unresponsive
+    /// somas before its parent gives up on it.
+    pub fn timeout(self, timeout: time::Duration) -> Self {
+        Self {
+            timeout: timeout,
+            ..self
+        }
+    }
+
+    /// settings for the somas one level further down
+    pub(crate) fn nested(&self) -> Self {
+        Self {
+            depth: self.depth + 1,
+            timeout: self.timeout - self.timeout / NESTED_TIMEOUT_MARGIN,
+            ..self.clone()
+        }
+    }
+
+    /// check whether an organelle at the current depth should be walked
+    pub(crate) fn within_depth(&self) -> bool {
+        self.max_depth.map_or(true, |max_depth| self.depth <= max_depth)
+    }
+
+    /// check whether the soma passes the filters
+    pub(crate) fn matches(&self, data: &SomaData) -> bool {
+        if self.names.is_empty() && self.uuids.is_empty() {
+            return true;
+        }
+
+        match data {
+            &SomaData::Organelle { .. } => true,
+            &SomaData::Axon { uuid, ref name, .. }
+            | &SomaData::Soma { uuid, ref name, .. }
+            | &SomaData::Unresponsive { uuid, ref name } => {
+                self.uuids.contains(&uuid)
+                    || self.names.iter().any(|n| name.contains(n.as_str()))
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Request {
+    Probe(Settings, oneshot::Sender<SomaData>),
+}
+
+/// sender for a probe operation
+#[derive(Debug, Clone)]
+pub struct Terminal {
+    tx: mpsc::Sender<Request>,
+}
+
+impl Terminal {
+    /// perform the probe
+    #[async]
+    pub fn probe(self, settings: Settings) -> Result<SomaData> {
+        let (tx, rx) = oneshot::channel();
+
+        await!(
+            self.tx
+                .send(Request::Probe(settings, tx))
+                .map(|_| ())
+                .map_err(|_| Error::from("unable to send probe request"))
+        )?;
+
+        await!(rx.map_err(|_| Error::from("unable to receive probe response")))
+    }
+}
+
+/// receive for a probe operation
+#[derive(Debug)]
+pub struct Dendrite {
+    rx: mpsc::Receiver<Request>,
+}
+
+/// create a junction between two probe-ready somas
+pub fn synapse() -> (Terminal, Dendrite) {
+    let (tx, rx) = mpsc::channel(PROBE_CHANNEL_CAPACITY);
+
+    (Terminal { tx: tx }, Dendrite { rx: rx })
+}
+
+impl soma::Synapse for Synapse {
+    type Terminal = Terminal;
+    type Dendrite = Dendrite;
+
+    fn synapse(self) -> (Terminal, Dendrite) {
+        match self {
+            Synapse::Probe => synapse(),
+        }
+    }
+}
+
+impl soma::Soma for Soma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, Synapse::Probe, rx) => {
+                self.dendrites.push(rx);
+
+                Ok(self)
+            },
+
+            Impulse::Start(ctx) => {
+                ctx.spawn(ProbeTask::run(
+                    ctx.sender(),
+                    ctx.handle(),
+                    self.dendrites,
+                ));
+
+                Ok(Self { dendrites: vec![] })
+            },
+
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+struct ProbeTask;
+
+impl ProbeTask {
+    #[async]
+    fn run(
+        main_tx: mpsc::Sender<Impulse<Synapse>>,
+        handle: reactor::Handle,
+        dendrites: Vec<Dendrite>,
+    ) -> Result<()> {
+        let (tx, rx) = mpsc::channel(PROBE_CHANNEL_CAPACITY);
+
+        for dendrite in dendrites {
+            handle.spawn(
+                tx.clone()
+                    .send_all(dendrite.rx.map_err(|_| unreachable!()))
+                    .map(|_| ())
+                    .map_err(|_| ()),
+            );
+        }
+
+        #[async]
+        for req in rx.map_err(|_| -> Error { unreachable!() }) {
+            match req {
+                Request::Probe(settings, tx) => {
+                    await!(
+                        main_tx
+                            .clone()
+                            .send(Impulse::Probe(settings, tx))
+                            .map_err(|_| "unable to send probe impulse")
+                    )?;
+                },
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+
+use serde_json;
+use uuid::Uuid;
+
+use super::{Document, FORMAT_VERSION};
+
+/// JSON schema describing a probe `Document`
+///
+/// the schema follows draft 7 of the JSON schema specification and covers the
+/// current format version only. it is generated from the probe data with
+/// `#[derive(Describe)]`, so objects only allow the fields their types have,
+/// just like the `deny_unknown_fields` used to load them.
+pub fn schema() -> serde_json::Value {
+    let mut definitions = Definitions::new();
+
+    Document::describe(&mut definitions);
+
+    let mut schema = definitions.values.remove("Document").unwrap();
+
+    schema["$schema"] = json!("http://json-schema.org/draft-07/schema#");
+    schema["title"] = json!("organelle probe");
+    schema["properties"]["version"] = json!({ "const": FORMAT_VERSION });
+    schema["definitions"] =
+        serde_json::Value::Object(definitions.values.into_iter().collect());
+
+    schema
+}
+
+/// types that can describe their JSON form
+pub(crate) trait Describe {
+    /// the schema of the type, adding the definitions it refers to
+    fn describe(definitions: &mut Definitions) -> serde_json::Value;
+
+    /// whether a field of this type can be left out
+    fn optional() -> bool {
+        false
+    }
+}
+
+/// the named schemas referred to by other schemas
+pub(crate) struct Definitions {
+    values: BTreeMap<String, serde_json::Value>,
+}
+
+impl Definitions {
+    fn new() -> Self {
+        Self {
+            values: BTreeMap::new(),
+        }
+    }
+
+    /// refer to the definition with the given name, describing it first if
+    /// it is not defined yet
+    pub fn define<F>(&mut self, name: &str, describe: F) -> serde_json::Value
+    where
+        F: FnOnce(&mut Self) -> serde_json::Value,
+    {
+        if !self.values.contains_key(name) {
+            // recursive types find the placeholder instead of describing
+            // themselves again
+            self.values.insert(name.to_string(), serde_json::Value::Null);
+
+            let schema = describe(self);
+            self.values.insert(name.to_string(), schema);
+        }
+
+        json!({ "$ref": format!("#/definitions/{}", name) })
+    }
+}
+
+/// schema of an object, built one property at a time
+pub(crate) struct Object {
+    properties: serde_json::Map<String, serde_json::Value>,
+    required: Vec<String>,
+    closed: bool,
+}
+
+impl Object {
+    /// start an object, which may not have unknown properties if closed
+    pub fn new(closed: bool) -> Self {
+        Self {
+            properties: serde_json::Map::new(),
+            required: vec![],
+            closed: closed,
+        }
+    }
+
+    /// add a property of type `T`, which can be left out if it has a default
+    pub fn property<T: Describe>(
+        mut self,
+        name: &str,
+        default: bool,
+        definitions: &mut Definitions,
+    ) -> Self {
+        if !default && !T::optional() {
+            self.required.push(name.to_string());
+        }
+
+        self.properties
+            .insert(name.to_string(), T::describe(definitions));
+
+        self
+    }
+
+    /// add the tag naming the variant of an internally tagged enum
+    pub fn tag(mut self, tag: &str, variant: &str) -> Self {
+        self.required.insert(0, tag.to_string());
+        self.properties
+            .insert(tag.to_string(), json!({ "const": variant }));
+
+        self
+    }
+
+    /// finish the object
+    pub fn build(self) -> serde_json::Value {
+        json!({
+            "type": "object",
+            "additionalProperties": !self.closed,
+            "required": self.required,
+            "properties": self.properties,
+        })
+    }
+}
+
+/// schema matching exactly one of the given schemas
+pub(crate) fn one_of(schemas: Vec<serde_json::Value>) -> serde_json::Value {
+    json!({ "oneOf": schemas })
+}
+
+/// schema matching one of the given names
+pub(crate) fn names(names: &[&str]) -> serde_json::Value {
+    json!({ "enum": names })
+}
+
+impl Describe for String {
+    fn describe(_: &mut Definitions) -> serde_json::Value {
+        json!({ "type": "string" })
+    }
+}
+
+impl Describe for Uuid {
+    fn describe(_: &mut Definitions) -> serde_json::Value {
+        json!({ "type": "string", "format": "uuid" })
+    }
+}
+
+impl Describe for u32 {
+    fn describe(_: &mut Definitions) -> serde_json::Value {
+        json!({ "type": "integer", "minimum": 0 })
+    }
+}
+
+impl Describe for u64 {
+    fn describe(_: &mut Definitions) -> serde_json::Value {
+        json!({ "type": "integer", "minimum": 0 })
+    }
+}
+
+impl Describe for usize {
+    fn describe(_: &mut Definitions) -> serde_json::Value {
+        json!({ "type": "integer", "minimum": 0 })
+    }
+}
+
+impl Describe for serde_json::Value {
+    fn describe(_: &mut Definitions) -> serde_json::Value {
+        json!({})
+    }
+}
+
+impl<T: Describe> Describe for Vec<T> {
+    fn describe(definitions: &mut Definitions) -> serde_json::Value {
+        json!({ "type": "array", "items": T::describe(definitions) })
+    }
+}
+
+impl<T: Describe> Describe for Box<T> {
+    fn describe(definitions: &mut Definitions) -> serde_json::Value {
+        T::describe(definitions)
+    }
+}
+
+impl<T: Describe> Describe for Option<T> {
+    fn describe(definitions: &mut Definitions) -> serde_json::Value {
+        json!({ "anyOf": [T::describe(definitions), { "type": "null" }] })
+    }
+
+    fn optional() -> bool {
+        true
+    }
+}
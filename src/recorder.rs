@@ -0,0 +1,375 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json;
+use uuid::Uuid;
+
+use super::{Error, ErrorKind, Result};
+use directory::Directory;
+use metrics::micros;
+use soma::{Impulse, SomaOrigin, Synapse};
+use validation::ValidationIssue;
+
+/// the kind of impulse recorded in an event
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub enum ImpulseKind {
+    /// `Impulse::AddDendrite`
+    #[serde(rename = "add_dendrite")]
+    AddDendrite,
+    /// `Impulse::AddTerminal`
+    #[serde(rename = "add_terminal")]
+    AddTerminal,
+    /// `Impulse::RemoveDendrite`
+    #[serde(rename = "remove_dendrite")]
+    RemoveDendrite,
+    /// `Impulse::RemoveTerminal`
+    #[serde(rename = "remove_terminal")]
+    RemoveTerminal,
+    /// `Impulse::Start`
+    #[serde(rename = "start")]
+    Start,
+    /// `Impulse::Probe`
+    #[serde(rename = "probe")]
+    Probe,
+    /// `Impulse::Stop`
+    #[serde(rename = "stop")]
+    Stop,
+    /// `Impulse::Error`
+    #[serde(rename = "error")]
+    Error,
+    /// a constraint violated by rewiring a running axon
+    #[serde(rename = "violation")]
+    Violation,
+}
+
+/// a single impulse delivered by the runtime
+///
+/// synapse impulses come from the soma on the other end of the synapse,
+/// lifecycle impulses come from the organelle, and errors go from the soma
+/// that raised them to the organelle. violations go from the soma on the
+/// other end of the synapse to the axon that reported them.
+#[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq)]
+pub struct ImpulseEvent {
+    /// microseconds since the unix epoch
+    pub timestamp_us: u64,
+    /// the kind of impulse
+    pub kind: ImpulseKind,
+    /// unique id of the soma or organelle the impulse came from, if known
+    pub source: Option<Uuid>,
+    /// name of the soma or organelle the impulse came from, if known
+    pub source_name: Option<String>,
+    /// unique id of the soma or organelle the impulse was delivered to
+    pub target: Option<Uuid>,
+    /// name of the soma or organelle the impulse was delivered to
+    pub target_name: Option<String>,
+    /// the enum variant of the synapse, for synapse impulses
+    pub variant: Option<String>,
+    /// description of the error or violation
+    pub message: Option<String>,
+}
+
+enum Sink {
+    Writer(Box<Write>),
+    Memory(Vec<u8>),
+    Failed(String),
+}
+
+/// opt-in log of every impulse delivered by the runtime
+///
+/// each event is written as a single line of JSON. give the recorder to the
+/// outermost organelle with `Organelle::set_recorder` and nested organelles
+/// pick it up once started.
+#[derive(Clone)]
+pub struct Recorder {
+    sink: Rc<RefCell<Sink>>,
+}
+
+impl Recorder {
+    /// record events to any writer
+    pub fn new<W: Write + 'static>(writer: W) -> Self {
+        Self {
+            sink: Rc::new(RefCell::new(Sink::Writer(Box::new(writer)))),
+        }
+    }
+
+    /// record events to a file, replacing its contents
+    ///
+    /// each line is flushed as soon as it is written, so the file is still
+    /// useful if the process dies.
+    pub fn file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::new(LineWriter::new(File::create(path)?)))
+    }
+
+    /// record events to an in-memory buffer
+    pub fn memory() -> Self {
+        Self {
+            sink: Rc::new(RefCell::new(Sink::Memory(vec![]))),
+        }
+    }
+
+    /// the JSON lines recorded so far, if the recorder writes to memory
+    pub fn buffer(&self) -> Option<String> {
+        match *self.sink.borrow() {
+            Sink::Memory(ref buffer) => {
+                Some(String::from_utf8_lossy(buffer).into_owned())
+            },
+            Sink::Writer(_) | Sink::Failed(_) => None,
+        }
+    }
+
+    /// why the recorder gave up writing events, if its writer failed
+    pub fn failure(&self) -> Option<String> {
+        match *self.sink.borrow() {
+            Sink::Failed(ref e) => Some(e.clone()),
+            Sink::Writer(_) | Sink::Memory(_) => None,
+        }
+    }
+
+    /// the events recorded so far, if the recorder writes to memory
+    pub fn events(&self) -> Result<Vec<ImpulseEvent>> {
+        let buffer = self.buffer().ok_or_else(|| {
+            Error::from("only in-memory recorders can be read back")
+        })?;
+
+        let mut events = vec![];
+
+        for line in buffer.lines() {
+            events.push(serde_json::from_str(line)?);
+        }
+
+        Ok(events)
+    }
+
+    /// record a constraint violated by a synapse of a running soma
+    pub(crate) fn violated<S: Synapse>(
+        &self,
+        peer: Uuid,
+        synapse: S,
+        issue: &ValidationIssue,
+    ) {
+        self.record(ImpulseEvent {
+            timestamp_us: now(),
+            kind: ImpulseKind::Violation,
+            source: Some(peer),
+            source_name: None,
+            target: issue.origin.uuid,
+            target_name: Some(issue.origin.name.clone()),
+            variant: Some(format!("{:?}", synapse)),
+            message: Some(issue.message.clone()),
+        });
+    }
+
+    fn record(&self, event: ImpulseEvent) {
+        // events only hold strings and numbers, so they always serialize
+        let mut line = serde_json::to_vec(&event).unwrap();
+        line.push(b'\n');
+
+        let mut sink = self.sink.borrow_mut();
+
+        let failure = match *sink {
+            Sink::Writer(ref mut writer) => writer.write_all(&line).err(),
+            Sink::Memory(ref mut buffer) => {
+                buffer.extend(line);
+                None
+            },
+            Sink::Failed(_) => None,
+        };
+
+        // recording is best-effort, so a writer that fails is given up on
+        // rather than interrupting the organelle
+        if let Some(e) = failure {
+            *sink = Sink::Failed(e.to_string());
+        }
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.sink.borrow() {
+            Sink::Writer(_) => write!(f, "Recorder(writer)"),
+            Sink::Memory(ref buffer) => {
+                write!(f, "Recorder(memory, {} bytes)", buffer.len())
+            },
+            Sink::Failed(ref e) => write!(f, "Recorder(failed, {})", e),
+        }
+    }
+}
+
+struct TapState {
+    recorder: Option<Recorder>,
+    organelle: Option<(Uuid, String)>,
+}
+
+/// records the impulses delivered within a single organelle
+///
+/// the tap is shared with the inboxes of the organelle's somas, so a recorder
+/// can be set or inherited after the somas were spawned.
+#[derive(Clone)]
+pub(crate) struct Tap {
+    state: Rc<RefCell<TapState>>,
+    directory: Directory,
+}
+
+impl Tap {
+    pub(crate) fn new(directory: Directory) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(TapState {
+                recorder: None,
+                organelle: None,
+            })),
+            directory: directory,
+        }
+    }
+
+    pub(crate) fn recorder(&self) -> Option<Recorder> {
+        self.state.borrow().recorder.clone()
+    }
+
+    pub(crate) fn set_recorder(&self, recorder: Recorder) {
+        self.state.borrow_mut().recorder = Some(recorder);
+    }
+
+    /// remember which organelle the lifecycle impulses come from
+    pub(crate) fn set_organelle(&self, uuid: Uuid, name: String) {
+        self.state.borrow_mut().organelle = Some((uuid, name));
+    }
+
+    /// describe an impulse about to be delivered to a soma in the organelle
+    ///
+    /// returns `None` if nothing is being recorded. the event is only recorded
+    /// once it is passed to `delivered`.
+    pub(crate) fn describe<S: Synapse>(
+        &self,
+        imp: &Impulse<S>,
+        target: Uuid,
+        target_name: &str,
+    ) -> Option<ImpulseEvent> {
+        let state = self.state.borrow();
+
+        if state.recorder.is_none() {
+            return None;
+        }
+
+        let (kind, peer, synapse) = match *imp {
+            Impulse::AddDendrite(uuid, synapse, _) => {
+                (ImpulseKind::AddDendrite, Some(uuid), Some(synapse))
+            },
+            Impulse::AddTerminal(uuid, synapse, _) => {
+                (ImpulseKind::AddTerminal, Some(uuid), Some(synapse))
+            },
+            Impulse::RemoveDendrite(uuid, synapse) => {
+                (ImpulseKind::RemoveDendrite, Some(uuid), Some(synapse))
+            },
+            Impulse::RemoveTerminal(uuid, synapse) => {
+                (ImpulseKind::RemoveTerminal, Some(uuid), Some(synapse))
+            },
+            Impulse::Start(_) => (ImpulseKind::Start, None, None),
+            Impulse::Probe(_, _) => (ImpulseKind::Probe, None, None),
+            Impulse::Stop => (ImpulseKind::Stop, None, None),
+            Impulse::Error(_) => (ImpulseKind::Error, None, None),
+        };
+
+        let (source, source_name) = match peer {
+            Some(uuid) => {
+                (Some(uuid), self.directory.get(uuid).map(|peer| peer.name))
+            },
+            None => match state.organelle {
+                Some((uuid, ref name)) => (Some(uuid), Some(name.clone())),
+                None => (None, None),
+            },
+        };
+
+        Some(ImpulseEvent {
+            timestamp_us: now(),
+            kind: kind,
+            source: source,
+            source_name: source_name,
+            target: Some(target),
+            target_name: Some(target_name.to_string()),
+            variant: synapse.map(|synapse| format!("{:?}", synapse)),
+            message: None,
+        })
+    }
+
+    /// record an impulse once it is in the mailbox of its soma
+    pub(crate) fn delivered(&self, event: ImpulseEvent) {
+        if let Some(ref recorder) = self.state.borrow().recorder {
+            recorder.record(ImpulseEvent {
+                timestamp_us: now(),
+                ..event
+            });
+        }
+    }
+
+    /// record an error raised by a soma in the organelle
+    ///
+    /// errors forwarded by nested organelles were already recorded there, so
+    /// only errors without an organelle path are recorded.
+    pub(crate) fn reported(&self, e: &Error) {
+        if origin(e).map_or(false, |origin| !origin.path.is_empty()) {
+            return;
+        }
+
+        let state = self.state.borrow();
+
+        let recorder = match state.recorder {
+            Some(ref recorder) => recorder,
+            None => return,
+        };
+
+        let (source, source_name) = match origin(e) {
+            Some(origin) => (origin.uuid, Some(origin.name.clone())),
+            None => (None, None),
+        };
+        let (target, target_name) = match state.organelle {
+            Some((uuid, ref name)) => (Some(uuid), Some(name.clone())),
+            None => (None, None),
+        };
+
+        recorder.record(ImpulseEvent {
+            timestamp_us: now(),
+            kind: ImpulseKind::Error,
+            source: source,
+            source_name: source_name,
+            target: target,
+            target_name: target_name,
+            variant: None,
+            message: Some(e.to_string()),
+        });
+    }
+}
+
+/// the soma that raised an error, if the error carries one
+fn origin(e: &Error) -> Option<&SomaOrigin> {
+    match e.0 {
+        ErrorKind::SomaError(ref origin)
+        | ErrorKind::SomaPanicked(ref origin, _)
+        | ErrorKind::InvalidSynapse(ref origin, _)
+        | ErrorKind::MissingSynapse(ref origin, _)
+        | ErrorKind::UnmappedSynapse(ref origin, _)
+        | ErrorKind::MailboxOverflow(ref origin, _)
+        | ErrorKind::StopTimeout(ref origin, _) => Some(origin),
+
+        // the soma that hit the restart limit is in the chained error
+        ErrorKind::RestartLimit(_) => e.1
+            .next_error
+            .as_ref()
+            .and_then(|next| next.downcast_ref::<Error>())
+            .and_then(origin),
+
+        _ => None,
+    }
+}
+
+fn now() -> u64 {
+    micros(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default(),
+    )
+}
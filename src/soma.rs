@@ -1,15 +1,21 @@
 use std;
-use std::fmt::Debug;
+use std::any::Any;
+use std::fmt::{self, Debug};
 use std::hash::Hash;
 use std::intrinsics;
+use std::panic::AssertUnwindSafe;
 
+use futures::future;
 use futures::prelude::*;
 use futures::unsync::{mpsc, oneshot};
+use serde_json;
 use tokio_core::reactor;
 use uuid::Uuid;
 
-use super::{Error, Result};
-use probe::{self, SomaData, SynapseData};
+use super::{Error, ErrorKind, Result};
+use axon::Requirements;
+use context::SomaContext;
+use probe::{self, ConnectionData, SomaData, SynapseData};
 
 /// trait alias to express requirements of a Synapse type
 pub trait Synapse: Debug + Copy + Clone + Hash + PartialEq + Eq {
@@ -19,8 +25,11 @@ pub trait Synapse: Debug + Copy + Clone + Hash + PartialEq + Eq {
     type Dendrite: Debug;
 
     /// get the data associated with the synapse
+    ///
+    /// the default only knows the name of the synapse type. implement this
+    /// (or derive `Synapse`) to describe each variant as well.
     fn data() -> SynapseData {
-        SynapseData(unsafe { intrinsics::type_name::<Self>().to_string() })
+        SynapseData::new::<Self>(vec![])
     }
 
     /// form a synapse for this synapse into a terminal and dendrite
@@ -42,13 +51,27 @@ pub enum Impulse<R: Synapse> {
     /// outputs. if your soma has outputs, it is best to wrap it with an Axon
     /// which can be used for validation purposes.
     AddTerminal(Uuid, R, R::Terminal),
+    /// the soma on the other end of a dendrite has been removed
+    ///
+    /// somas connected at runtime should expect to handle this impulse if the
+    /// organelle they belong to removes somas with `Control::remove_soma`.
+    RemoveDendrite(Uuid, R),
+    /// the soma on the other end of a terminal has been removed
+    ///
+    /// somas connected at runtime should expect to handle this impulse if the
+    /// organelle they belong to removes somas with `Control::remove_soma`.
+    RemoveTerminal(Uuid, R),
     /// notify the soma that it has received all of its inputs and outputs
     ///
     /// you should always expect to handle this impulse because it will be
-    /// passed to each soma regardless of configuration
-    Start(Uuid, mpsc::Sender<Impulse<R>>, reactor::Handle),
+    /// passed to each soma regardless of configuration. the context can be
+    /// used to spawn tasks, stop the organelle, or report errors.
+    Start(SomaContext<R>),
     /// stop the event loop and exit gracefully
     ///
+    /// organelles propagate this impulse to each of their somas, which are
+    /// then given a chance to flush their state with `Soma::on_stop`.
+    ///
     /// you should not expect to handle this impulse at any time, it is handled
     /// for you by the event loop
     Stop,
@@ -69,31 +92,183 @@ where
     R: Synapse,
 {
     /// convert from another type of impulse
-    pub fn convert_from<T>(imp: Impulse<T>) -> Self
+    ///
+    /// start impulses cannot be converted because their sender has to be
+    /// forwarded through a new channel.
+    pub fn convert_from<T>(imp: Impulse<T>) -> Result<Self>
     where
-        T: Synapse + Into<R>,
-        T::Dendrite: Into<R::Dendrite>,
-        T::Terminal: Into<R::Terminal>,
+        T: Synapse,
+        Mirror: Mapping<T, R>,
+    {
+        Self::map_down(imp, &Mirror)
+    }
+
+    /// convert an impulse from an organelle into an impulse for one of its
+    /// somas
+    pub fn map_down<P, M>(imp: Impulse<P>, mapping: &M) -> Result<Self>
+    where
+        P: Synapse,
+        M: Mapping<P, R> + ?Sized,
     {
         match imp {
             Impulse::AddDendrite(uuid, synapse, dendrite) => {
-                Impulse::AddDendrite(uuid, synapse.into(), dendrite.into())
+                match (mapping.synapse(synapse), mapping.dendrite(dendrite)) {
+                    (Some(synapse), Some(dendrite)) => {
+                        Ok(Impulse::AddDendrite(uuid, synapse, dendrite))
+                    },
+                    _ => Err(unmapped::<R, _>(synapse)),
+                }
             },
             Impulse::AddTerminal(uuid, synapse, terminal) => {
-                Impulse::AddTerminal(uuid, synapse.into(), terminal.into())
+                match (mapping.synapse(synapse), mapping.terminal(terminal)) {
+                    (Some(synapse), Some(terminal)) => {
+                        Ok(Impulse::AddTerminal(uuid, synapse, terminal))
+                    },
+                    _ => Err(unmapped::<R, _>(synapse)),
+                }
+            },
+            Impulse::RemoveDendrite(uuid, synapse) => {
+                match mapping.synapse(synapse) {
+                    Some(mapped) => Ok(Impulse::RemoveDendrite(uuid, mapped)),
+                    None => Err(unmapped::<R, _>(synapse)),
+                }
+            },
+            Impulse::RemoveTerminal(uuid, synapse) => {
+                match mapping.synapse(synapse) {
+                    Some(mapped) => Ok(Impulse::RemoveTerminal(uuid, mapped)),
+                    None => Err(unmapped::<R, _>(synapse)),
+                }
             },
-            Impulse::Stop => Impulse::Stop,
-            Impulse::Error(e) => Impulse::Error(e),
+            Impulse::Stop => Ok(Impulse::Stop),
+            Impulse::Error(e) => Ok(Impulse::Error(e)),
 
-            Impulse::Start(_, _, _) => {
-                panic!("no automatic conversion for start")
+            Impulse::Start(_) => bail!("no automatic conversion for start"),
+
+            Impulse::Probe(settings, tx) => Ok(Impulse::Probe(settings, tx)),
+        }
+    }
+
+    /// convert an impulse sent by a soma into an impulse for its organelle
+    pub fn map_up<C, M>(imp: Impulse<C>, mapping: &M) -> Result<Self>
+    where
+        C: Synapse,
+        M: Mapping<R, C> + ?Sized,
+    {
+        match imp {
+            Impulse::AddDendrite(uuid, synapse, dendrite) => {
+                match (
+                    mapping.parent_synapse(synapse),
+                    mapping.parent_dendrite(dendrite),
+                ) {
+                    (Some(synapse), Some(dendrite)) => {
+                        Ok(Impulse::AddDendrite(uuid, synapse, dendrite))
+                    },
+                    _ => Err(unmapped::<R, _>(synapse)),
+                }
+            },
+            Impulse::AddTerminal(uuid, synapse, terminal) => {
+                match (
+                    mapping.parent_synapse(synapse),
+                    mapping.parent_terminal(terminal),
+                ) {
+                    (Some(synapse), Some(terminal)) => {
+                        Ok(Impulse::AddTerminal(uuid, synapse, terminal))
+                    },
+                    _ => Err(unmapped::<R, _>(synapse)),
+                }
+            },
+            Impulse::RemoveDendrite(uuid, synapse) => {
+                match mapping.parent_synapse(synapse) {
+                    Some(mapped) => Ok(Impulse::RemoveDendrite(uuid, mapped)),
+                    None => Err(unmapped::<R, _>(synapse)),
+                }
             },
+            Impulse::RemoveTerminal(uuid, synapse) => {
+                match mapping.parent_synapse(synapse) {
+                    Some(mapped) => Ok(Impulse::RemoveTerminal(uuid, mapped)),
+                    None => Err(unmapped::<R, _>(synapse)),
+                }
+            },
+            Impulse::Stop => Ok(Impulse::Stop),
+            Impulse::Error(e) => Ok(Impulse::Error(e)),
+
+            Impulse::Start(_) => bail!("no automatic conversion for start"),
 
-            Impulse::Probe(settings, tx) => Impulse::Probe(settings, tx),
+            Impulse::Probe(settings, tx) => Ok(Impulse::Probe(settings, tx)),
         }
     }
 }
 
+fn unmapped<R: Synapse, T: Synapse>(synapse: T) -> Error {
+    ErrorKind::UnmappedSynapse(
+        SomaOrigin::new(None, unsafe { intrinsics::type_name::<R>() }),
+        format!("{:?}", synapse),
+    ).into()
+}
+
+/// a partial mapping between the synapses of an organelle and one of its somas
+///
+/// this allows reusable somas to be added to organelles without mirroring
+/// every synapse in the host organelle. impulses for synapses that have no
+/// mapping are rejected with `ErrorKind::UnmappedSynapse`.
+pub trait Mapping<P: Synapse, C: Synapse> {
+    /// map a synapse of the organelle onto the soma
+    fn synapse(&self, synapse: P) -> Option<C>;
+    /// map a terminal of the organelle onto the soma
+    fn terminal(&self, terminal: P::Terminal) -> Option<C::Terminal>;
+    /// map a dendrite of the organelle onto the soma
+    fn dendrite(&self, dendrite: P::Dendrite) -> Option<C::Dendrite>;
+
+    /// map a synapse of the soma back onto the organelle
+    fn parent_synapse(&self, _synapse: C) -> Option<P> {
+        None
+    }
+    /// map a terminal of the soma back onto the organelle
+    fn parent_terminal(&self, _terminal: C::Terminal) -> Option<P::Terminal> {
+        None
+    }
+    /// map a dendrite of the soma back onto the organelle
+    fn parent_dendrite(&self, _dendrite: C::Dendrite) -> Option<P::Dendrite> {
+        None
+    }
+}
+
+/// maps synapses that convert to and from each other with `From` and `Into`
+///
+/// `#[derive(Synapse)]` also implements this mapping from each
+/// `#[synapse(parent = "...")]` onto the derived synapse. parent synapses
+/// that the derived synapse does not have are left unmapped.
+#[derive(Debug, Copy, Clone)]
+pub struct Mirror;
+
+impl<P, C> Mapping<P, C> for Mirror
+where
+    P: Synapse,
+    C: Synapse + From<P> + Into<P>,
+    C::Dendrite: From<P::Dendrite> + Into<P::Dendrite>,
+    C::Terminal: From<P::Terminal> + Into<P::Terminal>,
+{
+    fn synapse(&self, synapse: P) -> Option<C> {
+        Some(synapse.into())
+    }
+    fn terminal(&self, terminal: P::Terminal) -> Option<C::Terminal> {
+        Some(terminal.into())
+    }
+    fn dendrite(&self, dendrite: P::Dendrite) -> Option<C::Dendrite> {
+        Some(dendrite.into())
+    }
+
+    fn parent_synapse(&self, synapse: C) -> Option<P> {
+        Some(synapse.into())
+    }
+    fn parent_terminal(&self, terminal: C::Terminal) -> Option<P::Terminal> {
+        Some(terminal.into())
+    }
+    fn parent_dendrite(&self, dendrite: C::Dendrite) -> Option<P::Dendrite> {
+        Some(dendrite.into())
+    }
+}
+
 /// a singular cell of functionality that can be ported between organelles
 ///
 /// you can think of a soma as a stream of impulses folded over a structure.
@@ -105,9 +280,11 @@ pub trait Soma: Sized {
     /// the synapse a synapse plays in a connection between somas.
     type Synapse: Synapse;
     /// the types of errors that this soma can return
-    type Error: std::error::Error + Send + Into<Error>;
+    type Error: std::error::Error + Send + Into<Error> + 'static;
 
     /// probe the internal structure of this soma
+    ///
+    /// the runtime fills in the uuid and connected synapses of plain somas.
     #[async(boxed)]
     fn probe(
         self,
@@ -120,11 +297,44 @@ pub trait Soma: Sized {
             self,
             SomaData::Soma {
                 synapse: Self::Synapse::data(),
+                dendrites: vec![],
+                terminals: vec![],
+                uuid: Uuid::nil(),
                 name: unsafe { intrinsics::type_name::<Self>().to_string() },
+                state: None,
             },
         ))
     }
 
+    /// the constraints that the synapses wired to this soma must satisfy
+    ///
+    /// this is used by `Organelle::validate` to check the wiring before the
+    /// organelle runs. somas have no constraints by default.
+    fn requirements(&self) -> Option<Requirements<Self::Synapse>> {
+        None
+    }
+
+    /// take a snapshot of the soma's internal state
+    ///
+    /// the snapshot is attached to the soma's data whenever it is probed.
+    /// somas have no state to show by default.
+    fn inspect(&self) -> Option<serde_json::Value> {
+        None
+    }
+
+    /// flush any remaining state before the soma is shut down
+    ///
+    /// this is called by the event loop when a stop impulse reaches the soma.
+    /// the event loop waits for this to resolve (up to the organelle's stop
+    /// timeout) before resolving itself.
+    #[async(boxed)]
+    fn on_stop(self) -> std::result::Result<(), Self::Error>
+    where
+        Self: 'static,
+    {
+        Ok(())
+    }
+
     /// react to a single impulse
     fn update(
         self,
@@ -141,10 +351,15 @@ pub trait Soma: Sized {
         let (tx, rx) = mpsc::channel(1);
 
         let uuid = Uuid::new_v4();
+        let name = unsafe { intrinsics::type_name::<Self>().to_string() };
+
+        let ctx = SomaContext::new(uuid, name.clone(), tx.clone(), handle);
+        let _tasks = ctx.guard();
+
+        let mut connections = Connections::new(uuid);
 
         await!(
-            tx.clone()
-                .send(Impulse::Start(uuid, tx, handle))
+            tx.send(Impulse::Start(ctx.clone()))
                 .map_err(|_| Error::from("unable to send start signal"))
         )?;
 
@@ -152,12 +367,448 @@ pub trait Soma: Sized {
         for imp in rx.map_err(|_| -> Error { unreachable!() }) {
             match imp {
                 Impulse::Error(e) => bail!(e),
-                Impulse::Stop => break,
+                Impulse::Stop => {
+                    ctx.cancel_tasks();
+
+                    await!(call_soma(uuid, name.clone(), move || {
+                        self.on_stop()
+                    }))?;
+                    break;
+                },
+                Impulse::Probe(settings, tx) => {
+                    self = await!(probe_soma(
+                        self,
+                        uuid,
+                        name.clone(),
+                        connections.clone(),
+                        settings,
+                        tx
+                    ))?
+                },
 
-                _ => self = await!(self.update(imp)).map_err(|e| e.into())?,
+                _ => {
+                    connections.update(&imp);
+
+                    self = await!(call_soma(uuid, name.clone(), move || {
+                        self.update(imp)
+                    }))?
+                },
             }
         }
 
         Ok(())
     }
 }
+
+/// identifies the soma that raised an error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SomaOrigin {
+    /// unique id of the soma, if it was known when the error was raised
+    pub uuid: Option<Uuid>,
+    /// type name of the soma
+    pub name: String,
+    /// names of the organelles containing the soma, outermost first
+    pub path: Vec<String>,
+}
+
+impl SomaOrigin {
+    /// create an origin for a soma that is not nested in any organelles
+    pub fn new<T: Into<String>>(uuid: Option<Uuid>, name: T) -> Self {
+        Self {
+            uuid: uuid,
+            name: name.into(),
+            path: vec![],
+        }
+    }
+}
+
+impl fmt::Display for SomaOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for organelle in &self.path {
+            write!(f, "{}/", organelle)?;
+        }
+
+        write!(f, "{}", short_name(&self.name))?;
+
+        if let Some(uuid) = self.uuid {
+            write!(f, " ({})", uuid)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// strip the module paths from a type name
+pub fn short_name(name: &str) -> String {
+    let mut short = String::new();
+    let mut segment = String::new();
+
+    let mut chars = name.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ':' if chars.peek() == Some(&':') => {
+                chars.next();
+                segment.clear();
+            },
+            c if c.is_alphanumeric() || c == '_' => segment.push(c),
+            c => {
+                short.push_str(&segment);
+                segment.clear();
+                short.push(c);
+            },
+        }
+    }
+
+    short.push_str(&segment);
+
+    short
+}
+
+/// attribute an error returned by a soma to that soma
+///
+/// organelle errors that already identify a soma are left as they are, and
+/// any other errors are chained to `ErrorKind::SomaError` so that the soma's
+/// own error can be recovered with `Error::soma_error`.
+pub fn attribute_error<E>(e: E, uuid: Uuid, name: &str) -> Error
+where
+    E: std::error::Error + Send + Into<Error> + 'static,
+{
+    attribute_origin(e, SomaOrigin::new(Some(uuid), name))
+}
+
+/// attribute an error to a soma whose uuid may not be known yet
+///
+/// errors attributed without a uuid get one once they are attributed again
+/// by the organelle running the soma.
+pub(crate) fn attribute_origin<E>(e: E, soma: SomaOrigin) -> Error
+where
+    E: std::error::Error + Send + Into<Error> + 'static,
+{
+    let e: Box<Any> = Box::new(e);
+
+    // most somas use the organelle error type, which may already identify
+    // the soma that raised it
+    let mut e = match e.downcast::<Error>() {
+        Ok(e) => *e,
+        Err(e) => match e.downcast::<E>() {
+            Ok(e) => return Error::with_chain(*e, ErrorKind::SomaError(soma)),
+            Err(_) => unreachable!(),
+        },
+    };
+
+    let attributed = match e.0 {
+        ErrorKind::SomaError(ref mut origin)
+        | ErrorKind::InvalidSynapse(ref mut origin, _)
+        | ErrorKind::MissingSynapse(ref mut origin, _)
+        | ErrorKind::UnmappedSynapse(ref mut origin, _) => {
+            if origin.uuid.is_none() {
+                origin.uuid = soma.uuid;
+                origin.name = soma.name.clone();
+            }
+
+            true
+        },
+
+        ErrorKind::SomaPanicked(_, _)
+        | ErrorKind::MailboxOverflow(_, _)
+        | ErrorKind::StopTimeout(_, _)
+        | ErrorKind::RestartLimit(_) => true,
+
+        _ => false,
+    };
+
+    if attributed {
+        e
+    } else {
+        Error::with_chain(e, ErrorKind::SomaError(soma))
+    }
+}
+
+/// add an organelle to the path of the soma that raised an error
+pub fn nest_error(mut e: Error, organelle: &str) -> Error {
+    nest_origin(&mut e, organelle);
+
+    e
+}
+
+fn nest_origin(e: &mut Error, organelle: &str) {
+    match e.0 {
+        ErrorKind::SomaError(ref mut origin)
+        | ErrorKind::SomaPanicked(ref mut origin, _)
+        | ErrorKind::InvalidSynapse(ref mut origin, _)
+        | ErrorKind::MissingSynapse(ref mut origin, _)
+        | ErrorKind::UnmappedSynapse(ref mut origin, _)
+        | ErrorKind::MailboxOverflow(ref mut origin, _)
+        | ErrorKind::StopTimeout(ref mut origin, _) => {
+            origin.path.insert(0, organelle.to_string())
+        },
+
+        // the soma that hit the restart limit is in the chained error
+        ErrorKind::RestartLimit(_) => {
+            let next = e.1
+                .next_error
+                .as_mut()
+                .and_then(|next| next.downcast_mut::<Error>());
+
+            if let Some(next) = next {
+                nest_origin(next, organelle);
+            }
+        },
+
+        _ => (),
+    }
+}
+
+/// call into a soma, attributing any errors or panics to that soma
+///
+/// the soma is only called once the returned future is polled, so panics that
+/// occur before the soma returns a future are caught as well.
+pub fn call_soma<F, U>(
+    uuid: Uuid,
+    name: String,
+    f: F,
+) -> Box<Future<Item = U::Item, Error = Error>>
+where
+    F: FnOnce() -> U + 'static,
+    U: IntoFuture + 'static,
+    U::Error: std::error::Error + Send + Into<Error> + 'static,
+{
+    Box::new(AssertUnwindSafe(future::lazy(f)).catch_unwind().then(
+        move |result| match result {
+            Ok(Ok(item)) => Ok(item),
+            Ok(Err(e)) => Err(attribute_error(e, uuid, &name)),
+            Err(payload) => Err(ErrorKind::SomaPanicked(
+                SomaOrigin::new(Some(uuid), name),
+                panic_message(payload),
+            ).into()),
+        },
+    ))
+}
+
+/// synapses connected to a soma, tracked by the runtime for probes
+#[derive(Debug, Clone)]
+pub struct Connections {
+    uuid: Uuid,
+
+    dendrites: Vec<ConnectionData>,
+    terminals: Vec<ConnectionData>,
+}
+
+impl Connections {
+    /// track the synapses connected to the given soma
+    pub fn new(uuid: Uuid) -> Self {
+        Self {
+            uuid: uuid,
+
+            dendrites: vec![],
+            terminals: vec![],
+        }
+    }
+
+    /// record any synapse added or removed by the impulse
+    pub fn update<R: Synapse>(&mut self, imp: &Impulse<R>) {
+        match imp {
+            &Impulse::AddDendrite(uuid, synapse, _) => {
+                Self::add(&mut self.dendrites, uuid, synapse)
+            },
+            &Impulse::AddTerminal(uuid, synapse, _) => {
+                Self::add(&mut self.terminals, uuid, synapse)
+            },
+            &Impulse::RemoveDendrite(uuid, synapse) => {
+                Self::remove(&mut self.dendrites, uuid, synapse)
+            },
+            &Impulse::RemoveTerminal(uuid, synapse) => {
+                Self::remove(&mut self.terminals, uuid, synapse)
+            },
+            _ => (),
+        }
+    }
+
+    /// fill in the details of a soma that only the runtime knows
+    ///
+    /// every soma gets the uuid it runs under, since axons and organelles
+    /// only learn theirs once started. plain somas also get their synapses.
+    pub fn describe(&self, data: SomaData) -> SomaData {
+        match data {
+            SomaData::Soma {
+                synapse,
+                name,
+                state,
+                ..
+            } => SomaData::Soma {
+                synapse: synapse,
+                dendrites: self.dendrites.clone(),
+                terminals: self.terminals.clone(),
+                uuid: self.uuid,
+                name: name,
+                state: state,
+            },
+            SomaData::Axon {
+                terminals,
+                dendrites,
+                violations,
+                name,
+                state,
+                ..
+            } => SomaData::Axon {
+                terminals: terminals,
+                dendrites: dendrites,
+                violations: violations,
+                uuid: self.uuid,
+                name: name,
+                state: state,
+            },
+            SomaData::Organelle {
+                nucleus,
+                somas,
+                mailboxes,
+                metrics,
+                dendrite_ports,
+                terminal_ports,
+                name,
+                state,
+                ..
+            } => SomaData::Organelle {
+                nucleus: nucleus,
+                somas: somas,
+                mailboxes: mailboxes,
+                metrics: metrics,
+                dendrite_ports: dendrite_ports,
+                terminal_ports: terminal_ports,
+                uuid: self.uuid,
+                name: name,
+                state: state,
+            },
+            SomaData::Unresponsive { name, .. } => SomaData::Unresponsive {
+                uuid: self.uuid,
+                name: name,
+            },
+        }
+    }
+
+    fn add<R: Synapse>(
+        connections: &mut Vec<ConnectionData>,
+        uuid: Uuid,
+        synapse: R,
+    ) {
+        let connection = ConnectionData {
+            variant: format!("{:?}", synapse),
+            soma: uuid,
+        };
+
+        // the same soma reconnects after a restart
+        if !connections.contains(&connection) {
+            connections.push(connection);
+        }
+    }
+
+    fn remove<R: Synapse>(
+        connections: &mut Vec<ConnectionData>,
+        uuid: Uuid,
+        synapse: R,
+    ) {
+        let variant = format!("{:?}", synapse);
+
+        connections.retain(|c| c.soma != uuid || c.variant != variant);
+    }
+}
+
+/// probe a soma on behalf of the runtime and send the data back
+///
+/// somas never see probe impulses in `update`, so every soma can be probed
+/// through its `probe` method without handling the impulse itself.
+#[async]
+pub fn probe_soma<T: Soma + 'static>(
+    soma: T,
+    uuid: Uuid,
+    name: String,
+    connections: Connections,
+    settings: probe::Settings,
+    tx: oneshot::Sender<SomaData>,
+) -> Result<T> {
+    let include_state = settings.include_state;
+
+    let (soma, data) =
+        await!(call_soma(uuid, name, move || soma.probe(settings)))?;
+
+    let state = if include_state { soma.inspect() } else { None };
+    let data = attach_state(connections.describe(data), state);
+
+    if let Err(_) = tx.send(data) {
+        // rx does not care anymore
+    }
+
+    Ok(soma)
+}
+
+/// attach a snapshot of a soma's state to its data
+fn attach_state(
+    data: SomaData,
+    snapshot: Option<serde_json::Value>,
+) -> SomaData {
+    match data {
+        SomaData::Organelle {
+            nucleus,
+            somas,
+            mailboxes,
+            metrics,
+            dendrite_ports,
+            terminal_ports,
+            uuid,
+            name,
+            ..
+        } => SomaData::Organelle {
+            nucleus: nucleus,
+            somas: somas,
+            mailboxes: mailboxes,
+            metrics: metrics,
+            dendrite_ports: dendrite_ports,
+            terminal_ports: terminal_ports,
+            uuid: uuid,
+            name: name,
+            state: snapshot,
+        },
+        SomaData::Axon {
+            terminals,
+            dendrites,
+            violations,
+            uuid,
+            name,
+            ..
+        } => SomaData::Axon {
+            terminals: terminals,
+            dendrites: dendrites,
+            violations: violations,
+            uuid: uuid,
+            name: name,
+            state: snapshot,
+        },
+        SomaData::Soma {
+            synapse,
+            dendrites,
+            terminals,
+            uuid,
+            name,
+            ..
+        } => SomaData::Soma {
+            synapse: synapse,
+            dendrites: dendrites,
+            terminals: terminals,
+            uuid: uuid,
+            name: name,
+            state: snapshot,
+        },
+        data @ SomaData::Unresponsive { .. } => data,
+    }
+}
+
+fn panic_message(payload: Box<Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "soma panicked with a non-string payload".to_string()
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt;
+
+use soma::SomaOrigin;
+
+/// the kind of problem found while validating an organelle
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum IssueKind {
+    /// a constraint has fewer synapses than it requires
+    MissingSynapse,
+    /// a constraint has more synapses than it allows
+    DuplicateSynapse,
+    /// a synapse has no matching constraint
+    UnknownSynapse,
+    /// a synapse connects to a soma that fails a peer constraint
+    WrongPeer,
+    /// nothing is connected to a port whose constraint admits zero synapses,
+    /// such as a `Constraint::Variadic` or `Constraint::Optional`
+    UnconnectedPort,
+    /// a soma has no synapses at all
+    UnusedSoma,
+}
+
+impl IssueKind {
+    /// check whether the issue would keep the organelle from starting
+    pub fn is_error(&self) -> bool {
+        match *self {
+            IssueKind::MissingSynapse
+            | IssueKind::DuplicateSynapse
+            | IssueKind::UnknownSynapse
+            | IssueKind::WrongPeer => true,
+
+            IssueKind::UnconnectedPort | IssueKind::UnusedSoma => false,
+        }
+    }
+}
+
+/// a single problem found while validating an organelle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationIssue {
+    /// the kind of problem
+    pub kind: IssueKind,
+    /// the soma with the problem
+    pub origin: SomaOrigin,
+    /// description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = if self.kind.is_error() {
+            "error"
+        } else {
+            "warning"
+        };
+
+        write!(f, "{}: soma {} - {}", severity, self.origin, self.message)
+    }
+}
+
+/// every problem found while validating the wiring of an organelle
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// the problems, grouped by soma in the order the somas were added
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// problems that would keep the organelle from starting
+    pub fn errors(&self) -> Vec<&ValidationIssue> {
+        self.issues.iter().filter(|i| i.kind.is_error()).collect()
+    }
+
+    /// problems that are likely wiring mistakes but will not stop the
+    /// organelle
+    pub fn warnings(&self) -> Vec<&ValidationIssue> {
+        self.issues.iter().filter(|i| !i.kind.is_error()).collect()
+    }
+
+    /// check whether the organelle can start
+    pub fn is_valid(&self) -> bool {
+        self.errors().is_empty()
+    }
+
+    pub(crate) fn push(
+        &mut self,
+        kind: IssueKind,
+        origin: &SomaOrigin,
+        message: String,
+    ) {
+        self.issues.push(ValidationIssue {
+            kind: kind,
+            origin: origin.clone(),
+            message: message,
+        });
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{}", issue)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,8 +1,10 @@
 #[allow(dead_code)]
 mod dot;
+mod prometheus;
 
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
 use std::net::SocketAddr;
+use std::rc::Rc;
 
 use bytes::BufMut;
 use futures::future;
@@ -17,7 +19,15 @@ use uuid::Uuid;
 use super::{Error, Result};
 use axon::{Axon, Constraint};
 use organelle::Organelle;
-use probe::{self, ConstraintData, SomaData, Synapse, Terminal};
+use probe::{
+    self,
+    ConnectionData,
+    ConstraintData,
+    MetricsData,
+    SomaData,
+    Synapse,
+    Terminal,
+};
 use soma::{self, Impulse};
 
 /// visualizer settings
@@ -25,6 +35,7 @@ use soma::{self, Impulse};
 pub struct Settings {
     open_on_start: bool,
     port: u16,
+    address: Rc<Cell<Option<SocketAddr>>>,
 }
 
 impl Settings {
@@ -37,9 +48,20 @@ impl Settings {
     }
 
     /// set the port that the visualizer is hosted on
+    ///
+    /// use port 0 to have the system pick a free port, which `address` gives
+    /// once the visualizer has started.
     pub fn port(self, port: u16) -> Self {
         Self { port: port, ..self }
     }
+
+    /// the address the visualizer is hosted on, once it has started
+    ///
+    /// clones of the settings share the address, so keep one around to find
+    /// the visualizer after giving the settings to it.
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.address.get()
+    }
 }
 
 impl Default for Settings {
@@ -47,6 +69,7 @@ impl Default for Settings {
         Self {
             open_on_start: false,
             port: 8080,
+            address: Rc::new(Cell::new(None)),
         }
     }
 }
@@ -98,19 +121,13 @@ impl soma::Soma for Soma {
                 Ok(self)
             },
 
-            Impulse::Start(_, main_tx, handle) => {
-                handle.spawn(
+            Impulse::Start(ctx) => {
+                ctx.spawn(
                     VisualizerTask::new(
                         self.settings.clone(),
                         self.probe.unwrap(),
-                        handle.clone(),
-                    ).run()
-                        .or_else(move |e| {
-                            main_tx
-                                .send(Impulse::Error(e))
-                                .map(|_| ())
-                                .map_err(|_| ())
-                        }),
+                        ctx.handle(),
+                    ).run(),
                 );
 
                 Ok(Self {
@@ -128,6 +145,7 @@ struct VisualizerTask {
     probe: Terminal,
     port: u16,
     open_on_start: bool,
+    address: Rc<Cell<Option<SocketAddr>>>,
     handle: reactor::Handle,
 }
 
@@ -141,6 +159,7 @@ impl VisualizerTask {
             probe: probe,
             port: settings.port,
             open_on_start: settings.open_on_start,
+            address: settings.address,
 
             handle: handle,
         }
@@ -150,8 +169,20 @@ impl VisualizerTask {
     fn run(self) -> Result<()> {
         let addr: SocketAddr = format!("127.0.0.1:{}", self.port).parse()?;
         let stream_handle = self.handle.clone();
-        let hypersf_handle = self.handle.clone();
         let probe = self.probe;
+        let baseline = Rc::new(RefCell::new(None));
+
+        let serve = Http::new().serve_addr_handle(
+            &addr,
+            &self.handle,
+            move || {
+                Ok(VisualizerService::new(probe.clone(), baseline.clone()))
+            },
+        )?;
+
+        // the port may have been picked by the system
+        let addr = serve.incoming_ref().local_addr();
+        self.address.set(Some(addr));
 
         if self.open_on_start {
             if let Err(e) = open::that(format!("http://{}", addr.to_string())) {
@@ -159,38 +190,37 @@ impl VisualizerTask {
             }
         }
 
-        await!(
-            Http::new()
-                .serve_addr_handle(&addr, &self.handle, move || Ok(
-                    VisualizerService::new(&hypersf_handle, probe.clone())
-                ))?
-                .for_each(move |connection| {
-                    stream_handle.spawn(connection.map(|_| ()).or_else(
-                        move |e| {
-                            eprintln!(
-                                "error while serving HTTP request - {:?}",
-                                e
-                            );
-
-                            Ok(())
-                        },
-                    ));
-
-                    Ok(())
-                })
-        )?;
+        await!(serve.for_each(move |connection| {
+            stream_handle.spawn(connection.map(|_| ()).or_else(move |e| {
+                eprintln!("error while serving HTTP request - {:?}", e);
+
+                Ok(())
+            }));
+
+            Ok(())
+        }))?;
 
         Ok(())
     }
 }
 
+/// the probe that diffs are compared against
+///
+/// it only changes through `POST /api/probe/baseline`, so every diff shows the
+/// changes since the baseline was last set.
+type Baseline = Rc<RefCell<Option<SomaData>>>;
+
 struct VisualizerService {
     probe: Terminal,
+    baseline: Baseline,
 }
 
 impl VisualizerService {
-    fn new(_handle: &reactor::Handle, probe: Terminal) -> Self {
-        Self { probe: probe }
+    fn new(probe: Terminal, baseline: Baseline) -> Self {
+        Self {
+            probe: probe,
+            baseline: baseline,
+        }
     }
 
     fn get(&self, req: hyper::Request) -> <Self as Service>::Future {
@@ -210,8 +240,19 @@ impl VisualizerService {
                 Box::new(future::ok(rsp))
             },
             _ => Box::new(
-                Self::get_api(req, self.probe.clone()).map_err(|e| e.into()),
+                Self::get_api(req, self.probe.clone(), self.baseline.clone())
+                    .map_err(|e| e.into()),
+            ),
+        }
+    }
+
+    fn post(&self, req: hyper::Request) -> <Self as Service>::Future {
+        match req.path() {
+            "/api/probe/baseline" => Box::new(
+                Self::set_baseline(self.probe.clone(), self.baseline.clone())
+                    .map_err(|e| e.into()),
             ),
+            _ => Box::new(Self::not_found(req).map_err(|e| e.into())),
         }
     }
 
@@ -219,11 +260,20 @@ impl VisualizerService {
     fn get_api(
         req: hyper::Request,
         probe: Terminal,
+        baseline: Baseline,
     ) -> Result<hyper::Response> {
         if req.path() == "/api/probe/json" {
             await!(Self::probe_json(probe))
+        } else if req.path() == "/api/probe/diff" {
+            await!(Self::probe_diff(probe, baseline))
         } else if req.path() == "/api/probe/dot" {
             await!(Self::probe_dot(probe))
+        } else if req.path() == "/api/probe/schema" {
+            await!(Self::probe_schema())
+        } else if req.path() == "/api/metrics" {
+            await!(Self::metrics(probe))
+        } else if req.path() == "/metrics" {
+            await!(Self::prometheus(probe))
         } else {
             await!(Self::not_found(req))
         }
@@ -235,7 +285,7 @@ impl VisualizerService {
 
         match await!(probe.probe(probe::Settings::new())) {
             Ok(data) => {
-                rsp.set_body(serde_json::to_string(&data)?);
+                rsp.set_body(probe::Document::new(data).to_json()?);
             },
             Err(e) => {
                 rsp.set_status(hyper::StatusCode::InternalServerError);
@@ -263,6 +313,107 @@ impl VisualizerService {
         Ok(rsp)
     }
 
+    /// probe the organelle and keep the result as the baseline for diffs
+    #[async]
+    fn set_baseline(
+        probe: Terminal,
+        baseline: Baseline,
+    ) -> Result<hyper::Response> {
+        let mut rsp = hyper::Response::new();
+
+        match await!(probe.probe(probe::Settings::new())) {
+            Ok(data) => {
+                *baseline.borrow_mut() = Some(data.clone());
+
+                rsp.set_body(probe::Document::new(data).to_json()?);
+            },
+            Err(e) => {
+                rsp.set_status(hyper::StatusCode::InternalServerError);
+                rsp.set_body(format!("{:#?}", e));
+            },
+        }
+
+        Ok(rsp)
+    }
+
+    /// diff a fresh probe against the baseline, leaving the baseline as is
+    #[async]
+    fn probe_diff(
+        probe: Terminal,
+        baseline: Baseline,
+    ) -> Result<hyper::Response> {
+        let mut rsp = hyper::Response::new();
+
+        match await!(probe.probe(probe::Settings::new())) {
+            Ok(data) => {
+                // without a baseline there is nothing to compare against
+                let diff = match *baseline.borrow() {
+                    Some(ref before) => probe::diff(before, &data),
+                    None => probe::Diff::default(),
+                };
+
+                rsp.set_body(serde_json::to_string(&diff)?);
+            },
+            Err(e) => {
+                rsp.set_status(hyper::StatusCode::InternalServerError);
+                rsp.set_body(format!("{:#?}", e));
+            },
+        }
+
+        Ok(rsp)
+    }
+
+    /// the run-time metrics of every soma, at any depth
+    #[async]
+    fn metrics(probe: Terminal) -> Result<hyper::Response> {
+        let mut rsp = hyper::Response::new();
+
+        match await!(probe.probe(probe::Settings::new().include_state(false)))
+        {
+            Ok(data) => {
+                let mut metrics = vec![];
+                collect_metrics(&data, &mut metrics);
+
+                rsp.set_body(serde_json::to_string(&metrics)?);
+            },
+            Err(e) => {
+                rsp.set_status(hyper::StatusCode::InternalServerError);
+                rsp.set_body(format!("{:#?}", e));
+            },
+        }
+
+        Ok(rsp)
+    }
+
+    /// the run-time metrics in the Prometheus text exposition format
+    #[async]
+    fn prometheus(probe: Terminal) -> Result<hyper::Response> {
+        let mut rsp = hyper::Response::new();
+
+        match await!(probe.probe(probe::Settings::new().include_state(false)))
+        {
+            Ok(data) => {
+                rsp.headers_mut()
+                    .set_raw("Content-Type", prometheus::CONTENT_TYPE);
+                rsp.set_body(prometheus::render(&data));
+            },
+            Err(e) => {
+                rsp.set_status(hyper::StatusCode::InternalServerError);
+                rsp.set_body(format!("{:#?}", e));
+            },
+        }
+
+        Ok(rsp)
+    }
+
+    #[async]
+    fn probe_schema() -> Result<hyper::Response> {
+        let mut rsp = hyper::Response::new();
+        rsp.set_body(serde_json::to_string(&probe::schema())?);
+
+        Ok(rsp)
+    }
+
     #[async]
     fn not_found(req: hyper::Request) -> Result<hyper::Response> {
         let mut rsp = hyper::Response::new();
@@ -282,18 +433,37 @@ impl Service for VisualizerService {
     fn call(&self, req: hyper::Request) -> Self::Future {
         match req.method() {
             &hyper::Method::Get => self.get(req),
+            &hyper::Method::Post => self.post(req),
 
             _ => Box::new(Self::not_found(req).map_err(|e| e.into())),
         }
     }
 }
 
+fn collect_metrics(data: &SomaData, metrics: &mut Vec<MetricsData>) {
+    if let &SomaData::Organelle {
+        ref nucleus,
+        ref somas,
+        metrics: ref organelle,
+        ..
+    } = data
+    {
+        metrics.extend(organelle.iter().cloned());
+
+        collect_metrics(nucleus, metrics);
+
+        for soma in somas {
+            collect_metrics(soma, metrics);
+        }
+    }
+}
+
 fn render_organelle(
     uuid: Uuid,
     name: String,
     nucleus: SomaData,
     mut somas: Vec<SomaData>,
-    remap: &HashMap<Uuid, Uuid>,
+    graph: &probe::Graph,
 ) -> dot::SubGraph {
     let mut organelle = dot::SubGraph::new()
         .id(dot::Id::quoted(format!("cluster_{}", uuid)))
@@ -319,62 +489,21 @@ fn render_organelle(
                 uuid,
                 ref terminals,
                 ..
-            } => {
-                let src_uuid = uuid;
-
-                for t in terminals {
-                    match t {
-                        &ConstraintData::One { ref variant, soma } => {
-                            let tgt_uuid = if let Some(uuid) = remap.get(&soma)
-                            {
-                                *uuid
-                            } else {
-                                soma
-                            };
-
-                            edges.push(dot::NodeId::new(dot::Id::quoted(
-                                src_uuid.to_string(),
-                            )).port(dot::Id::ident(format!("t_{}", variant)))
-                                .connect(
-                                    dot::EdgeOp::Directed,
-                                    dot::NodeId::new(dot::Id::quoted(
-                                        tgt_uuid.to_string(),
-                                    )).port(dot::Id::ident(format!(
-                                        "d_{}",
-                                        variant
-                                    ))),
-                                ));
-                        },
-                        &ConstraintData::Variadic {
-                            ref variant,
-                            ref somas,
-                        } => for uuid in somas {
-                            let tgt_uuid =
-                                if let Some(remapped) = remap.get(&uuid) {
-                                    *remapped
-                                } else {
-                                    *uuid
-                                };
-
-                            edges.push(dot::NodeId::new(dot::Id::quoted(
-                                src_uuid.to_string(),
-                            )).port(dot::Id::ident(format!("t_{}", variant)))
-                                .connect(
-                                    dot::EdgeOp::Directed,
-                                    dot::NodeId::new(dot::Id::quoted(
-                                        tgt_uuid.to_string(),
-                                    )).port(dot::Id::ident(format!(
-                                        "d_{}",
-                                        variant
-                                    ))),
-                                ));
-                        },
-                    }
+            } => for t in terminals {
+                for soma in t.somas() {
+                    edges.push(render_edge(uuid, soma, t.variant(), graph));
                 }
             },
+            &SomaData::Soma {
+                uuid,
+                ref terminals,
+                ..
+            } => for t in terminals {
+                edges.push(render_edge(uuid, t.soma, &t.variant, graph));
+            },
             _ => (),
         }
-        organelle = organelle.add(render_soma(soma, remap));
+        organelle = organelle.add(render_soma(soma, graph));
     }
 
     for edge in edges {
@@ -384,44 +513,92 @@ fn render_organelle(
     organelle
 }
 
+fn render_edge(
+    src_uuid: Uuid,
+    tgt_uuid: Uuid,
+    variant: &str,
+    graph: &probe::Graph,
+) -> dot::Edge {
+    // synapses to an organelle are drawn to the soma that handles them
+    let tgt_uuid = graph.dendrite_handler(tgt_uuid, variant);
+
+    dot::NodeId::new(dot::Id::quoted(src_uuid.to_string()))
+        .port(dot::Id::ident(format!("t_{}", variant)))
+        .connect(
+            dot::EdgeOp::Directed,
+            dot::NodeId::new(dot::Id::quoted(tgt_uuid.to_string()))
+                .port(dot::Id::ident(format!("d_{}", variant))),
+        )
+}
+
 fn render_axon(
     uuid: Uuid,
     name: String,
     terminals: Vec<ConstraintData>,
     dendrites: Vec<ConstraintData>,
-    _remap: &HashMap<Uuid, Uuid>,
 ) -> dot::SubGraph {
-    let mut axon = dot::SubGraph::new();
+    let ports = |constraints: Vec<ConstraintData>| {
+        let mut ports: Vec<(String, String)> = vec![];
+
+        // one port per variant, labelled with the number of synapses accepted
+        for c in constraints {
+            if !ports.iter().any(|&(ref variant, _)| variant == c.variant()) {
+                ports.push((
+                    c.variant().to_string(),
+                    format!("{} [{}]", c.variant(), c.bounds()),
+                ));
+            }
+        }
 
-    let terminals: Vec<String> = terminals
-        .into_iter()
-        .map(|t| match t {
-            ConstraintData::One { variant, .. } => {
-                format!("<t_{}> {}", variant, variant)
-            },
-            ConstraintData::Variadic { variant, .. } => {
-                format!("<t_{}> {}", variant, variant)
-            },
-        })
-        .collect();
+        ports
+    };
+
+    render_node(uuid, name, ports(terminals), ports(dendrites))
+}
+
+fn render_plain_soma(
+    uuid: Uuid,
+    name: String,
+    terminals: Vec<ConnectionData>,
+    dendrites: Vec<ConnectionData>,
+) -> dot::SubGraph {
+    let variants = |connections: Vec<ConnectionData>| {
+        let mut variants = vec![];
+
+        // one port per variant, no matter how many somas are connected
+        for c in connections {
+            if !variants.iter().any(|&(ref variant, _)| *variant == c.variant)
+            {
+                variants.push((c.variant.clone(), c.variant));
+            }
+        }
 
-    let terminals = terminals.join(" | ");
+        variants
+    };
 
-    let dendrites: Vec<String> = dendrites
+    render_node(uuid, name, variants(terminals), variants(dendrites))
+}
+
+/// render a soma with a port for each `(variant, label)` pair
+fn render_node(
+    uuid: Uuid,
+    name: String,
+    terminals: Vec<(String, String)>,
+    dendrites: Vec<(String, String)>,
+) -> dot::SubGraph {
+    let terminals = terminals
         .into_iter()
-        .map(|d| match d {
-            ConstraintData::One { variant, .. } => {
-                format!("<d_{}> {}", variant, variant)
-            },
-            ConstraintData::Variadic { variant, .. } => {
-                format!("<d_{}> {}", variant, variant)
-            },
-        })
-        .collect();
+        .map(|(variant, label)| format!("<t_{}> {}", variant, label))
+        .collect::<Vec<_>>()
+        .join(" | ");
 
-    let dendrites = dendrites.join(" | ");
+    let dendrites = dendrites
+        .into_iter()
+        .map(|(variant, label)| format!("<d_{}> {}", variant, label))
+        .collect::<Vec<_>>()
+        .join(" | ");
 
-    axon = axon.add(
+    dot::SubGraph::new().add(
         dot::Node::new(dot::Id::quoted(uuid.to_string()))
             .add(dot::Attribute::new(
                 dot::Id::ident("label"),
@@ -440,56 +617,56 @@ fn render_axon(
                 dot::Id::ident("style"),
                 dot::Id::ident("rounded"),
             )),
-    );
+    )
+}
 
-    axon
+fn render_unresponsive(uuid: Uuid, name: String) -> dot::SubGraph {
+    dot::SubGraph::new().add(
+        dot::Node::new(dot::Id::quoted(uuid.to_string()))
+            .add(dot::Attribute::new(
+                dot::Id::ident("label"),
+                dot::Id::quoted(format!(
+                    "<name> {} | unresponsive",
+                    name.replace("<", "\\<").replace(">", "\\>"),
+                )),
+            ))
+            .add(dot::Attribute::new(
+                dot::Id::ident("shape"),
+                dot::Id::ident("Mrecord"),
+            ))
+            .add(dot::Attribute::new(
+                dot::Id::ident("style"),
+                dot::Id::quoted("rounded,dashed"),
+            )),
+    )
 }
 
-fn render_soma(data: SomaData, remap: &HashMap<Uuid, Uuid>) -> dot::SubGraph {
+fn render_soma(data: SomaData, graph: &probe::Graph) -> dot::SubGraph {
     match data {
         SomaData::Organelle {
             uuid,
             nucleus,
             somas,
             name,
-        } => render_organelle(uuid, name, *nucleus, somas, remap),
+            ..
+        } => render_organelle(uuid, name, *nucleus, somas, graph),
         SomaData::Axon {
             terminals,
             dendrites,
             uuid,
             name,
-        } => render_axon(uuid, name, terminals, dendrites, remap),
-        _ => unimplemented!(),
-    }
-}
-
-fn get_uuid(data: &SomaData) -> Option<Uuid> {
-    match data {
-        &SomaData::Organelle { ref nucleus, .. } => get_uuid(nucleus),
-        &SomaData::Axon { uuid, .. } => Some(uuid),
-        _ => None,
-    }
-}
-
-fn remap_uuids(data: &SomaData, remap: &mut HashMap<Uuid, Uuid>) {
-    match data {
-        &SomaData::Organelle {
+            ..
+        } => render_axon(uuid, name, terminals, dendrites),
+        SomaData::Soma {
+            terminals,
+            dendrites,
             uuid,
-            ref nucleus,
-            ref somas,
+            name,
             ..
-        } => {
-            if let Some(inner_uuid) = get_uuid(data) {
-                remap.insert(uuid, inner_uuid);
-            }
-
-            remap_uuids(nucleus, remap);
-
-            for soma in somas {
-                remap_uuids(soma, remap);
-            }
+        } => render_plain_soma(uuid, name, terminals, dendrites),
+        SomaData::Unresponsive { uuid, name } => {
+            render_unresponsive(uuid, name)
         },
-        _ => (),
     }
 }
 
@@ -497,12 +674,10 @@ fn render_dot(data: SomaData) -> Result<String> {
     let buf = Vec::new();
     let mut writer = buf.writer();
 
-    let mut remap = HashMap::new();
-
-    remap_uuids(&data, &mut remap);
+    let graph = probe::Graph::new(&data);
 
     let dot = dot::Dot::DiGraph(
-        dot::SubGraph::new().add(render_soma(data, &remap)).add(
+        dot::SubGraph::new().add(render_soma(data, &graph)).add(
             dot::Attribute::new(
                 dot::Id::ident("rankdir"),
                 dot::Id::ident("LR"),
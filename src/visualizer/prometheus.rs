@@ -0,0 +1,205 @@
+use probe::{MetricsData, SomaData};
+use soma;
+
+/// content type of the Prometheus text exposition format
+pub const CONTENT_TYPE: &'static str = "text/plain; version=0.0.4";
+
+/// render the metrics of a probe in the Prometheus text exposition format
+///
+/// organelles are labelled with the path of uuids leading to them, since
+/// sibling organelles may share a name, along with their own name. somas are
+/// labelled with their name, uuid and the path of the organelle that contains
+/// them.
+pub fn render(data: &SomaData) -> String {
+    let mut organelles = vec![];
+    let mut somas = vec![];
+
+    collect(data, "", &mut organelles, &mut somas);
+
+    let mut out = String::new();
+
+    family(
+        &mut out,
+        "organelle_somas",
+        "gauge",
+        "number of somas in an organelle, including its nucleus",
+    );
+    for &(ref path, ref name, count) in &organelles {
+        sample(
+            &mut out,
+            "organelle_somas",
+            &organelle_labels(path, name),
+            count,
+        );
+    }
+
+    family(
+        &mut out,
+        "organelle_soma_impulses_total",
+        "counter",
+        "impulses taken from the mailbox of a soma",
+    );
+    for &(ref path, ref m) in &somas {
+        sample(
+            &mut out,
+            "organelle_soma_impulses_total",
+            &soma_labels(path, m),
+            m.impulses,
+        );
+    }
+
+    family(
+        &mut out,
+        "organelle_soma_updates_total",
+        "counter",
+        "calls to the update of a soma",
+    );
+    for &(ref path, ref m) in &somas {
+        sample(
+            &mut out,
+            "organelle_soma_updates_total",
+            &soma_labels(path, m),
+            m.updates,
+        );
+    }
+
+    family(
+        &mut out,
+        "organelle_soma_update_seconds",
+        "summary",
+        "time spent in the update of a soma",
+    );
+    for &(ref path, ref m) in &somas {
+        let labels = soma_labels(path, m);
+
+        for &(quantile, us) in
+            &[("0.5", m.p50_update_us), ("0.99", m.p99_update_us)]
+        {
+            sample(
+                &mut out,
+                "organelle_soma_update_seconds",
+                &format!("{},quantile=\"{}\"", labels, quantile),
+                seconds(us),
+            );
+        }
+
+        sample(
+            &mut out,
+            "organelle_soma_update_seconds_sum",
+            &labels,
+            seconds(m.update_time_us),
+        );
+        sample(
+            &mut out,
+            "organelle_soma_update_seconds_count",
+            &labels,
+            m.updates,
+        );
+    }
+
+    family(
+        &mut out,
+        "organelle_soma_mailbox_depth",
+        "gauge",
+        "impulses waiting in the mailbox of a soma",
+    );
+    for &(ref path, ref m) in &somas {
+        sample(
+            &mut out,
+            "organelle_soma_mailbox_depth",
+            &soma_labels(path, m),
+            m.mailbox_depth,
+        );
+    }
+
+    family(
+        &mut out,
+        "organelle_soma_errors_total",
+        "counter",
+        "errors raised by a soma",
+    );
+    for &(ref path, ref m) in &somas {
+        sample(
+            &mut out,
+            "organelle_soma_errors_total",
+            &soma_labels(path, m),
+            m.errors,
+        );
+    }
+
+    out
+}
+
+/// gather the organelles and soma metrics along with their organelle paths
+fn collect(
+    data: &SomaData,
+    parent: &str,
+    organelles: &mut Vec<(String, String, usize)>,
+    somas: &mut Vec<(String, MetricsData)>,
+) {
+    if let &SomaData::Organelle {
+        ref name,
+        ref nucleus,
+        somas: ref children,
+        ref metrics,
+        uuid,
+        ..
+    } = data
+    {
+        let path = if parent.is_empty() {
+            uuid.to_string()
+        } else {
+            format!("{}/{}", parent, uuid)
+        };
+
+        organelles.push((
+            path.clone(),
+            soma::short_name(name),
+            children.len() + 1,
+        ));
+
+        for m in metrics {
+            somas.push((path.clone(), m.clone()));
+        }
+
+        collect(nucleus, &path, organelles, somas);
+
+        for child in children {
+            collect(child, &path, organelles, somas);
+        }
+    }
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    out.push_str(&format!("# HELP {} {}\n", name, help));
+    out.push_str(&format!("# TYPE {} {}\n", name, kind));
+}
+
+fn sample<T: ToString>(out: &mut String, name: &str, labels: &str, value: T) {
+    out.push_str(&format!("{}{{{}}} {}\n", name, labels, value.to_string()));
+}
+
+fn organelle_labels(path: &str, name: &str) -> String {
+    format!("organelle=\"{}\",name=\"{}\"", escape(path), escape(name))
+}
+
+fn soma_labels(path: &str, metrics: &MetricsData) -> String {
+    format!(
+        "organelle=\"{}\",soma=\"{}\",uuid=\"{}\"",
+        escape(path),
+        escape(&soma::short_name(&metrics.name)),
+        metrics.soma
+    )
+}
+
+fn seconds(us: u64) -> f64 {
+    us as f64 / 1_000_000.0
+}
+
+/// escape a label value as required by the exposition format
+fn escape(value: &str) -> String {
+    value
+        .replace("\\", "\\\\")
+        .replace("\"", "\\\"")
+        .replace("\n", "\\n")
+}
@@ -6,11 +6,13 @@ extern crate error_chain;
 extern crate futures_await as futures;
 extern crate organelle;
 extern crate tokio_core;
+extern crate uuid;
 
 use futures::prelude::*;
 use futures::unsync;
 use organelle::*;
 use tokio_core::reactor;
+use uuid::Uuid;
 
 #[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
 enum Synapse {
@@ -68,7 +70,7 @@ impl Soma for GiverSoma {
                 Synapse::GiveSomething,
                 Terminal::Giver(tx),
             ) => Ok(Self { tx: Some(tx) }),
-            Impulse::Start(_, _, _) => {
+            Impulse::Start(_) => {
                 await!(
                     self.tx
                         .unwrap()
@@ -109,14 +111,14 @@ impl Soma for TakerSoma {
                 Synapse::GiveSomething,
                 Dendrite::Taker(rx),
             ) => Ok(Self { rx: Some(rx) }),
-            Impulse::Start(_, tx, _) => {
+            Impulse::Start(ctx) => {
                 await!(
                     self.rx
                         .unwrap()
-                        .for_each(move |_| tx.clone()
-                            .send(Impulse::Stop)
-                            .map(|_| ())
-                            .map_err(|_| ()))
+                        .for_each(move |_| {
+                            ctx.stop();
+                            Ok(())
+                        })
                         .map_err(|_| Error::from("unable to stop"))
                 )?;
 
@@ -143,7 +145,7 @@ fn test_invalid_input() {
 
     if let Err(e) = core.run(organelle.run(handle)) {
         match e.kind() {
-            &ErrorKind::InvalidSynapse(ref msg) => {
+            &ErrorKind::InvalidSynapse(_, ref msg) => {
                 println!("got expected error: {}", *msg)
             },
             _ => panic!("GiverSoma spewed an unexpected error: {:#?}", e),
@@ -176,7 +178,7 @@ fn test_require_one() {
     {
         if let Err(e) = core.run(TakerSoma::axon().run(handle.clone())) {
             match e.kind() {
-                &ErrorKind::MissingSynapse(ref msg) => {
+                &ErrorKind::MissingSynapse(_, ref msg) => {
                     println!("got expected error: {}", *msg)
                 },
                 _ => panic!("unexpected error: {:#?}", e),
@@ -187,7 +189,7 @@ fn test_require_one() {
 
         if let Err(e) = core.run(GiverSoma::axon().run(handle.clone())) {
             match e.kind() {
-                &ErrorKind::MissingSynapse(ref msg) => {
+                &ErrorKind::MissingSynapse(_, ref msg) => {
                     println!("got expected error: {}", *msg)
                 },
                 _ => panic!("unexpected error: {:#?}", e),
@@ -197,3 +199,493 @@ fn test_require_one() {
         }
     }
 }
+
+#[test]
+fn test_validate() {
+    let core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    // a fully wired organelle has nothing to report
+    {
+        let mut organelle = Organelle::new(GiverSoma::axon(), handle.clone());
+
+        let giver = organelle.nucleus();
+        let taker = organelle.add_soma(TakerSoma::axon());
+
+        organelle
+            .connect(giver, taker, Synapse::GiveSomething)
+            .unwrap();
+
+        let report = organelle.validate();
+
+        assert!(report.is_valid());
+        assert!(report.issues.is_empty());
+    }
+
+    // every wiring mistake is reported at once
+    {
+        let mut organelle = Organelle::new(GiverSoma::axon(), handle.clone());
+
+        let giver1 = organelle.nucleus();
+        let giver2 = organelle.add_soma(GiverSoma::axon());
+        let giver3 = organelle.add_soma(GiverSoma::axon());
+
+        let missing = organelle.add_soma(TakerSoma::axon());
+        let duplicate = organelle.add_soma(TakerSoma::axon());
+        let unknown = organelle.add_soma(Axon::new(
+            TakerSoma { rx: None },
+            vec![],
+            vec![],
+        ));
+        let variadic = organelle.add_soma(Axon::new(
+            TakerSoma { rx: None },
+            vec![Constraint::Variadic(Synapse::GiveSomething)],
+            vec![],
+        ));
+
+        organelle
+            .connect(giver1, duplicate, Synapse::GiveSomething)
+            .unwrap();
+        organelle
+            .connect(giver2, duplicate, Synapse::GiveSomething)
+            .unwrap();
+        organelle
+            .connect(giver3, unknown, Synapse::GiveSomething)
+            .unwrap();
+
+        let report = organelle.validate();
+
+        let kinds = |uuid| {
+            report
+                .issues
+                .iter()
+                .filter(|issue| issue.origin.uuid == Some(uuid))
+                .map(|issue| issue.kind)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            kinds(missing),
+            vec![IssueKind::UnusedSoma, IssueKind::MissingSynapse]
+        );
+        assert_eq!(kinds(duplicate), vec![IssueKind::DuplicateSynapse]);
+        assert_eq!(kinds(unknown), vec![IssueKind::UnknownSynapse]);
+        assert_eq!(
+            kinds(variadic),
+            vec![IssueKind::UnusedSoma, IssueKind::UnconnectedPort]
+        );
+
+        assert!(!report.is_valid());
+        assert_eq!(report.errors().len(), 3);
+        assert_eq!(report.warnings().len(), 3);
+    }
+
+    // any constraint that may stay empty is reported when it does
+    {
+        let mut organelle = Organelle::new(GiverSoma::axon(), handle.clone());
+
+        let optional = vec![
+            Constraint::Optional(Synapse::GiveSomething),
+            Constraint::AtLeast(Synapse::GiveSomething, 0),
+            Constraint::AtMost(Synapse::GiveSomething, 2),
+            Constraint::Range(Synapse::GiveSomething, 0, 2),
+        ];
+
+        for constraint in optional {
+            let uuid = organelle.add_soma(Axon::new(
+                TakerSoma { rx: None },
+                vec![constraint],
+                vec![],
+            ));
+
+            let report = organelle.validate();
+            let kinds = report
+                .issues
+                .iter()
+                .filter(|issue| issue.origin.uuid == Some(uuid))
+                .map(|issue| issue.kind)
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                kinds,
+                vec![IssueKind::UnusedSoma, IssueKind::UnconnectedPort]
+            );
+        }
+    }
+
+    // nested organelles are checked against their ports and their own wiring
+    {
+        let mut nested = Organelle::new(
+            Axon::new(TakerSoma { rx: None }, vec![], vec![]),
+            handle.clone(),
+        );
+        nested.set_name("nested");
+
+        let port = nested.add_soma(TakerSoma::axon());
+        let unwired = nested.add_soma(TakerSoma::axon());
+
+        nested
+            .expose_dendrite(Constraint::One(Synapse::GiveSomething), port)
+            .unwrap();
+
+        let mut organelle = Organelle::new(GiverSoma::axon(), handle.clone());
+
+        let giver = organelle.nucleus();
+        let nested = organelle.add_soma(nested);
+
+        let issues = |organelle: &Organelle<Axon<GiverSoma>>| {
+            organelle
+                .validate()
+                .issues
+                .into_iter()
+                .map(|issue| (issue.origin.uuid, issue.origin.path, issue.kind))
+                .collect::<Vec<_>>()
+        };
+        let inner = vec!["nested".to_string()];
+
+        assert_eq!(
+            issues(&organelle),
+            vec![
+                (Some(giver), vec![], IssueKind::MissingSynapse),
+                (Some(nested), vec![], IssueKind::UnusedSoma),
+                (Some(nested), vec![], IssueKind::MissingSynapse),
+                (Some(unwired), inner.clone(), IssueKind::UnusedSoma),
+                (Some(unwired), inner.clone(), IssueKind::MissingSynapse),
+            ]
+        );
+
+        // the synapse given to the organelle reaches the soma on its port
+        organelle
+            .connect(giver, nested, Synapse::GiveSomething)
+            .unwrap();
+
+        assert_eq!(
+            issues(&organelle),
+            vec![
+                (Some(unwired), inner.clone(), IssueKind::UnusedSoma),
+                (Some(unwired), inner.clone(), IssueKind::MissingSynapse),
+            ]
+        );
+    }
+
+    // synapses wired by hand from outside the organelle count as well
+    {
+        let mut organelle = Organelle::new(GiverSoma::axon(), handle.clone());
+
+        let giver = organelle.nucleus();
+        let taker = organelle.add_soma(TakerSoma::axon());
+
+        let (tx, rx) = unsync::mpsc::channel(1);
+
+        organelle
+            .add_terminal(
+                (Uuid::new_v4(), Terminal::Giver(tx)),
+                giver,
+                Synapse::GiveSomething,
+            )
+            .unwrap();
+        organelle
+            .add_dendrite(
+                (Uuid::new_v4(), Dendrite::Taker(rx)),
+                taker,
+                Synapse::GiveSomething,
+            )
+            .unwrap();
+
+        assert!(organelle.validate().issues.is_empty());
+    }
+}
+
+#[test]
+fn test_bounded_constraints() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let taker = |constraint| {
+        Axon::new(TakerSoma { rx: None }, vec![constraint], vec![])
+    };
+
+    // too few synapses are caught when the axon starts
+    if let Err(e) = core.run(
+        taker(Constraint::AtLeast(Synapse::GiveSomething, 2))
+            .run(handle.clone()),
+    ) {
+        match e.kind() {
+            &ErrorKind::MissingSynapse(_, ref msg) => {
+                println!("got expected error: {}", *msg)
+            },
+            _ => panic!("unexpected error: {:#?}", e),
+        }
+    } else {
+        panic!("TakerSoma needs at least two inputs, so it should fail")
+    }
+
+    // too many synapses are caught while wiring
+    {
+        let mut organelle = Organelle::new(
+            taker(Constraint::Range(Synapse::GiveSomething, 1, 2)),
+            handle.clone(),
+        );
+
+        let taker = organelle.nucleus();
+
+        for _ in 0..3 {
+            let giver = organelle.add_soma(GiverSoma::axon());
+
+            organelle
+                .connect(giver, taker, Synapse::GiveSomething)
+                .unwrap();
+        }
+
+        if let Err(e) = core.run(organelle.run(handle.clone())) {
+            match e.kind() {
+                &ErrorKind::InvalidSynapse(_, ref msg) => {
+                    println!("got expected error: {}", *msg)
+                },
+                _ => panic!("unexpected error: {:#?}", e),
+            }
+        } else {
+            panic!("TakerSoma accepts at most two inputs, so it should fail")
+        }
+    }
+
+    // validation checks the bounds of every constraint
+    {
+        let mut organelle = Organelle::new(GiverSoma::axon(), handle.clone());
+
+        let giver1 = organelle.nucleus();
+        let giver2 = organelle.add_soma(GiverSoma::axon());
+
+        let optional = organelle.add_soma(taker(Constraint::Optional(
+            Synapse::GiveSomething,
+        )));
+        let at_most = organelle.add_soma(taker(Constraint::AtMost(
+            Synapse::GiveSomething,
+            1,
+        )));
+
+        organelle
+            .connect(giver1, at_most, Synapse::GiveSomething)
+            .unwrap();
+        organelle
+            .connect(giver2, at_most, Synapse::GiveSomething)
+            .unwrap();
+
+        let report = organelle.validate();
+
+        assert_eq!(
+            report
+                .issues
+                .iter()
+                .map(|issue| (issue.origin.uuid, issue.kind))
+                .collect::<Vec<_>>(),
+            vec![
+                (Some(optional), IssueKind::UnusedSoma),
+                (Some(optional), IssueKind::UnconnectedPort),
+                (Some(at_most), IssueKind::DuplicateSynapse),
+            ]
+        );
+    }
+}
+
+#[test]
+#[should_panic(
+    expected = "range for GiveSomething has a minimum above its maximum"
+)]
+fn test_inverted_range() {
+    Axon::new(
+        TakerSoma { rx: None },
+        vec![Constraint::Range(Synapse::GiveSomething, 2, 1)],
+        vec![],
+    );
+}
+
+#[test]
+fn test_peer_constraints() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    // peers of the right type are accepted
+    {
+        let mut organelle = Organelle::new(GiverSoma::axon(), handle.clone());
+
+        let giver = organelle.nucleus();
+        let taker = organelle.add_soma(TakerSoma::axon().with_dendrite_peer(
+            Synapse::GiveSomething,
+            PeerConstraint::soma::<GiverSoma>(),
+        ));
+
+        organelle
+            .connect(giver, taker, Synapse::GiveSomething)
+            .unwrap();
+
+        assert!(organelle.validate().is_valid());
+
+        core.run(organelle.run(handle.clone())).unwrap();
+    }
+
+    // peers without the required tag are rejected
+    {
+        let mut organelle = Organelle::new(GiverSoma::axon(), handle.clone());
+
+        let giver = organelle.nucleus();
+        let taker = organelle.add_soma(TakerSoma::axon().with_dendrite_peer(
+            Synapse::GiveSomething,
+            PeerConstraint::tag("production"),
+        ));
+
+        organelle
+            .connect(giver, taker, Synapse::GiveSomething)
+            .unwrap();
+
+        let report = organelle.validate();
+
+        assert_eq!(report.errors().len(), 1);
+        assert_eq!(report.errors()[0].kind, IssueKind::WrongPeer);
+        assert_eq!(report.errors()[0].origin.uuid, Some(taker));
+
+        if let Err(e) = core.run(organelle.run(handle.clone())) {
+            match e.kind() {
+                &ErrorKind::InvalidSynapse(_, ref msg) => {
+                    println!("got expected error: {}", *msg)
+                },
+                _ => panic!("unexpected error: {:#?}", e),
+            }
+        } else {
+            panic!("GiverSoma is not tagged, so TakerSoma should fail")
+        }
+    }
+
+    // tagging the peer satisfies the constraint
+    {
+        let mut organelle = Organelle::new(GiverSoma::axon(), handle.clone());
+
+        let giver = organelle.nucleus();
+        let taker = organelle.add_soma(TakerSoma::axon().with_dendrite_peer(
+            Synapse::GiveSomething,
+            PeerConstraint::tag("production"),
+        ));
+
+        organelle
+            .connect(giver, taker, Synapse::GiveSomething)
+            .unwrap();
+        organelle.add_tag(giver, "production").unwrap();
+
+        assert!(organelle.validate().is_valid());
+
+        core.run(organelle.run(handle.clone())).unwrap();
+    }
+}
+
+#[test]
+fn test_runtime_violations() {
+    let origin = SomaOrigin::new(None, "TakerSoma");
+    let giver = Uuid::new_v4();
+
+    let mut requirements = Requirements::new(
+        vec![Constraint::One(Synapse::GiveSomething)],
+        vec![],
+    );
+
+    // unknown synapses are still rejected before startup
+    assert!(
+        requirements
+            .add_terminal(&origin, giver, Synapse::GiveSomething)
+            .is_err()
+    );
+
+    let issues = requirements
+        .add_dendrite(&origin, giver, Synapse::GiveSomething)
+        .unwrap();
+
+    assert!(issues.is_empty());
+    requirements.start(&origin).unwrap();
+
+    // losing a required synapse is returned instead of failing
+    let issues =
+        requirements.remove_dendrite(&origin, giver, Synapse::GiveSomething);
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, IssueKind::MissingSynapse);
+    assert_eq!(requirements.violations(), &issues[..]);
+
+    // so is a synapse without a constraint once started
+    let issues = requirements
+        .add_terminal(&origin, giver, Synapse::GiveSomething)
+        .unwrap();
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, IssueKind::UnknownSynapse);
+    assert_eq!(requirements.violations().len(), 2);
+
+    // known violations are only returned once
+    let issues = requirements
+        .add_dendrite(&origin, giver, Synapse::GiveSomething)
+        .unwrap();
+
+    assert!(issues.is_empty());
+    assert_eq!(requirements.violations().len(), 1);
+
+    requirements.remove_terminal(&origin, giver, Synapse::GiveSomething);
+
+    assert!(requirements.violations().is_empty());
+}
+
+#[test]
+fn test_runtime_peer_violations() {
+    let origin = SomaOrigin::new(None, "TakerSoma");
+    let directory = Directory::default();
+
+    let first = Uuid::new_v4();
+    let second = Uuid::new_v4();
+
+    let mut requirements = Requirements::new(
+        vec![Constraint::Variadic(Synapse::GiveSomething)],
+        vec![],
+    );
+    requirements.add_dendrite_peer(
+        Synapse::GiveSomething,
+        PeerConstraint::tag("production"),
+    );
+    requirements.start(&origin).unwrap();
+
+    // a peer that does not match is returned instead of failing
+    requirements
+        .add_dendrite(&origin, first, Synapse::GiveSomething)
+        .unwrap();
+    let issues = requirements.check_dendrite_peer(
+        &origin,
+        &directory,
+        first,
+        Synapse::GiveSomething,
+    );
+
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].kind, IssueKind::WrongPeer);
+    assert_eq!(requirements.violations(), &issues[..]);
+
+    // only the added synapse is checked
+    requirements
+        .add_dendrite(&origin, second, Synapse::GiveSomething)
+        .unwrap();
+    let issues = requirements.check_dendrite_peer(
+        &origin,
+        &directory,
+        second,
+        Synapse::GiveSomething,
+    );
+
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.ends_with(&second.to_string()));
+    assert_eq!(requirements.violations().len(), 2);
+
+    // removing the synapse resolves the violation
+    requirements.remove_dendrite(&origin, first, Synapse::GiveSomething);
+
+    assert_eq!(requirements.violations().len(), 1);
+    assert!(
+        requirements.violations()[0]
+            .message
+            .ends_with(&second.to_string())
+    );
+}
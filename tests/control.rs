@@ -0,0 +1,420 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate tokio_core;
+extern crate uuid;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use futures::prelude::*;
+use futures::unsync::oneshot;
+use organelle::*;
+use tokio_core::reactor;
+use uuid::Uuid;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+enum Synapse {
+    Nothing,
+}
+
+impl organelle::Synapse for Synapse {
+    type Terminal = ();
+    type Dendrite = ();
+
+    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+        match self {
+            Synapse::Nothing => ((), ()),
+        }
+    }
+}
+
+/// grows a worker once started, then prunes it again
+struct GrowerSoma {
+    nucleus: Uuid,
+    ctx: Option<SomaContext<Synapse>>,
+    worker: Rc<Cell<bool>>,
+    removed: Rc<Cell<bool>>,
+}
+
+impl Soma for GrowerSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(ctx) => {
+                let control = ctx
+                    .control::<Synapse>()
+                    .ok_or_else(|| Error::from("grower has no control"))?;
+
+                if control.remove_soma(self.nucleus).is_ok() {
+                    bail!("the nucleus should not be removable")
+                }
+
+                let worker = control.add_soma(WorkerSoma {
+                    started: self.worker.clone(),
+                });
+
+                control.connect(ctx.uuid(), worker, Synapse::Nothing)?;
+                control.start(worker)?;
+
+                if control.start(worker).is_ok() {
+                    bail!("the worker should only start once")
+                }
+
+                control.remove_soma(worker)?;
+                self.ctx = Some(ctx);
+
+                Ok(self)
+            },
+            Impulse::AddTerminal(_, Synapse::Nothing, _) => Ok(self),
+            Impulse::RemoveTerminal(_, Synapse::Nothing) => {
+                self.removed.set(true);
+
+                if let Some(ref ctx) = self.ctx {
+                    ctx.stop();
+                }
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+struct IdleSoma;
+
+impl Soma for IdleSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+struct WorkerSoma {
+    started: Rc<Cell<bool>>,
+}
+
+impl Soma for WorkerSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, Synapse::Nothing, _) => Ok(self),
+            Impulse::Start(_) => {
+                self.started.set(true);
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// swaps the source of the listener while the organelle runs
+struct RewirerSoma {
+    control: Control<Synapse>,
+    listener: Uuid,
+    old_source: Uuid,
+    new_source: Uuid,
+}
+
+impl Soma for RewirerSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_) => {
+                self.control.disconnect(
+                    self.old_source,
+                    self.listener,
+                    Synapse::Nothing,
+                )?;
+
+                let disconnected = self.control.disconnect(
+                    self.old_source,
+                    self.listener,
+                    Synapse::Nothing,
+                );
+
+                if disconnected.is_ok() {
+                    bail!("the synapse should already be disconnected")
+                }
+
+                self.control.connect(
+                    self.new_source,
+                    self.listener,
+                    Synapse::Nothing,
+                )?;
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+struct SourceSoma;
+
+impl Soma for SourceSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(_, Synapse::Nothing, _)
+            | Impulse::RemoveTerminal(_, Synapse::Nothing)
+            | Impulse::Start(_) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// stops the organelle once its source has been swapped
+struct ListenerSoma {
+    sources: Rc<Cell<usize>>,
+    ctx: Option<SomaContext<Synapse>>,
+}
+
+impl ListenerSoma {
+    fn axon(sources: Rc<Cell<usize>>) -> Axon<Self> {
+        Axon::new(
+            ListenerSoma {
+                sources: sources,
+                ctx: None,
+            },
+            vec![Constraint::One(Synapse::Nothing)],
+            vec![],
+        )
+    }
+}
+
+impl Soma for ListenerSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, Synapse::Nothing, _) => {
+                self.sources.set(self.sources.get() + 1);
+
+                if let Some(ref ctx) = self.ctx {
+                    ctx.stop();
+                }
+
+                Ok(self)
+            },
+            Impulse::RemoveDendrite(_, Synapse::Nothing) => Ok(self),
+            Impulse::Start(ctx) => {
+                self.ctx = Some(ctx);
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// connects a second source to a running axon, then probes it
+struct HotWirerSoma {
+    control: Control<Synapse>,
+    listener: Uuid,
+    source: Uuid,
+    violations: Rc<RefCell<Vec<String>>>,
+}
+
+impl HotWirerSoma {
+    #[async]
+    fn probe_listener(
+        ctx: SomaContext<Synapse>,
+        listener: Uuid,
+        violations: Rc<RefCell<Vec<String>>>,
+    ) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+
+        await!(
+            ctx.sender()
+                .send(Impulse::Probe(probe::Settings::new(), tx))
+                .map_err(|_| Error::from("unable to send probe"))
+        )?;
+
+        let data =
+            await!(rx.map_err(|_| Error::from("unable to receive probe")))?;
+
+        if let SomaData::Organelle { somas, .. } = data {
+            for soma in somas {
+                if let SomaData::Axon {
+                    uuid,
+                    violations: found,
+                    ..
+                } = soma
+                {
+                    if uuid == listener {
+                        *violations.borrow_mut() = found;
+                    }
+                }
+            }
+        }
+
+        ctx.stop();
+
+        Ok(())
+    }
+}
+
+impl Soma for HotWirerSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(ctx) => {
+                // the listener only takes one source
+                self.control.connect(
+                    self.source,
+                    self.listener,
+                    Synapse::Nothing,
+                )?;
+
+                ctx.spawn(Self::probe_listener(
+                    ctx.clone(),
+                    self.listener,
+                    self.violations.clone(),
+                ));
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[test]
+fn test_add_and_remove_at_runtime() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let worker = Rc::new(Cell::new(false));
+    let removed = Rc::new(Cell::new(false));
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+    let grower = GrowerSoma {
+        nucleus: organelle.nucleus(),
+        ctx: None,
+        worker: worker.clone(),
+        removed: removed.clone(),
+    };
+    organelle.add_soma(grower);
+
+    core.run(organelle.run(handle)).unwrap();
+
+    assert!(worker.get(), "worker should start at runtime");
+    assert!(removed.get(), "peers should be notified of removed somas");
+}
+
+#[test]
+fn test_rewire_at_runtime() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let sources = Rc::new(Cell::new(0));
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+    let listener = organelle.add_soma(ListenerSoma::axon(sources.clone()));
+    let old_source = organelle.add_soma(SourceSoma);
+    let new_source = organelle.add_soma(SourceSoma);
+
+    organelle
+        .connect(old_source, listener, Synapse::Nothing)
+        .unwrap();
+
+    let rewirer = RewirerSoma {
+        control: organelle.control(),
+        listener: listener,
+        old_source: old_source,
+        new_source: new_source,
+    };
+    organelle.add_soma(rewirer);
+
+    core.run(organelle.run(handle)).unwrap();
+
+    assert_eq!(sources.get(), 2);
+}
+
+#[test]
+fn test_hot_wired_violations() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let recorder = Recorder::memory();
+    let violations = Rc::new(RefCell::new(vec![]));
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+    organelle.set_recorder(recorder.clone());
+
+    let listener = organelle.add_soma(Axon::new(
+        WorkerSoma {
+            started: Rc::new(Cell::new(false)),
+        },
+        vec![Constraint::One(Synapse::Nothing)],
+        vec![],
+    ));
+    let old_source = organelle.add_soma(SourceSoma);
+    let new_source = organelle.add_soma(SourceSoma);
+
+    organelle
+        .connect(old_source, listener, Synapse::Nothing)
+        .unwrap();
+
+    let hot_wirer = HotWirerSoma {
+        control: organelle.control(),
+        listener: listener,
+        source: new_source,
+        violations: violations.clone(),
+    };
+    organelle.add_soma(hot_wirer);
+
+    core.run(organelle.run(handle)).unwrap();
+
+    // the extra source is reported instead of stopping the organelle
+    assert_eq!(
+        *violations.borrow(),
+        vec!["expected only one dendrite for Nothing".to_string()]
+    );
+
+    let events = recorder.events().unwrap();
+    let raised: Vec<_> = events
+        .iter()
+        .filter(|event| event.kind == ImpulseKind::Violation)
+        .collect();
+
+    assert_eq!(raised.len(), 1);
+    assert_eq!(raised[0].source, Some(new_source));
+    assert_eq!(raised[0].target, Some(listener));
+    assert_eq!(raised[0].variant, Some("Nothing".to_string()));
+    assert_eq!(
+        raised[0].message,
+        Some("expected only one dendrite for Nothing".to_string())
+    );
+}
@@ -0,0 +1,176 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate tokio_core;
+
+use std::error::Error as StdError;
+use std::fmt;
+
+use futures::prelude::*;
+use organelle::*;
+use tokio_core::reactor;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+enum Synapse {
+    Nothing,
+}
+
+impl organelle::Synapse for Synapse {
+    type Terminal = ();
+    type Dendrite = ();
+
+    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+        match self {
+            Synapse::Nothing => ((), ()),
+        }
+    }
+}
+
+struct IdleSoma;
+
+impl Soma for IdleSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+struct PanickySoma;
+
+impl Soma for PanickySoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_) => panic!("sensor exploded"),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[test]
+fn test_panic_is_isolated() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+    let panicky = organelle.add_soma(PanickySoma);
+
+    if let Err(e) = core.run(organelle.run(handle)) {
+        match e.kind() {
+            &ErrorKind::SomaPanicked(ref origin, ref message) => {
+                assert_eq!(origin.uuid, Some(panicky));
+                assert!(origin.name.ends_with("PanickySoma"));
+                assert_eq!(message, "sensor exploded");
+            },
+            _ => panic!("unexpected error: {:#?}", e),
+        }
+    } else {
+        panic!("PanickySoma should fail the organelle")
+    }
+}
+
+#[derive(Debug)]
+struct SensorError;
+
+impl fmt::Display for SensorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+impl StdError for SensorError {
+    fn description(&self) -> &str {
+        "sensor failed"
+    }
+}
+
+impl From<SensorError> for Error {
+    fn from(e: SensorError) -> Self {
+        Error::with_chain(e, "sensor failed")
+    }
+}
+
+struct FailingSoma;
+
+impl Soma for FailingSoma {
+    type Synapse = Synapse;
+    type Error = SensorError;
+
+    #[async(boxed)]
+    fn update(
+        self,
+        imp: Impulse<Self::Synapse>,
+    ) -> std::result::Result<Self, SensorError> {
+        match imp {
+            Impulse::Start(_) => Err(SensorError),
+            _ => Ok(self),
+        }
+    }
+}
+
+/// run a failing soma inside a nested organelle and check how its error is
+/// attributed
+fn check_attribution<U>(soma: U, name: &str)
+where
+    U: Soma<Synapse = Synapse> + 'static,
+{
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let mut planner = Organelle::new(IdleSoma, handle.clone());
+    planner.set_name("planner");
+
+    let sensor = planner.add_soma(soma);
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+    organelle.set_name("root");
+
+    organelle.add_soma(planner);
+
+    if let Err(e) = core.run(organelle.run(handle)) {
+        match e.kind() {
+            &ErrorKind::SomaError(ref origin) => {
+                assert_eq!(origin.uuid, Some(sensor));
+                assert_eq!(origin.path, vec!["root", "planner"]);
+                assert!(
+                    origin
+                        .to_string()
+                        .starts_with(&format!("root/planner/{}", name))
+                );
+            },
+            _ => panic!("unexpected error: {:#?}", e),
+        }
+
+        assert!(e.soma_error::<SensorError>().is_some());
+    } else {
+        panic!("{} should fail the organelle", name)
+    }
+}
+
+#[test]
+fn test_error_attribution() {
+    check_attribution(FailingSoma, "FailingSoma");
+}
+
+#[test]
+fn test_axon_error_attribution() {
+    // the axon keeps the error of the soma it wraps
+    check_attribution(
+        Axon::new(FailingSoma, vec![], vec![]),
+        "Axon<FailingSoma>",
+    );
+}
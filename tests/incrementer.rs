@@ -5,6 +5,8 @@ extern crate error_chain;
 
 extern crate futures_await as futures;
 extern crate organelle;
+#[macro_use]
+extern crate organelle_derive;
 extern crate tokio_core;
 extern crate tokio_timer;
 extern crate uuid;
@@ -18,121 +20,19 @@ use organelle::*;
 use tokio_core::reactor;
 use tokio_timer::Timer;
 
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Synapse)]
+#[synapse(parent = "IncrementerSynapse")]
 enum CounterSynapse {
+    #[synapse(channel = "mpsc", message = "()", buffer = 10)]
     Increment,
 }
 
-#[derive(Debug)]
-enum CounterTerminal {
-    Incrementer(unsync::mpsc::Sender<()>),
-}
-
-#[derive(Debug)]
-enum CounterDendrite {
-    Counter(unsync::mpsc::Receiver<()>),
-}
-
-impl Synapse for CounterSynapse {
-    type Terminal = CounterTerminal;
-    type Dendrite = CounterDendrite;
-
-    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
-        match self {
-            CounterSynapse::Increment => {
-                let (tx, rx) = unsync::mpsc::channel(10);
-
-                (
-                    CounterTerminal::Incrementer(tx),
-                    CounterDendrite::Counter(rx),
-                )
-            },
-        }
-    }
-}
-
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Synapse)]
 enum IncrementerSynapse {
+    #[synapse(channel = "mpsc", message = "()", buffer = 10)]
     Increment,
 }
 
-#[derive(Debug)]
-enum IncrementerTerminal {
-    Incrementer(unsync::mpsc::Sender<()>),
-}
-
-#[derive(Debug)]
-enum IncrementerDendrite {
-    Counter(unsync::mpsc::Receiver<()>),
-}
-
-impl Synapse for IncrementerSynapse {
-    type Terminal = IncrementerTerminal;
-    type Dendrite = IncrementerDendrite;
-
-    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
-        match self {
-            IncrementerSynapse::Increment => {
-                let (tx, rx) = unsync::mpsc::channel(10);
-
-                (
-                    IncrementerTerminal::Incrementer(tx),
-                    IncrementerDendrite::Counter(rx),
-                )
-            },
-        }
-    }
-}
-
-impl From<IncrementerSynapse> for CounterSynapse {
-    fn from(synapse: IncrementerSynapse) -> Self {
-        match synapse {
-            IncrementerSynapse::Increment => CounterSynapse::Increment,
-        }
-    }
-}
-impl From<CounterSynapse> for IncrementerSynapse {
-    fn from(synapse: CounterSynapse) -> Self {
-        match synapse {
-            CounterSynapse::Increment => IncrementerSynapse::Increment,
-        }
-    }
-}
-
-impl From<IncrementerTerminal> for CounterTerminal {
-    fn from(synapse: IncrementerTerminal) -> Self {
-        match synapse {
-            IncrementerTerminal::Incrementer(tx) => {
-                CounterTerminal::Incrementer(tx)
-            },
-        }
-    }
-}
-impl From<CounterTerminal> for IncrementerTerminal {
-    fn from(synapse: CounterTerminal) -> Self {
-        match synapse {
-            CounterTerminal::Incrementer(tx) => {
-                IncrementerTerminal::Incrementer(tx)
-            },
-        }
-    }
-}
-
-impl From<IncrementerDendrite> for CounterDendrite {
-    fn from(synapse: IncrementerDendrite) -> Self {
-        match synapse {
-            IncrementerDendrite::Counter(rx) => CounterDendrite::Counter(rx),
-        }
-    }
-}
-impl From<CounterDendrite> for IncrementerDendrite {
-    fn from(synapse: CounterDendrite) -> Self {
-        match synapse {
-            CounterDendrite::Counter(rx) => IncrementerDendrite::Counter(rx),
-        }
-    }
-}
-
 struct Incrementer {
     timer: Option<Timer>,
     tx: Option<unsync::mpsc::Sender<()>>,
@@ -156,7 +56,7 @@ impl Incrementer {
             await!(
                 timer
                     .sleep(time::Duration::from_millis(250))
-                    .map_err(|e| Error::with_chain(e, ErrorKind::SomaError))
+                    .map_err(|e| Error::with_chain(e, "timer failed"))
             )?;
 
             await!(
@@ -179,7 +79,7 @@ impl Soma for Incrementer {
             Impulse::AddTerminal(
                 _,
                 IncrementerSynapse::Increment,
-                IncrementerTerminal::Incrementer(tx),
+                IncrementerTerminal::Increment(tx),
             ) => {
                 println!("incrementer got output");
 
@@ -188,13 +88,11 @@ impl Soma for Incrementer {
                     tx: Some(tx),
                 })
             },
-            Impulse::Start(_, tx, handle) => {
+            Impulse::Start(ctx) => {
                 let sender = self.tx.as_ref().unwrap().clone();
                 let timer = mem::replace(&mut self.timer, None).unwrap();
 
-                handle.spawn(Self::increment(sender, timer).or_else(|e| {
-                    tx.send(Impulse::Error(e)).map(|_| ()).map_err(|_| ())
-                }));
+                ctx.spawn(Self::increment(sender, timer));
 
                 Ok(self)
             },
@@ -228,17 +126,17 @@ impl Soma for Counter {
             Impulse::AddDendrite(
                 _,
                 CounterSynapse::Increment,
-                CounterDendrite::Counter(rx),
+                CounterDendrite::Increment(rx),
             ) => {
                 println!("counter got input");
 
                 Ok(Self { rx: Some(rx) })
             },
-            Impulse::Start(_, tx, handle) => {
-                let stopper = tx.clone();
+            Impulse::Start(ctx) => {
+                let stopper = ctx.clone();
                 let rx = mem::replace(&mut self.rx, None).unwrap();
 
-                handle.spawn(
+                ctx.spawn(
                     async_block! {
                         let mut i = 0;
 
@@ -256,16 +154,10 @@ impl Soma for Counter {
                                 )))
                         )?;
 
-                        await!(stopper.send(Impulse::Stop)
-                            .map_err(|_| Error::from(
-                                "unable to stop gracefully"
-                            ))
-                        )?;
+                        stopper.stop();
 
                         Ok(())
-                    }.or_else(|e| {
-                        tx.send(Impulse::Error(e)).map(|_| ()).map_err(|_| ())
-                    }),
+                    },
                 );
 
                 Ok(self)
@@ -0,0 +1,284 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+#[macro_use]
+extern crate organelle_derive;
+extern crate tokio_core;
+extern crate uuid;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time;
+
+use futures::prelude::*;
+use futures::unsync::oneshot;
+use organelle::*;
+use tokio_core::reactor;
+use uuid::Uuid;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Synapse)]
+enum MailboxSynapse {
+    #[synapse(channel = "mpsc", message = "u32")]
+    Data,
+}
+
+/// accepts its synapses and does nothing with them
+struct IdleSoma;
+
+impl Soma for IdleSoma {
+    type Synapse = MailboxSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, MailboxSynapse::Data, _)
+            | Impulse::AddTerminal(_, MailboxSynapse::Data, _)
+            | Impulse::Start(_) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// waits for a gate to open before handling its first impulse, then logs the
+/// impulses it received
+struct GatedSoma {
+    gate: Option<oneshot::Receiver<()>>,
+    log: Rc<RefCell<Vec<&'static str>>>,
+}
+
+impl Soma for GatedSoma {
+    type Synapse = MailboxSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        if let Some(gate) = self.gate.take() {
+            await!(gate.map_err(|_| Error::from("gate was dropped")))?;
+        }
+
+        match imp {
+            Impulse::AddDendrite(_, MailboxSynapse::Data, _) => {
+                self.log.borrow_mut().push("dendrite");
+
+                Ok(self)
+            },
+            Impulse::Start(ctx) => {
+                self.log.borrow_mut().push("start");
+                ctx.stop();
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// fill a mailbox of capacity 1 with wiring while its soma is held up
+fn run_gated(overflow: Overflow) -> Vec<&'static str> {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let (gate_tx, gate_rx) = oneshot::channel();
+    let log = Rc::new(RefCell::new(vec![]));
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+    let idle = organelle.nucleus();
+    let gated = organelle.add_soma_with_mailbox(
+        GatedSoma {
+            gate: Some(gate_rx),
+            log: log.clone(),
+        },
+        Mirror,
+        Mailbox::new(1).overflow(overflow),
+    );
+
+    for _ in 0..5 {
+        organelle.connect(idle, gated, MailboxSynapse::Data).unwrap();
+    }
+
+    let timeout =
+        reactor::Timeout::new(time::Duration::from_millis(50), &handle)
+            .unwrap();
+    handle.spawn(timeout.then(move |_| gate_tx.send(())).map_err(|_| ()));
+
+    core.run(organelle.run(handle)).unwrap();
+
+    let log = log.borrow().clone();
+    log
+}
+
+#[test]
+fn test_mailbox_block() {
+    let log = run_gated(Overflow::Block);
+
+    // the senders waited for room instead of losing anything
+    assert_eq!(log.iter().filter(|&&imp| imp == "dendrite").count(), 5);
+    assert!(log.contains(&"start"));
+}
+
+#[test]
+fn test_mailbox_keeps_wiring() {
+    // wiring survives a full mailbox no matter which impulse would be dropped
+    for overflow in vec![Overflow::DropOldest, Overflow::DropNewest] {
+        let log = run_gated(overflow);
+
+        assert_eq!(log.iter().filter(|&&imp| imp == "dendrite").count(), 5);
+        assert_eq!(log.last(), Some(&"start"));
+    }
+}
+
+/// probes its organelle a few times while another soma is held up, then lets
+/// the soma go and stops the organelle
+struct ProberSoma {
+    gate: Option<oneshot::Sender<()>>,
+    mailboxes: Rc<RefCell<Vec<MailboxData>>>,
+}
+
+impl ProberSoma {
+    #[async]
+    fn probe_organelle(
+        ctx: SomaContext<MailboxSynapse>,
+        gate: oneshot::Sender<()>,
+        mailboxes: Rc<RefCell<Vec<MailboxData>>>,
+    ) -> Result<()> {
+        let settings =
+            probe::Settings::new().timeout(time::Duration::from_millis(50));
+
+        for _ in 0..3 {
+            let (tx, rx) = oneshot::channel();
+
+            await!(
+                ctx.sender()
+                    .send(Impulse::Probe(settings.clone(), tx))
+                    .map_err(|_| Error::from("unable to send probe"))
+            )?;
+
+            match await!(
+                rx.map_err(|_| Error::from("unable to receive probe"))
+            )? {
+                SomaData::Organelle {
+                    mailboxes: probed, ..
+                } => *mailboxes.borrow_mut() = probed,
+                data => bail!("unexpected probe data: {:#?}", data),
+            }
+        }
+
+        let _ = gate.send(());
+        ctx.stop();
+
+        Ok(())
+    }
+}
+
+impl Soma for ProberSoma {
+    type Synapse = MailboxSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(ctx) => {
+                let gate = self.gate
+                    .take()
+                    .ok_or_else(|| Error::from("started twice"))?;
+
+                ctx.spawn(Self::probe_organelle(
+                    ctx.clone(),
+                    gate,
+                    self.mailboxes.clone(),
+                ));
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// probe a soma with a mailbox of capacity 1 while it is held up
+///
+/// the first probe fills the mailbox, so the two after it overflow.
+fn run_probed(overflow: Overflow) -> Result<(Uuid, Vec<MailboxData>)> {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let (gate_tx, gate_rx) = oneshot::channel();
+    let mailboxes = Rc::new(RefCell::new(vec![]));
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+    let gated = organelle.add_soma_with_mailbox(
+        GatedSoma {
+            gate: Some(gate_rx),
+            log: Rc::new(RefCell::new(vec![])),
+        },
+        Mirror,
+        Mailbox::new(1).overflow(overflow),
+    );
+    organelle.add_soma(ProberSoma {
+        gate: Some(gate_tx),
+        mailboxes: mailboxes.clone(),
+    });
+
+    core.run(organelle.run(handle))?;
+
+    let mailboxes = mailboxes.borrow().clone();
+    Ok((gated, mailboxes))
+}
+
+/// the number of impulses a soma's mailbox dropped
+fn dropped(mailboxes: &[MailboxData], soma: Uuid) -> usize {
+    mailboxes
+        .iter()
+        .find(|mailbox| mailbox.soma == soma)
+        .map(|mailbox| mailbox.dropped)
+        .expect("soma should have a mailbox")
+}
+
+#[test]
+fn test_mailbox_drop_oldest() {
+    let (gated, mailboxes) = run_probed(Overflow::DropOldest).unwrap();
+
+    // each probe pushed out the one waiting before it
+    assert_eq!(dropped(&mailboxes, gated), 2);
+}
+
+#[test]
+fn test_mailbox_drop_newest() {
+    let (gated, mailboxes) = run_probed(Overflow::DropNewest).unwrap();
+
+    // the probes that did not fit were dropped
+    assert_eq!(dropped(&mailboxes, gated), 2);
+}
+
+#[test]
+#[should_panic(expected = "mailbox capacity must be at least 1")]
+fn test_mailbox_capacity() {
+    Mailbox::new(0);
+}
+
+#[test]
+fn test_mailbox_overflow() {
+    match run_probed(Overflow::Error) {
+        Err(e) => match e.kind() {
+            &ErrorKind::MailboxOverflow(ref origin, capacity) => {
+                assert!(origin.name.ends_with("GatedSoma"));
+                assert_eq!(capacity, 1);
+            },
+            _ => panic!("unexpected error: {:#?}", e),
+        },
+        Ok(_) => panic!("GatedSoma's mailbox should overflow"),
+    }
+
+    // wiring never overflows, even with the error policy
+    let log = run_gated(Overflow::Error);
+
+    assert_eq!(log.iter().filter(|&&imp| imp == "dendrite").count(), 5);
+    assert_eq!(log.last(), Some(&"start"));
+}
@@ -0,0 +1,133 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate tokio_core;
+
+use futures::prelude::*;
+use organelle::*;
+use tokio_core::reactor;
+
+/// synapses used by the host organelle
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+enum HostSynapse {
+    Ping,
+    Telemetry,
+}
+
+impl Synapse for HostSynapse {
+    type Terminal = ();
+    type Dendrite = ();
+
+    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+        ((), ())
+    }
+}
+
+/// synapses understood by the reusable ping soma
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+enum PingSynapse {
+    Ping,
+}
+
+impl Synapse for PingSynapse {
+    type Terminal = ();
+    type Dendrite = ();
+
+    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+        ((), ())
+    }
+}
+
+struct PingMapping;
+
+impl Mapping<HostSynapse, PingSynapse> for PingMapping {
+    fn synapse(&self, synapse: HostSynapse) -> Option<PingSynapse> {
+        match synapse {
+            HostSynapse::Ping => Some(PingSynapse::Ping),
+            _ => None,
+        }
+    }
+    fn terminal(&self, terminal: ()) -> Option<()> {
+        Some(terminal)
+    }
+    fn dendrite(&self, dendrite: ()) -> Option<()> {
+        Some(dendrite)
+    }
+}
+
+struct HostSoma;
+
+impl Soma for HostSoma {
+    type Synapse = HostSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(_, HostSynapse::Ping, _)
+            | Impulse::Start(_) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+struct PingSoma;
+
+impl Soma for PingSoma {
+    type Synapse = PingSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, PingSynapse::Ping, _) => Ok(self),
+            Impulse::Start(ctx) => {
+                ctx.stop();
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[test]
+fn test_partial_mapping() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let mut organelle = Organelle::new(HostSoma, handle.clone());
+
+    let host = organelle.nucleus();
+    let ping = organelle.add_soma_with(PingSoma, PingMapping);
+
+    organelle.connect(host, ping, HostSynapse::Ping).unwrap();
+
+    core.run(organelle.run(handle)).unwrap();
+}
+
+#[test]
+fn test_unmapped_synapse() {
+    let core = reactor::Core::new().unwrap();
+
+    let mut organelle = Organelle::new(HostSoma, core.handle());
+
+    let host = organelle.nucleus();
+    let ping = organelle.add_soma_with(PingSoma, PingMapping);
+
+    if let Err(e) = organelle.connect(host, ping, HostSynapse::Telemetry) {
+        match e.kind() {
+            &ErrorKind::UnmappedSynapse(ref origin, ref synapse) => {
+                assert_eq!(origin.uuid, Some(ping));
+                assert_eq!(synapse, "Telemetry");
+            },
+            _ => panic!("unexpected error: {:#?}", e),
+        }
+    } else {
+        panic!("PingSoma should not accept telemetry")
+    }
+}
@@ -0,0 +1,163 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+#[macro_use]
+extern crate organelle_derive;
+extern crate tokio_core;
+
+use std::mem;
+
+use futures::prelude::*;
+use futures::unsync;
+use organelle::*;
+use tokio_core::reactor;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Synapse)]
+enum PortSynapse {
+    #[synapse(channel = "mpsc", message = "()")]
+    Data,
+}
+
+struct IdleSoma;
+
+impl Soma for IdleSoma {
+    type Synapse = PortSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+struct SourceSoma {
+    tx: Option<unsync::mpsc::Sender<()>>,
+}
+
+impl Soma for SourceSoma {
+    type Synapse = PortSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddTerminal(
+                _,
+                PortSynapse::Data,
+                PortTerminal::Data(tx),
+            ) => {
+                self.tx = Some(tx);
+
+                Ok(self)
+            },
+            Impulse::Start(_) => {
+                let tx = mem::replace(&mut self.tx, None)
+                    .ok_or_else(|| Error::from("source was not connected"))?;
+
+                await!(
+                    tx.send(())
+                        .map_err(|_| Error::from("unable to send data"))
+                )?;
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// stops the organelle once it receives data from outside of its organelle
+struct SinkSoma {
+    rx: Option<unsync::mpsc::Receiver<()>>,
+}
+
+impl Soma for SinkSoma {
+    type Synapse = PortSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(mut self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(
+                _,
+                PortSynapse::Data,
+                PortDendrite::Data(rx),
+            ) => {
+                self.rx = Some(rx);
+
+                Ok(self)
+            },
+            Impulse::Start(ctx) => {
+                let rx = mem::replace(&mut self.rx, None)
+                    .ok_or_else(|| Error::from("sink was not connected"))?;
+
+                let stopper = ctx.clone();
+
+                ctx.spawn(
+                    rx.into_future()
+                        .map(move |_| stopper.stop())
+                        .map_err(|_| Error::from("unable to receive data")),
+                );
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[test]
+fn test_exposed_dendrite() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let mut inner = Organelle::new(IdleSoma, handle.clone());
+
+    let sink = inner.add_soma(SinkSoma { rx: None });
+    inner
+        .expose_dendrite(Constraint::One(PortSynapse::Data), sink)
+        .unwrap();
+
+    let mut organelle = Organelle::new(SourceSoma { tx: None }, handle.clone());
+
+    let source = organelle.nucleus();
+    let inner = organelle.add_soma(inner);
+
+    organelle.connect(source, inner, PortSynapse::Data).unwrap();
+
+    core.run(organelle.run(handle)).unwrap();
+}
+
+#[test]
+fn test_missing_port() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let mut inner = Organelle::new(IdleSoma, handle.clone());
+
+    let sink = inner.add_soma(SinkSoma { rx: None });
+    inner
+        .expose_dendrite(Constraint::One(PortSynapse::Data), sink)
+        .unwrap();
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+    organelle.add_soma(inner);
+
+    if let Err(e) = core.run(organelle.run(handle)) {
+        match e.kind() {
+            &ErrorKind::MissingSynapse(_, ref msg) => {
+                println!("got expected error: {}", *msg)
+            },
+            _ => panic!("unexpected error: {:#?}", e),
+        }
+    } else {
+        panic!("the inner organelle should require its exposed dendrite")
+    }
+}
@@ -0,0 +1,1012 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+#[macro_use]
+extern crate organelle_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate tokio_core;
+extern crate uuid;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time;
+
+use futures::future;
+use futures::prelude::*;
+use futures::unsync::oneshot;
+use organelle::*;
+use tokio_core::reactor;
+use uuid::Uuid;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq, Synapse)]
+enum ProbeSynapse {
+    #[synapse(channel = "mpsc", message = "u32")]
+    Data,
+}
+
+/// ignores everything but the start impulse, leaving probes to the runtime
+struct PlainSoma;
+
+impl Soma for PlainSoma {
+    type Synapse = ProbeSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, ProbeSynapse::Data, _)
+            | Impulse::AddTerminal(_, ProbeSynapse::Data, _)
+            | Impulse::Start(_) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// exposes a snapshot of its state to probes
+struct StatefulSoma {
+    count: u32,
+}
+
+impl Soma for StatefulSoma {
+    type Synapse = ProbeSynapse;
+    type Error = Error;
+
+    fn inspect(&self) -> Option<serde_json::Value> {
+        Some(json!({ "count": self.count }))
+    }
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_) => Ok(Self {
+                count: self.count + 1,
+            }),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// never finishes starting, so it cannot respond to probes
+struct BlockedSoma;
+
+impl Soma for BlockedSoma {
+    type Synapse = ProbeSynapse;
+    type Error = Error;
+
+    fn update(
+        self,
+        imp: Impulse<Self::Synapse>,
+    ) -> Box<Future<Item = Self, Error = Self::Error>> {
+        match imp {
+            Impulse::Start(_) => Box::new(future::empty()),
+            _ => Box::new(future::err(Error::from("unexpected impulse"))),
+        }
+    }
+}
+
+/// probes its organelle once started, then stops it
+struct ProberSoma {
+    data: Rc<RefCell<Option<SomaData>>>,
+    settings: probe::Settings,
+}
+
+impl ProberSoma {
+    #[async]
+    fn probe_organelle(
+        ctx: SomaContext<ProbeSynapse>,
+        data: Rc<RefCell<Option<SomaData>>>,
+        settings: probe::Settings,
+    ) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+
+        await!(
+            ctx.sender()
+                .send(Impulse::Probe(settings, tx))
+                .map_err(|_| Error::from("unable to send probe"))
+        )?;
+
+        let result =
+            await!(rx.map_err(|_| Error::from("unable to receive probe")))?;
+
+        *data.borrow_mut() = Some(result);
+        ctx.stop();
+
+        Ok(())
+    }
+}
+
+impl Soma for ProberSoma {
+    type Synapse = ProbeSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(ctx) => {
+                let data = self.data.clone();
+                let settings = self.settings.clone();
+
+                ctx.spawn(Self::probe_organelle(ctx.clone(), data, settings));
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// adds an axon and an organelle once started, then probes before either
+/// of them has started
+struct GrowerSoma {
+    data: Rc<RefCell<Option<SomaData>>>,
+    grown: Rc<RefCell<Vec<Uuid>>>,
+}
+
+impl Soma for GrowerSoma {
+    type Synapse = ProbeSynapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(ctx) => {
+                let control = ctx
+                    .control::<ProbeSynapse>()
+                    .ok_or_else(|| Error::from("grower has no control"))?;
+
+                let axon =
+                    control.add_soma(Axon::new(PlainSoma, vec![], vec![]));
+                let nested =
+                    control.add_soma(Organelle::new(PlainSoma, ctx.handle()));
+
+                self.grown.borrow_mut().extend(vec![axon, nested]);
+
+                ctx.spawn(ProberSoma::probe_organelle(
+                    ctx.clone(),
+                    self.data.clone(),
+                    probe::Settings::new()
+                        .timeout(time::Duration::from_millis(100)),
+                ));
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+fn find(somas: &[SomaData], uuid: Uuid) -> &SomaData {
+    somas
+        .iter()
+        .find(|data| match *data {
+            &SomaData::Soma { uuid: soma, .. } => soma == uuid,
+            _ => false,
+        })
+        .expect("soma should be probed")
+}
+
+#[test]
+fn test_probe_plain_somas() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let data = Rc::new(RefCell::new(None));
+
+    let mut organelle = Organelle::new(PlainSoma, handle.clone());
+
+    let source = organelle.add_soma(PlainSoma);
+    let sink = organelle.add_soma(PlainSoma);
+    organelle.add_soma(ProberSoma {
+        data: data.clone(),
+        settings: probe::Settings::new(),
+    });
+
+    organelle.connect(source, sink, ProbeSynapse::Data).unwrap();
+
+    core.run(organelle.run(handle)).unwrap();
+
+    let somas = match data.borrow_mut().take() {
+        Some(SomaData::Organelle { nucleus, somas, .. }) => {
+            match *nucleus {
+                SomaData::Soma { ref name, .. } => {
+                    assert!(name.ends_with("PlainSoma"))
+                },
+                ref data => panic!("unexpected nucleus data: {:#?}", data),
+            }
+
+            assert_eq!(somas.len(), 3);
+
+            somas
+        },
+        data => panic!("unexpected probe data: {:#?}", data),
+    };
+
+    let connection = |soma| probe::ConnectionData {
+        variant: "Data".to_string(),
+        soma: soma,
+    };
+
+    match find(&somas, source) {
+        &SomaData::Soma {
+            ref synapse,
+            ref dendrites,
+            ref terminals,
+            ..
+        } => {
+            assert_eq!(synapse.variants.len(), 1);
+            assert_eq!(synapse.variants[0].variant, "Data");
+            assert_eq!(synapse.variants[0].channel, "mpsc");
+            assert_eq!(synapse.variants[0].message, "u32");
+
+            assert!(dendrites.is_empty());
+            assert_eq!(*terminals, vec![connection(sink)]);
+        },
+        _ => unreachable!(),
+    }
+
+    match find(&somas, sink) {
+        &SomaData::Soma {
+            ref dendrites,
+            ref terminals,
+            ..
+        } => {
+            assert_eq!(*dendrites, vec![connection(source)]);
+            assert!(terminals.is_empty());
+        },
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_inspect_state() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let data = Rc::new(RefCell::new(None));
+
+    let mut organelle = Organelle::new(PlainSoma, handle.clone());
+
+    let stateful = organelle.add_soma(StatefulSoma { count: 0 });
+    let axon = organelle.add_soma(Axon::new(
+        StatefulSoma { count: 41 },
+        vec![],
+        vec![],
+    ));
+    organelle.add_soma(ProberSoma {
+        data: data.clone(),
+        settings: probe::Settings::new(),
+    });
+
+    core.run(organelle.run(handle)).unwrap();
+
+    let somas = match data.borrow_mut().take() {
+        Some(SomaData::Organelle { nucleus, somas, .. }) => {
+            match *nucleus {
+                SomaData::Soma { ref state, .. } => assert_eq!(*state, None),
+                ref data => panic!("unexpected nucleus data: {:#?}", data),
+            }
+
+            somas
+        },
+        data => panic!("unexpected probe data: {:#?}", data),
+    };
+
+    match find(&somas, stateful) {
+        &SomaData::Soma { ref state, .. } => {
+            assert_eq!(*state, Some(json!({ "count": 1 })))
+        },
+        _ => unreachable!(),
+    }
+
+    let axon_state = somas.iter().find(|data| match *data {
+        &SomaData::Axon { uuid, .. } => uuid == axon,
+        _ => false,
+    });
+
+    match axon_state {
+        Some(&SomaData::Axon { ref state, .. }) => {
+            assert_eq!(*state, Some(json!({ "count": 42 })))
+        },
+        data => panic!("unexpected axon data: {:#?}", data),
+    }
+}
+
+#[test]
+fn test_unresponsive_soma() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let data = Rc::new(RefCell::new(None));
+
+    let mut organelle = Organelle::new(PlainSoma, handle.clone());
+    organelle.set_stop_timeout(time::Duration::from_millis(100));
+
+    let blocked = organelle.add_soma(BlockedSoma);
+    organelle.add_soma(ProberSoma {
+        data: data.clone(),
+        settings: probe::Settings::new()
+            .timeout(time::Duration::from_millis(100)),
+    });
+
+    // the blocked soma is abandoned once the stop timeout expires
+    let e = core.run(organelle.run(handle)).unwrap_err();
+
+    match e.kind() {
+        &ErrorKind::StopTimeout(_, timeout) => {
+            assert_eq!(timeout, time::Duration::from_millis(100))
+        },
+        _ => panic!("unexpected error: {:#?}", e),
+    }
+
+    match data.borrow_mut().take() {
+        Some(SomaData::Organelle { somas, .. }) => {
+            let unresponsive = somas.iter().any(|data| match *data {
+                SomaData::Unresponsive { uuid, .. } => uuid == blocked,
+                _ => false,
+            });
+
+            assert!(unresponsive, "blocked soma should be unresponsive");
+        },
+        data => panic!("unexpected probe data: {:#?}", data),
+    }
+}
+
+#[test]
+fn test_probe_before_start() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let data = Rc::new(RefCell::new(None));
+    let grown = Rc::new(RefCell::new(vec![]));
+
+    let mut organelle = Organelle::new(PlainSoma, handle.clone());
+    organelle.add_soma(GrowerSoma {
+        data: data.clone(),
+        grown: grown.clone(),
+    });
+
+    core.run(organelle.run(handle)).unwrap();
+
+    let somas = match data.borrow_mut().take() {
+        Some(SomaData::Organelle { somas, .. }) => somas,
+        data => panic!("unexpected probe data: {:#?}", data),
+    };
+
+    // somas that have not started yet still report the uuid they run under
+    let axon = grown.borrow()[0];
+    let nested = grown.borrow()[1];
+
+    assert!(somas.iter().any(|data| match *data {
+        SomaData::Axon { uuid, .. } => uuid == axon,
+        _ => false,
+    }));
+    assert!(somas.iter().any(|data| match *data {
+        SomaData::Organelle { uuid, .. } => uuid == nested,
+        _ => false,
+    }));
+}
+
+#[test]
+fn test_nested_unresponsive_soma() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let data = Rc::new(RefCell::new(None));
+
+    let mut nested = Organelle::new(PlainSoma, handle.clone());
+    nested.set_stop_timeout(time::Duration::from_millis(100));
+    let blocked = nested.add_soma(BlockedSoma);
+
+    let mut organelle = Organelle::new(PlainSoma, handle.clone());
+    organelle.set_stop_timeout(time::Duration::from_millis(200));
+
+    let inner = organelle.add_soma(nested);
+    organelle.add_soma(ProberSoma {
+        data: data.clone(),
+        settings: probe::Settings::new()
+            .timeout(time::Duration::from_millis(200)),
+    });
+
+    // the nested organelle reports the soma it abandoned to the outer one
+    let e = core.run(organelle.run(handle)).unwrap_err();
+
+    match e.kind() {
+        &ErrorKind::StopTimeout(ref origin, _) => {
+            assert_eq!(origin.uuid, Some(inner))
+        },
+        _ => panic!("unexpected error: {:#?}", e),
+    }
+
+    // the nested organelle gives up on its blocked soma before the outer
+    // organelle gives up on it, so the blocked soma is the one reported
+    let somas = match data.borrow_mut().take() {
+        Some(SomaData::Organelle { somas, .. }) => somas,
+        data => panic!("unexpected probe data: {:#?}", data),
+    };
+
+    let nested = somas
+        .iter()
+        .find(|data| match *data {
+            &SomaData::Organelle { uuid, .. } => uuid == inner,
+            _ => false,
+        })
+        .expect("nested organelle should respond");
+
+    match *nested {
+        SomaData::Organelle { ref somas, .. } => {
+            assert!(somas.iter().any(|data| match *data {
+                SomaData::Unresponsive { uuid, .. } => uuid == blocked,
+                _ => false,
+            }));
+        },
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn test_probe_mailboxes() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let data = Rc::new(RefCell::new(None));
+
+    let mut organelle = Organelle::new(PlainSoma, handle.clone());
+
+    let nucleus = organelle.nucleus();
+    let sensor = organelle.add_soma_with_mailbox(
+        PlainSoma,
+        Mirror,
+        Mailbox::new(64).overflow(Overflow::DropOldest),
+    );
+    let supervised = organelle.add_supervised_soma_with_mailbox(
+        || PlainSoma,
+        Mirror,
+        Mailbox::new(3).overflow(Overflow::DropNewest),
+    );
+    organelle.add_soma(ProberSoma {
+        data: data.clone(),
+        settings: probe::Settings::new(),
+    });
+
+    core.run(organelle.run(handle)).unwrap();
+
+    let mailboxes = match data.borrow_mut().take() {
+        Some(SomaData::Organelle { mailboxes, .. }) => mailboxes,
+        data => panic!("unexpected probe data: {:#?}", data),
+    };
+
+    let mailbox = |uuid| {
+        mailboxes
+            .iter()
+            .find(|mailbox| mailbox.soma == uuid)
+            .cloned()
+            .expect("mailbox should be probed")
+    };
+
+    assert_eq!(
+        mailbox(sensor),
+        MailboxData {
+            soma: sensor,
+            capacity: 64,
+            overflow: Overflow::DropOldest,
+            dropped: 0,
+        }
+    );
+    assert_eq!(
+        mailbox(supervised),
+        MailboxData {
+            soma: supervised,
+            capacity: 3,
+            overflow: Overflow::DropNewest,
+            dropped: 0,
+        }
+    );
+    assert_eq!(
+        mailbox(nucleus),
+        MailboxData {
+            soma: nucleus,
+            capacity: DEFAULT_MAILBOX_CAPACITY,
+            overflow: Overflow::Block,
+            dropped: 0,
+        }
+    );
+}
+
+#[test]
+fn test_probe_metrics() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let data = Rc::new(RefCell::new(None));
+
+    let mut organelle = Organelle::new(PlainSoma, handle.clone());
+
+    let source = organelle.add_soma(PlainSoma);
+    let sink = organelle.add_soma(PlainSoma);
+    organelle.add_soma(ProberSoma {
+        data: data.clone(),
+        settings: probe::Settings::new(),
+    });
+
+    organelle.connect(source, sink, ProbeSynapse::Data).unwrap();
+
+    core.run(organelle.run(handle.clone())).unwrap();
+
+    let metrics = match data.borrow_mut().take() {
+        Some(SomaData::Organelle { metrics, .. }) => metrics,
+        data => panic!("unexpected probe data: {:#?}", data),
+    };
+
+    assert_eq!(metrics.len(), 4);
+
+    let sink = metrics
+        .iter()
+        .find(|metrics| metrics.soma == sink)
+        .expect("sink should have metrics");
+
+    // the dendrite, the start impulse and the probe, which skips `update`
+    assert_eq!(sink.impulses, 3);
+    assert_eq!(sink.updates, 2);
+    assert!(sink.p50_update_us <= sink.p99_update_us);
+    assert!(sink.p99_update_us <= sink.update_time_us);
+    assert_eq!(sink.mailbox_depth, 0);
+    assert_eq!(sink.errors, 0);
+    assert!(sink.name.ends_with("PlainSoma"));
+
+    let mut organelle = Organelle::new(PlainSoma, handle.clone());
+
+    organelle.add_soma(ProberSoma {
+        data: data.clone(),
+        settings: probe::Settings::new().include_metrics(false),
+    });
+
+    core.run(organelle.run(handle)).unwrap();
+
+    match data.borrow_mut().take() {
+        Some(SomaData::Organelle { metrics, .. }) => {
+            assert!(metrics.is_empty())
+        },
+        data => panic!("unexpected probe data: {:#?}", data),
+    }
+}
+
+#[test]
+fn test_probe_filters() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let data = Rc::new(RefCell::new(None));
+
+    let mut organelle = Organelle::new(PlainSoma, handle.clone());
+
+    let mut nested = Organelle::new(PlainSoma, handle.clone());
+    nested.add_soma(StatefulSoma { count: 0 });
+
+    organelle.add_soma(nested);
+    organelle.add_soma(PlainSoma);
+    let stateful = organelle.add_soma(StatefulSoma { count: 0 });
+    organelle.add_soma(ProberSoma {
+        data: data.clone(),
+        settings: probe::Settings::new()
+            .filter_name("StatefulSoma")
+            .include_state(false)
+            .max_depth(0),
+    });
+
+    core.run(organelle.run(handle)).unwrap();
+
+    let somas = match data.borrow_mut().take() {
+        Some(SomaData::Organelle { somas, .. }) => somas,
+        data => panic!("unexpected probe data: {:#?}", data),
+    };
+
+    assert_eq!(somas.len(), 2);
+
+    match find(&somas, stateful) {
+        &SomaData::Soma { ref state, .. } => assert_eq!(*state, None),
+        _ => unreachable!(),
+    }
+
+    for soma in &somas {
+        if let &SomaData::Organelle { ref somas, .. } = soma {
+            assert!(somas.is_empty(), "nested organelle should be cut off");
+        }
+    }
+}
+
+#[test]
+fn test_document_round_trip() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let data = Rc::new(RefCell::new(None));
+
+    let mut organelle = Organelle::new(PlainSoma, handle.clone());
+
+    organelle.add_soma(StatefulSoma { count: 0 });
+    organelle.add_soma(Axon::new(PlainSoma, vec![], vec![]));
+    organelle.add_soma(ProberSoma {
+        data: data.clone(),
+        settings: probe::Settings::new(),
+    });
+
+    core.run(organelle.run(handle)).unwrap();
+
+    let document = probe::Document::new(data.borrow_mut().take().unwrap());
+    let json = document.to_json().unwrap();
+
+    assert_eq!(probe::Document::from_json(&json).unwrap(), document);
+
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["version"] = json!(probe::FORMAT_VERSION + 1);
+
+    match probe::Document::from_json(&value.to_string()) {
+        Err(e) => match e.kind() {
+            &ErrorKind::ProbeVersion(version) => {
+                assert_eq!(version, probe::FORMAT_VERSION + 1)
+            },
+            _ => panic!("unexpected error: {:#?}", e),
+        },
+        Ok(_) => panic!("newer documents should be rejected"),
+    }
+
+    let schema = probe::schema();
+    assert_eq!(
+        schema["properties"]["version"]["const"],
+        json!(probe::FORMAT_VERSION)
+    );
+}
+
+#[test]
+fn test_document_schema() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let data = Rc::new(RefCell::new(None));
+
+    let mut nested = Organelle::new(PlainSoma, handle.clone());
+    nested.add_soma(StatefulSoma { count: 0 });
+
+    let mut organelle =
+        Organelle::new(StatefulSoma { count: 0 }, handle.clone());
+    organelle.set_stop_timeout(time::Duration::from_millis(100));
+
+    let axon = organelle.add_soma(Axon::new(
+        PlainSoma,
+        vec![Constraint::Variadic(ProbeSynapse::Data)],
+        vec![Constraint::Optional(ProbeSynapse::Data)],
+    ));
+    let plain = organelle.add_soma(PlainSoma);
+    organelle.add_soma(nested);
+    organelle.add_soma(BlockedSoma);
+    organelle.add_soma(ProberSoma {
+        data: data.clone(),
+        settings: probe::Settings::new()
+            .timeout(time::Duration::from_millis(100)),
+    });
+
+    organelle.connect(axon, plain, ProbeSynapse::Data).unwrap();
+
+    // the blocked soma is abandoned once the stop timeout expires
+    let e = core.run(organelle.run(handle)).unwrap_err();
+
+    match e.kind() {
+        &ErrorKind::StopTimeout(_, _) => (),
+        _ => panic!("unexpected error: {:#?}", e),
+    }
+
+    // every kind of soma data is covered, along with state and metrics
+    let document = probe::Document::new(data.borrow_mut().take().unwrap());
+    let json = document.to_json().unwrap();
+
+    for kind in &["organelle", "axon", "soma", "unresponsive", "optional"] {
+        assert!(json.contains(&format!("\"type\":\"{}\"", kind)));
+    }
+    assert!(json.contains("\"count\":1"));
+    assert!(json.contains("\"p99_update_us\""));
+
+    // the schema is generated from the same types that write the document
+    let schema = probe::schema();
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    let organelle = schema["definitions"]["SomaData"]["oneOf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|option| {
+            option["properties"]["type"]["const"] == json!("organelle")
+        })
+        .unwrap();
+
+    assert_eq!(organelle["additionalProperties"], json!(false));
+    for key in value["root"].as_object().unwrap().keys() {
+        assert!(organelle["properties"].get(key).is_some());
+    }
+
+    // fields the schema does not allow are rejected when loading as well
+    value["root"]["nucleus"]["surprise"] = json!(true);
+
+    assert!(probe::Document::from_json(&value.to_string()).is_err());
+}
+
+fn plain_data(
+    uuid: Uuid,
+    dendrites: Vec<(&'static str, Uuid)>,
+    terminals: Vec<(&'static str, Uuid)>,
+) -> SomaData {
+    let connections = |peers: Vec<(&'static str, Uuid)>| {
+        peers
+            .into_iter()
+            .map(|(variant, soma)| probe::ConnectionData {
+                variant: variant.to_string(),
+                soma: soma,
+            })
+            .collect()
+    };
+
+    SomaData::Soma {
+        synapse: probe::SynapseData::new::<ProbeSynapse>(vec![]),
+        dendrites: connections(dendrites),
+        terminals: connections(terminals),
+        uuid: uuid,
+        name: "PlainSoma".to_string(),
+        state: None,
+    }
+}
+
+fn axon_data(
+    uuid: Uuid,
+    dendrites: Vec<probe::ConstraintData>,
+    violations: Vec<&str>,
+) -> SomaData {
+    SomaData::Axon {
+        terminals: vec![],
+        dendrites: dendrites,
+        violations: violations.into_iter().map(|v| v.to_string()).collect(),
+        uuid: uuid,
+        name: "Axon".to_string(),
+        state: None,
+    }
+}
+
+fn organelle_data(
+    uuid: Uuid,
+    name: &str,
+    nucleus: SomaData,
+    somas: Vec<SomaData>,
+    dendrite_ports: Vec<(&'static str, Uuid)>,
+) -> SomaData {
+    SomaData::Organelle {
+        nucleus: Box::new(nucleus),
+        somas: somas,
+        mailboxes: vec![],
+        metrics: vec![],
+        dendrite_ports: dendrite_ports
+            .into_iter()
+            .map(|(variant, soma)| probe::PortData {
+                variant: variant.to_string(),
+                soma: soma,
+            })
+            .collect(),
+        terminal_ports: vec![],
+        uuid: uuid,
+        name: name.to_string(),
+        state: None,
+    }
+}
+
+#[test]
+fn test_diff() {
+    let organelle = Uuid::new_v4();
+    let nucleus = Uuid::new_v4();
+    let source = Uuid::new_v4();
+    let old_sink = Uuid::new_v4();
+    let new_sink = Uuid::new_v4();
+    let axon = Uuid::new_v4();
+    let new_axon = Uuid::new_v4();
+
+    let before = organelle_data(
+        organelle,
+        "Organelle",
+        plain_data(nucleus, vec![], vec![]),
+        vec![
+            plain_data(source, vec![], vec![("Data", old_sink)]),
+            plain_data(old_sink, vec![("Data", source)], vec![]),
+            axon_data(
+                axon,
+                vec![],
+                vec!["expected dendrite synapse for Data"],
+            ),
+        ],
+        vec![],
+    );
+    let after = organelle_data(
+        organelle,
+        "Organelle",
+        plain_data(nucleus, vec![], vec![]),
+        vec![
+            plain_data(source, vec![], vec![("Data", new_sink)]),
+            plain_data(new_sink, vec![("Data", source)], vec![]),
+            axon_data(
+                axon,
+                vec![probe::ConstraintData::Optional {
+                    variant: "Data".to_string(),
+                    soma: None,
+                }],
+                vec![],
+            ),
+            axon_data(
+                new_axon,
+                vec![probe::ConstraintData::Variadic {
+                    variant: "Data".to_string(),
+                    somas: vec![],
+                }],
+                vec!["no dendrite connected for Data"],
+            ),
+        ],
+        vec![],
+    );
+
+    assert!(probe::diff(&before, &before).is_empty());
+    assert!(probe::diff(&after, &after).is_empty());
+
+    let diff = probe::diff(&before, &after);
+
+    assert_eq!(
+        diff.added,
+        vec![
+            probe::SomaChange {
+                uuid: new_sink,
+                name: "PlainSoma".to_string(),
+            },
+            probe::SomaChange {
+                uuid: new_axon,
+                name: "Axon".to_string(),
+            },
+        ]
+    );
+    assert_eq!(
+        diff.removed,
+        vec![probe::SomaChange {
+            uuid: old_sink,
+            name: "PlainSoma".to_string(),
+        }]
+    );
+    assert_eq!(
+        diff.constraints,
+        vec![
+            probe::ConstraintChange {
+                uuid: axon,
+                name: "Axon".to_string(),
+                added: vec!["dendrite Data (optional)".to_string()],
+                removed: vec![],
+                violated: vec![],
+                resolved: vec![
+                    "expected dendrite synapse for Data".to_string(),
+                ],
+            },
+            // new axons report every constraint they have
+            probe::ConstraintChange {
+                uuid: new_axon,
+                name: "Axon".to_string(),
+                added: vec!["dendrite Data (any number)".to_string()],
+                removed: vec![],
+                violated: vec!["no dendrite connected for Data".to_string()],
+                resolved: vec![],
+            },
+        ]
+    );
+    assert_eq!(
+        diff.connected,
+        vec![probe::Edge {
+            terminal: source,
+            dendrite: new_sink,
+            variant: "Data".to_string(),
+        }]
+    );
+    assert_eq!(
+        diff.disconnected,
+        vec![probe::Edge {
+            terminal: source,
+            dendrite: old_sink,
+            variant: "Data".to_string(),
+        }]
+    );
+}
+
+#[test]
+fn test_graph() {
+    let root = Uuid::new_v4();
+    let nucleus = Uuid::new_v4();
+    let source = Uuid::new_v4();
+    let inner = Uuid::new_v4();
+    let inner_nucleus = Uuid::new_v4();
+    let inner_axon = Uuid::new_v4();
+    let sink = Uuid::new_v4();
+
+    // both ends report each synapse, but only the inner somas know that
+    // they handle the synapses given to their organelle
+    let data = organelle_data(
+        root,
+        "Outer",
+        plain_data(nucleus, vec![], vec![]),
+        vec![
+            plain_data(source, vec![], vec![("Data", inner)]),
+            organelle_data(
+                inner,
+                "Inner",
+                plain_data(inner_nucleus, vec![], vec![("Data", sink)]),
+                vec![axon_data(
+                    inner_axon,
+                    vec![probe::ConstraintData::One {
+                        variant: "Data".to_string(),
+                        soma: source,
+                    }],
+                    vec![],
+                )],
+                vec![("Data", inner_axon)],
+            ),
+            plain_data(sink, vec![("Data", inner)], vec![]),
+        ],
+        vec![],
+    );
+
+    let graph = probe::Graph::new(&data);
+
+    assert_eq!(graph.root().uuid, root);
+    assert_eq!(graph.nodes().len(), 7);
+
+    let mut edges = vec![
+        probe::Edge {
+            terminal: source,
+            dendrite: inner_axon,
+            variant: "Data".to_string(),
+        },
+        probe::Edge {
+            terminal: inner_nucleus,
+            dendrite: sink,
+            variant: "Data".to_string(),
+        },
+    ];
+    edges.sort();
+
+    assert_eq!(graph.edges(), &edges[..]);
+
+    assert_eq!(graph.node(inner).unwrap().kind, probe::NodeKind::Organelle);
+    assert_eq!(graph.parent(inner_axon).unwrap().uuid, inner);
+    assert_eq!(
+        graph
+            .children(inner)
+            .into_iter()
+            .map(|node| node.uuid)
+            .collect::<Vec<_>>(),
+        vec![inner_nucleus, inner_axon]
+    );
+
+    assert_eq!(graph.neighbours(inner_axon)[0].uuid, source);
+    assert_eq!(graph.neighbours_by_name("Axon")[0].uuid, source);
+    assert_eq!(graph.innermost_nucleus(root), nucleus);
+    assert_eq!(graph.innermost_nucleus(inner), inner_nucleus);
+
+    // exposed dendrites are handled by the inner soma instead of the nucleus
+    assert_eq!(graph.dendrite_handler(inner, "Data"), inner_axon);
+    assert_eq!(graph.dendrite_handler(inner, "Other"), inner_nucleus);
+    assert_eq!(graph.dendrite_handler(root, "Data"), nucleus);
+    assert_eq!(graph.terminal_handler(inner, "Data"), inner_nucleus);
+
+    assert_eq!(
+        graph
+            .path(inner_axon)
+            .into_iter()
+            .map(|node| node.uuid)
+            .collect::<Vec<_>>(),
+        vec![root, inner, inner_axon]
+    );
+    assert_eq!(graph.resolve(&["Inner", "Axon"]).unwrap().uuid, inner_axon);
+    assert!(graph.resolve(&["Axon"]).is_none());
+}
@@ -0,0 +1,288 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate tokio_core;
+extern crate uuid;
+
+use std::io::{self, Write};
+
+use futures::prelude::*;
+use organelle::*;
+use tokio_core::reactor;
+use uuid::Uuid;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+enum Synapse {
+    Nothing,
+}
+
+impl organelle::Synapse for Synapse {
+    type Terminal = ();
+    type Dendrite = ();
+
+    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+        match self {
+            Synapse::Nothing => ((), ()),
+        }
+    }
+}
+
+struct StopperSoma;
+
+impl Soma for StopperSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(ctx) => {
+                ctx.stop();
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+struct IdleSoma;
+
+impl Soma for IdleSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::AddDendrite(_, Synapse::Nothing, _)
+            | Impulse::AddTerminal(_, Synapse::Nothing, _)
+            | Impulse::Start(_) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+struct FailingSoma;
+
+impl Soma for FailingSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, _: Impulse<Self::Synapse>) -> Result<Self> {
+        bail!("failed to start")
+    }
+}
+
+/// reports an error through its context instead of failing
+struct ReportingSoma;
+
+impl Soma for ReportingSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(ctx) => {
+                ctx.report_error("failed in the background");
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// a writer that can never be written to
+struct BrokenWriter;
+
+impl Write for BrokenWriter {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::Other, "disk full"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn errors(events: &[ImpulseEvent]) -> Vec<&ImpulseEvent> {
+    events
+        .iter()
+        .filter(|event| event.kind == ImpulseKind::Error)
+        .collect()
+}
+
+fn find(
+    events: &[ImpulseEvent],
+    kind: ImpulseKind,
+    target: Uuid,
+) -> Option<(usize, &ImpulseEvent)> {
+    events
+        .iter()
+        .enumerate()
+        .find(|&(_, event)| event.kind == kind && event.target == Some(target))
+}
+
+#[test]
+fn test_record_impulses() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let recorder = Recorder::memory();
+
+    let nested = Organelle::new(IdleSoma, handle.clone());
+    let inner = nested.nucleus();
+
+    let mut organelle = Organelle::new(StopperSoma, handle.clone());
+    organelle.set_recorder(recorder.clone());
+
+    let stopper = organelle.nucleus();
+    let source = organelle.add_soma(IdleSoma);
+    let sink = organelle.add_soma(nested);
+
+    organelle.connect(source, sink, Synapse::Nothing).unwrap();
+
+    core.run(organelle.run(handle)).unwrap();
+
+    // every line of the buffer is a separate event
+    let events = recorder.events().unwrap();
+    assert_eq!(recorder.buffer().unwrap().lines().count(), events.len());
+
+    let (terminal, event) =
+        find(&events, ImpulseKind::AddTerminal, source).unwrap();
+    assert_eq!(event.source, Some(sink));
+    assert_eq!(event.variant, Some("Nothing".to_string()));
+    assert!(event.source_name.as_ref().unwrap().contains("Organelle<"));
+    assert!(event.target_name.as_ref().unwrap().ends_with("IdleSoma"));
+
+    let (_, event) = find(&events, ImpulseKind::AddDendrite, sink).unwrap();
+    assert_eq!(event.source, Some(source));
+    assert_eq!(event.variant, Some("Nothing".to_string()));
+
+    // the synapse was delivered before the soma was started
+    let (start, event) = find(&events, ImpulseKind::Start, source).unwrap();
+    assert!(terminal < start);
+    assert!(events[terminal].timestamp_us <= event.timestamp_us);
+    assert!(event.source.is_some());
+    assert_eq!(event.variant, None);
+
+    // the nested organelle records with the same recorder once started
+    let (_, event) = find(&events, ImpulseKind::Start, inner).unwrap();
+    assert_eq!(event.source, Some(sink));
+
+    for uuid in &[stopper, source, sink, inner] {
+        assert!(find(&events, ImpulseKind::Start, *uuid).is_some());
+        assert!(find(&events, ImpulseKind::Stop, *uuid).is_some());
+    }
+}
+
+#[test]
+fn test_record_errors() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let recorder = Recorder::memory();
+
+    let mut organelle = Organelle::new(FailingSoma, handle.clone());
+    organelle.set_recorder(recorder.clone());
+
+    let failing = organelle.nucleus();
+
+    assert!(core.run(organelle.run(handle)).is_err());
+
+    let events = recorder.events().unwrap();
+
+    let error = events
+        .iter()
+        .find(|event| event.kind == ImpulseKind::Error)
+        .expect("error should be recorded");
+
+    assert_eq!(error.source, Some(failing));
+    assert!(error.source_name.as_ref().unwrap().ends_with("FailingSoma"));
+    assert!(error.target.is_some());
+    assert!(error.message.is_some());
+
+    // writers other than memory cannot be read back
+    assert!(Recorder::new(Vec::<u8>::new()).events().is_err());
+}
+
+#[test]
+fn test_record_nested_errors() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let recorder = Recorder::memory();
+
+    let mut nested = Organelle::new(IdleSoma, handle.clone());
+    let failing = nested.add_soma(FailingSoma);
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+    organelle.set_recorder(recorder.clone());
+
+    let inner = organelle.add_soma(nested);
+
+    assert!(core.run(organelle.run(handle)).is_err());
+
+    // the error is recorded once, by the organelle the soma lives in
+    let events = recorder.events().unwrap();
+    let errors = errors(&events);
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].source, Some(failing));
+    assert_eq!(errors[0].target, Some(inner));
+}
+
+#[test]
+fn test_record_context_errors() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let recorder = Recorder::memory();
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+    organelle.set_recorder(recorder.clone());
+
+    let reporting = organelle.add_soma(ReportingSoma);
+
+    match core.run(organelle.run(handle)) {
+        Err(e) => match e.kind() {
+            &ErrorKind::SomaError(ref origin) => {
+                assert_eq!(origin.uuid, Some(reporting))
+            },
+            _ => panic!("unexpected error: {:#?}", e),
+        },
+        Ok(_) => panic!("ReportingSoma should fail the organelle"),
+    }
+
+    // errors reported through the context are attributed and recorded too
+    let events = recorder.events().unwrap();
+    let errors = errors(&events);
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].source, Some(reporting));
+    assert!(errors[0].message.as_ref().unwrap().contains("ReportingSoma"));
+}
+
+#[test]
+fn test_recorder_failure() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let recorder = Recorder::new(BrokenWriter);
+
+    let mut organelle = Organelle::new(StopperSoma, handle.clone());
+    organelle.set_recorder(recorder.clone());
+    organelle.add_soma(IdleSoma);
+
+    // the organelle runs on without its recorder, which keeps the reason
+    core.run(organelle.run(handle)).unwrap();
+
+    assert_eq!(recorder.failure(), Some("disk full".to_string()));
+}
@@ -0,0 +1,158 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate tokio_core;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use futures::prelude::*;
+use organelle::*;
+use tokio_core::reactor;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+enum Synapse {
+    Nothing,
+}
+
+impl organelle::Synapse for Synapse {
+    type Terminal = ();
+    type Dendrite = ();
+
+    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+        match self {
+            Synapse::Nothing => ((), ()),
+        }
+    }
+}
+
+struct StopperSoma;
+
+impl Soma for StopperSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(ctx) => {
+                ctx.stop();
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+struct FlushSoma {
+    flushed: Rc<Cell<bool>>,
+}
+
+impl Soma for FlushSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn on_stop(self) -> Result<()> {
+        self.flushed.set(true);
+
+        Ok(())
+    }
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// fails to flush when stopped
+struct FailingSoma;
+
+impl Soma for FailingSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn on_stop(self) -> Result<()> {
+        bail!("unable to flush")
+    }
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+#[test]
+fn test_stop_propagates() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let flushed = Rc::new(Cell::new(false));
+    let nested_flushed = Rc::new(Cell::new(false));
+
+    let mut organelle = Organelle::new(StopperSoma, handle.clone());
+
+    organelle.add_soma(FlushSoma {
+        flushed: flushed.clone(),
+    });
+
+    let mut nested = Organelle::new(
+        FlushSoma {
+            flushed: nested_flushed.clone(),
+        },
+        handle.clone(),
+    );
+    nested.add_soma(FlushSoma {
+        flushed: Rc::new(Cell::new(false)),
+    });
+
+    organelle.add_soma(nested);
+
+    core.run(organelle.run(handle)).unwrap();
+
+    assert!(flushed.get(), "soma should be flushed on stop");
+    assert!(
+        nested_flushed.get(),
+        "nested soma should be flushed on stop"
+    );
+}
+
+#[test]
+fn test_stop_errors() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let mut nested = Organelle::new(
+        FlushSoma {
+            flushed: Rc::new(Cell::new(false)),
+        },
+        handle.clone(),
+    );
+    let failing = nested.add_soma(FailingSoma);
+
+    let mut organelle = Organelle::new(StopperSoma, handle.clone());
+    organelle.add_soma(nested);
+
+    // somas that fail to stop are not lost while the organelle stops
+    let e = core.run(organelle.run(handle)).unwrap_err();
+
+    match e.kind() {
+        &ErrorKind::SomaError(ref origin) => {
+            assert_eq!(origin.uuid, Some(failing));
+            assert_eq!(origin.path.len(), 2);
+        },
+        _ => panic!("unexpected error: {:#?}", e),
+    }
+}
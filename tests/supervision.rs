@@ -0,0 +1,319 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+#[macro_use]
+extern crate error_chain;
+
+extern crate futures_await as futures;
+extern crate organelle;
+extern crate tokio_core;
+extern crate uuid;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time;
+
+use futures::prelude::*;
+use organelle::*;
+use tokio_core::reactor;
+use uuid::Uuid;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+enum Synapse {
+    Nothing,
+}
+
+impl organelle::Synapse for Synapse {
+    type Terminal = ();
+    type Dendrite = ();
+
+    fn synapse(self) -> (Self::Terminal, Self::Dendrite) {
+        match self {
+            Synapse::Nothing => ((), ()),
+        }
+    }
+}
+
+struct IdleSoma;
+
+impl Soma for IdleSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_) => Ok(self),
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// fails every time it starts until it has been started `succeed_on` times
+struct FlakySoma {
+    starts: Rc<Cell<usize>>,
+    succeed_on: usize,
+}
+
+impl Soma for FlakySoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(ctx) => {
+                self.starts.set(self.starts.get() + 1);
+
+                if self.starts.get() < self.succeed_on {
+                    bail!("flaky sensor failed")
+                }
+
+                ctx.stop();
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+type Log = Rc<RefCell<Vec<String>>>;
+
+/// logs when it is built and every synapse and start it sees
+struct TrackedSoma {
+    name: &'static str,
+    log: Log,
+}
+
+impl TrackedSoma {
+    fn factory(name: &'static str, log: &Log) -> impl Fn() -> Self {
+        let log = log.clone();
+
+        move || {
+            log.borrow_mut().push(format!("{} built", name));
+
+            TrackedSoma {
+                name: name,
+                log: log.clone(),
+            }
+        }
+    }
+}
+
+impl Soma for TrackedSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        let event = match imp {
+            Impulse::AddTerminal(peer, Synapse::Nothing, ()) => {
+                format!("terminal {}", peer)
+            },
+            Impulse::AddDendrite(peer, Synapse::Nothing, ()) => {
+                format!("dendrite {}", peer)
+            },
+            Impulse::Start(_) => "started".to_string(),
+            _ => bail!("unexpected impulse"),
+        };
+
+        self.log.borrow_mut().push(format!("{} {}", self.name, event));
+
+        Ok(self)
+    }
+}
+
+/// fails the first time it starts, then stops the organelle
+///
+/// both happen after a delay so the other somas have time to start.
+struct FailOnceSoma {
+    starts: Rc<Cell<usize>>,
+}
+
+impl Soma for FailOnceSoma {
+    type Synapse = Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(ctx) => {
+                self.starts.set(self.starts.get() + 1);
+
+                if self.starts.get() == 1 {
+                    let delay = reactor::Timeout::new(
+                        time::Duration::from_millis(50),
+                        &ctx.handle(),
+                    )?;
+
+                    await!(delay)?;
+
+                    bail!("failed on the first start")
+                }
+
+                let stop = ctx.clone();
+
+                ctx.spawn(
+                    reactor::Timeout::new(
+                        time::Duration::from_millis(50),
+                        &ctx.handle(),
+                    )?
+                        .map(move |_| stop.stop())
+                        .map_err(|e| e.into()),
+                );
+
+                Ok(self)
+            },
+            _ => bail!("unexpected impulse"),
+        }
+    }
+}
+
+/// run a failing soma between two connected tracked somas
+fn run_restart(strategy: RestartStrategy) -> (Uuid, Uuid, Log) {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let log = Rc::new(RefCell::new(vec![]));
+    let starts = Rc::new(Cell::new(0));
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+    organelle.set_supervisor(Supervisor::new(strategy));
+
+    let first = organelle
+        .add_supervised_soma(TrackedSoma::factory("first", &log));
+    organelle.add_supervised_soma(move || FailOnceSoma {
+        starts: starts.clone(),
+    });
+    let last = organelle
+        .add_supervised_soma(TrackedSoma::factory("last", &log));
+
+    organelle.connect(first, last, Synapse::Nothing).unwrap();
+
+    core.run(organelle.run(handle)).unwrap();
+
+    (first, last, log)
+}
+
+/// the events logged by one of the tracked somas
+fn events(log: &Log, name: &str) -> Vec<String> {
+    let prefix = format!("{} ", name);
+
+    log.borrow()
+        .iter()
+        .filter(|event| event.starts_with(&prefix))
+        .map(|event| event[prefix.len()..].to_string())
+        .collect()
+}
+
+#[test]
+fn test_one_for_one_restart() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let starts = Rc::new(Cell::new(0));
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+    organelle.set_supervisor(Supervisor::new(RestartStrategy::OneForOne));
+
+    let factory_starts = starts.clone();
+    organelle.add_supervised_soma(move || FlakySoma {
+        starts: factory_starts.clone(),
+        succeed_on: 3,
+    });
+
+    core.run(organelle.run(handle)).unwrap();
+
+    assert_eq!(starts.get(), 3);
+}
+
+#[test]
+fn test_restart_limit() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    let starts = Rc::new(Cell::new(0));
+
+    let mut organelle = Organelle::new(IdleSoma, handle.clone());
+
+    organelle.set_supervisor(
+        Supervisor::new(RestartStrategy::OneForOne)
+            .intensity(2, time::Duration::from_secs(60)),
+    );
+
+    let factory_starts = starts.clone();
+    organelle.add_supervised_soma(move || FlakySoma {
+        starts: factory_starts.clone(),
+        succeed_on: usize::max_value(),
+    });
+
+    if let Err(e) = core.run(organelle.run(handle)) {
+        match e.kind() {
+            &ErrorKind::RestartLimit(restarts) => assert_eq!(restarts, 2),
+            _ => panic!("unexpected error: {:#?}", e),
+        }
+    } else {
+        panic!("FlakySoma should exceed the restart limit")
+    }
+
+    assert_eq!(starts.get(), 3);
+}
+
+#[test]
+fn test_one_for_all_restart() {
+    let (first, last, log) = run_restart(RestartStrategy::OneForAll);
+
+    // both siblings are rebuilt and wired to each other again
+    assert_eq!(
+        events(&log, "first"),
+        vec![
+            "built".to_string(),
+            format!("terminal {}", last),
+            "started".to_string(),
+            "built".to_string(),
+            format!("terminal {}", last),
+            "started".to_string(),
+        ]
+    );
+    assert_eq!(
+        events(&log, "last"),
+        vec![
+            "built".to_string(),
+            format!("dendrite {}", first),
+            "started".to_string(),
+            "built".to_string(),
+            format!("dendrite {}", first),
+            "started".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn test_rest_for_one_restart() {
+    let (first, last, log) = run_restart(RestartStrategy::RestForOne);
+
+    // only the somas after the failed one are rebuilt, but the ones before
+    // are wired to the rebuilt somas again
+    assert_eq!(
+        events(&log, "first"),
+        vec![
+            "built".to_string(),
+            format!("terminal {}", last),
+            "started".to_string(),
+            format!("terminal {}", last),
+        ]
+    );
+    assert_eq!(
+        events(&log, "last"),
+        vec![
+            "built".to_string(),
+            format!("dendrite {}", first),
+            "started".to_string(),
+            "built".to_string(),
+            format!("dendrite {}", first),
+            "started".to_string(),
+        ]
+    );
+}
@@ -0,0 +1,176 @@
+#![feature(proc_macro, conservative_impl_trait, generators)]
+
+extern crate futures_await as futures;
+extern crate hyper;
+extern crate organelle;
+extern crate tokio_core;
+
+use std::collections::HashSet;
+use std::time;
+
+use futures::prelude::*;
+use organelle::*;
+use tokio_core::reactor;
+
+/// idles until the organelle stops
+struct IdleSoma;
+
+impl organelle::Soma for IdleSoma {
+    type Synapse = probe::Synapse;
+    type Error = Error;
+
+    #[async(boxed)]
+    fn update(self, imp: Impulse<Self::Synapse>) -> Result<Self> {
+        match imp {
+            Impulse::Start(_) => Ok(self),
+            _ => Err(Error::from("unexpected impulse")),
+        }
+    }
+}
+
+/// request a path from the visualizer, retrying until its server is up
+fn get(
+    core: &mut reactor::Core,
+    settings: &visualizer::Settings,
+    path: &str,
+) -> (hyper::StatusCode, hyper::Headers, String) {
+    let client = hyper::Client::new(&core.handle());
+
+    for _ in 0..50 {
+        let addr = match settings.address() {
+            Some(addr) => addr,
+            None => {
+                core.turn(Some(time::Duration::from_millis(20)));
+                continue;
+            },
+        };
+
+        let uri: hyper::Uri =
+            format!("http://{}{}", addr, path).parse().unwrap();
+        let request = client.get(uri).and_then(|rsp| {
+            let status = rsp.status();
+            let headers = rsp.headers().clone();
+
+            rsp.body().concat2().map(move |body| {
+                (status, headers, String::from_utf8(body.to_vec()).unwrap())
+            })
+        });
+
+        match core.run(request) {
+            Ok(response) => return response,
+            Err(_) => core.turn(Some(time::Duration::from_millis(20))),
+        }
+    }
+
+    panic!("visualizer did not respond at {:?}", settings.address())
+}
+
+/// the value of the first label of a sample
+fn first_label(line: &str) -> &str {
+    line.split('"').nth(1).unwrap()
+}
+
+#[test]
+fn test_prometheus_metrics() {
+    let mut core = reactor::Core::new().unwrap();
+    let handle = core.handle();
+
+    // let the system pick a free port
+    let settings = visualizer::Settings::default().port(0);
+    let visualizer =
+        visualizer::Soma::organelle(settings.clone(), handle.clone())
+            .unwrap();
+
+    let mut organelle = Organelle::new(visualizer, handle.clone());
+    organelle.add_soma(Organelle::new(IdleSoma, handle.clone()));
+    organelle.add_soma(Organelle::new(IdleSoma, handle.clone()));
+
+    handle.spawn(
+        organelle
+            .run(handle.clone())
+            .map_err(|e| panic!("organelle failed - {}", e)),
+    );
+
+    let (status, headers, body) = get(&mut core, &settings, "/metrics");
+
+    assert_eq!(status, hyper::StatusCode::Ok);
+    assert_eq!(
+        headers.get_raw("Content-Type").and_then(|raw| raw.one()),
+        Some(&b"text/plain; version=0.0.4"[..])
+    );
+
+    let samples = body
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>();
+
+    // every line is either a comment or a sample with a numeric value
+    for line in &samples {
+        let value = line.rsplit(' ').next().unwrap();
+
+        assert!(value.parse::<f64>().is_ok(), "bad sample: {}", line);
+        assert!(line.starts_with("organelle_"), "bad sample: {}", line);
+    }
+
+    // prometheus rejects series that share their name and labels
+    let series = samples
+        .iter()
+        .map(|line| line.rsplitn(2, ' ').nth(1).unwrap())
+        .collect::<HashSet<_>>();
+    assert_eq!(series.len(), samples.len());
+
+    assert!(body.contains("# TYPE organelle_somas gauge\n"));
+
+    let sizes = |name: &str| {
+        samples
+            .iter()
+            .filter(|line| {
+                line.starts_with("organelle_somas{")
+                    && line.contains(&format!(",name=\"{}\"}}", name))
+            })
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+
+    let root = sizes("Organelle<Organelle<Axon<Soma>>>");
+    let nested = sizes("Organelle<Axon<Soma>>");
+    let siblings = sizes("Organelle<IdleSoma>");
+
+    assert_eq!(root.len(), 1);
+    assert!(root[0].ends_with(" 3"));
+    assert_eq!(nested.len(), 1);
+    assert!(nested[0].ends_with(" 2"));
+
+    // organelles of the same type are told apart by their uuid path
+    assert_eq!(siblings.len(), 2);
+    assert_ne!(first_label(siblings[0]), first_label(siblings[1]));
+
+    let root = first_label(root[0]);
+    let nested = first_label(nested[0]);
+
+    assert!(nested.starts_with(&format!("{}/", root)));
+
+    // the visualizer and its probe soma both live in the nested organelle
+    let samples = |name: &str| {
+        body.lines()
+            .filter(|line| {
+                line.starts_with(&format!(
+                    "{}{{organelle=\"{}\",soma=\"Axon<Soma>\"",
+                    name, nested
+                ))
+            })
+            .count()
+    };
+
+    assert_eq!(samples("organelle_soma_impulses_total"), 2);
+    assert_eq!(samples("organelle_soma_updates_total"), 2);
+    assert_eq!(samples("organelle_soma_update_seconds"), 4);
+    assert_eq!(samples("organelle_soma_update_seconds_sum"), 2);
+    assert_eq!(samples("organelle_soma_update_seconds_count"), 2);
+    assert_eq!(samples("organelle_soma_mailbox_depth"), 2);
+    assert_eq!(samples("organelle_soma_errors_total"), 2);
+
+    let (status, _, _) = get(&mut core, &settings, "/api/metrics");
+
+    assert_eq!(status, hyper::StatusCode::Ok);
+}